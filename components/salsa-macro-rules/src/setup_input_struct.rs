@@ -41,6 +41,9 @@ macro_rules! setup_input_struct {
         // Names for the field durability methods on the builder (typically `foo_durability`)
         field_durability_ids: [$($field_durability_id:ident),*],
 
+        // Names for the methods returning the database key of each field (typically `foo_database_key`)
+        field_database_key_ids: [$($field_database_key_vis:vis $field_database_key_id:ident),*],
+
         // Number of fields
         num_fields: $N:literal,
 
@@ -302,20 +305,43 @@ macro_rules! setup_input_struct {
                     $field_setter_vis fn $field_setter_id<'db, $Db>(self, db: &'db mut $Db) -> impl salsa::Setter<FieldTy = $field_ty> + use<'db, $Db>
                     where
                         // FIXME(rust-lang/rust#65991): The `db` argument *should* have the type `dyn Database`
-                        $Db: ?Sized + $zalsa::Database,
+                        $Db: ?Sized + $zalsa::input::FieldWriter<$field_ty>,
                     {
-                        let zalsa = db.zalsa_mut();
-                        let (ingredient, revision) = $Configuration::ingredient_mut(zalsa);
                         $zalsa::input::SetterImpl::new(
-                            revision,
+                            db,
                             self,
                             $field_index,
-                            ingredient,
+                            $Configuration::ingredient_mut,
                             |fields, f| ::std::mem::replace(&mut fields.$field_index, f),
                         )
                     }
                 )*
 
+                $(
+                    /// Returns the key identifying this field in the dependency graph,
+                    /// e.g. to look up the queries that read it.
+                    $field_database_key_vis fn $field_database_key_id<$Db>(self, db: &$Db) -> $zalsa::DatabaseKeyIndex
+                    where
+                        // FIXME(rust-lang/rust#65991): The `db` argument *should* have the type `dyn Database`
+                        $Db: ?Sized + $zalsa::Database,
+                    {
+                        $Configuration::ingredient_(db.zalsa()).field_database_key_index(self, $field_index)
+                    }
+                )*
+
+                /// Deletes this input.
+                ///
+                /// Tracked functions that read its fields are re-executed in the next
+                /// revision, and reading a field of the deleted input panics.
+                pub fn delete<$Db>(self, db: &mut $Db)
+                where
+                    // FIXME(rust-lang/rust#65991): The `db` argument *should* have the type `dyn Database`
+                    $Db: ?Sized + $zalsa::Database,
+                {
+                    $zalsa_struct::IngredientImpl::<$Configuration>::delete_input(db.zalsa_mut(), self);
+                    $zalsa::after_input_write(db);
+                }
+
                 $zalsa::macro_if! { $is_singleton =>
                     pub fn try_get<$Db>(db: &$Db) -> Option<Self>
                     where
@@ -49,6 +49,9 @@ macro_rules! setup_tracked_fn {
         // If true, this is specifiable.
         is_specifiable: $is_specifiable:tt,
 
+        // If true, panics of the function are memoized as `Err(QueryPanicked)`.
+        catch_panics: $catch_panics:tt,
+
         // Equality check strategy function
         values_equal: {$($values_equal:tt)+},
 
@@ -335,7 +338,13 @@ macro_rules! setup_tracked_fn {
 
                     $($inner_fn)*
 
-                    $inner($db, $($input_id),*)
+                    $zalsa::macro_if! {
+                        if $catch_panics {
+                            $zalsa::catch_query_panic($db, || $inner($db, $($input_id),*))
+                        } else {
+                            $inner($db, $($input_id),*)
+                        }
+                    }
                 }
 
                 fn cycle_initial<$db_lt>(db: &$db_lt Self::DbView, id: ::salsa::Id, ($($input_id),*): ($($interned_input_ty),*)) -> Self::Output<$db_lt> {
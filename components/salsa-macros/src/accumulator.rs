@@ -47,6 +47,8 @@ impl AllowedOptions for Accumulator {
     const REVISIONS: bool = false;
     const HEAP_SIZE: bool = false;
     const SELF_TY: bool = false;
+
+    const CATCH_PANICS: bool = false;
     // TODO: Support serializing accumulators.
     const PERSIST: AllowedPersistOptions = AllowedPersistOptions::Invalid;
 }
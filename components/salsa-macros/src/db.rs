@@ -101,6 +101,11 @@ impl DbMacro {
                     fn storage_mut(&mut self) -> &mut #zalsa::Storage<Self> {
                         &mut self.#storage
                     }
+
+                    fn clone_fn() -> Option<fn(&Self) -> Self> {
+                        use #zalsa::input::CloneFallback as _;
+                        #zalsa::input::CloneDispatch::<Self>::clone_fn()
+                    }
                 }
             };
         })
@@ -69,6 +69,8 @@ impl AllowedOptions for InputStruct {
 
     const SELF_TY: bool = false;
 
+    const CATCH_PANICS: bool = false;
+
     const PERSIST: AllowedPersistOptions = AllowedPersistOptions::AllowedValue;
 }
 
@@ -111,6 +113,7 @@ impl Macro {
         let field_options = salsa_struct.field_options();
         let field_tys = salsa_struct.field_tys();
         let field_durability_ids = salsa_struct.field_durability_ids();
+        let field_database_key_ids = salsa_struct.field_database_key_ids();
         let field_attrs = salsa_struct.field_attrs();
         let is_singleton = self.args.singleton.is_some();
         let generate_debug_impl = salsa_struct.generate_debug_impl();
@@ -144,6 +147,7 @@ impl Macro {
                     field_attrs: [#([#(#field_attrs),*]),*],
                     required_fields: [#(#required_fields),*],
                     field_durability_ids: [#(#field_durability_ids),*],
+                    field_database_key_ids: [#(#field_vis #field_database_key_ids),*],
                     num_fields: #num_fields,
                     is_singleton: #is_singleton,
                     generate_debug_impl: #generate_debug_impl,
@@ -69,6 +69,8 @@ impl AllowedOptions for InternedStruct {
 
     const SELF_TY: bool = false;
 
+    const CATCH_PANICS: bool = false;
+
     const PERSIST: AllowedPersistOptions = AllowedPersistOptions::AllowedValue;
 }
 
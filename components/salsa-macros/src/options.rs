@@ -116,6 +116,12 @@ pub(crate) struct Options<A: AllowedOptions> {
     /// functions. This is merely used to refine the query name.
     pub self_ty: Option<syn::Type>,
 
+    /// The `catch_panics` option is used to memoize a panic of a tracked function
+    /// as a `QueryPanicked` error instead of unwinding into its callers.
+    ///
+    /// If this is `Some`, the value is the `catch_panics` identifier.
+    pub catch_panics: Option<syn::Ident>,
+
     /// Remember the `A` parameter, which plays no role after parsing.
     phantom: PhantomData<A>,
 }
@@ -157,6 +163,7 @@ impl<A: AllowedOptions> Default for Options<A> {
             revisions: Default::default(),
             heap_size_fn: Default::default(),
             self_ty: Default::default(),
+            catch_panics: Default::default(),
             persist: Default::default(),
         }
     }
@@ -182,6 +189,7 @@ pub(crate) trait AllowedOptions {
     const REVISIONS: bool;
     const HEAP_SIZE: bool;
     const SELF_TY: bool;
+    const CATCH_PANICS: bool;
     const PERSIST: AllowedPersistOptions;
 }
 
@@ -534,6 +542,20 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
                         "`self_ty` option not allowed here",
                     ));
                 }
+            } else if ident == "catch_panics" {
+                if A::CATCH_PANICS {
+                    if let Some(old) = options.catch_panics.replace(ident) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `catch_panics` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`catch_panics` option not allowed here",
+                    ));
+                }
             } else {
                 return Err(syn::Error::new(
                     ident.span(),
@@ -572,6 +594,7 @@ impl<A: AllowedOptions> quote::ToTokens for Options<A> {
             revisions,
             heap_size_fn,
             self_ty,
+            catch_panics,
             persist,
             phantom: _,
         } = self;
@@ -629,6 +652,9 @@ impl<A: AllowedOptions> quote::ToTokens for Options<A> {
         if let Some(self_ty) = self_ty {
             tokens.extend(quote::quote! { self_ty = #self_ty, });
         }
+        if catch_panics.is_some() {
+            tokens.extend(quote::quote! { catch_panics, });
+        }
         if let Some(persist) = persist {
             let mut args = proc_macro2::TokenStream::new();
 
@@ -354,6 +354,13 @@ where
             .collect()
     }
 
+    pub(crate) fn field_database_key_ids(&self) -> Vec<syn::Ident> {
+        self.fields
+            .iter()
+            .map(|f| quote::format_ident!("{}_database_key", f.field.ident.as_ref().unwrap()))
+            .collect()
+    }
+
     pub(crate) fn field_tys(&self) -> Vec<&syn::Type> {
         self.fields.iter().map(|f| &f.field.ty).collect()
     }
@@ -62,6 +62,8 @@ impl AllowedOptions for TrackedFn {
 
     const SELF_TY: bool = true;
 
+    const CATCH_PANICS: bool = true;
+
     const PERSIST: AllowedPersistOptions = AllowedPersistOptions::AllowedIdent;
 }
 
@@ -98,7 +100,11 @@ impl Macro {
             );
             ty
         });
-        let output_ty = self.output_ty(&db_lt, &item)?;
+        let mut output_ty = self.output_ty(&db_lt, &item)?;
+        let catch_panics = self.catch_panics()?;
+        if catch_panics {
+            output_ty = parse_quote!(::core::result::Result<#output_ty, ::salsa::QueryPanicked>);
+        }
         let (cycle_recovery_fn, cycle_recovery_initial, cycle_recovery_strategy) =
             self.cycle_recovery()?;
         let is_specifiable = self.args.specify.is_some();
@@ -226,6 +232,7 @@ impl Macro {
                 cycle_recovery_initial: #cycle_recovery_initial,
                 cycle_recovery_strategy: #cycle_recovery_strategy,
                 is_specifiable: #is_specifiable,
+                catch_panics: #catch_panics,
                 values_equal: {#eq},
                 needs_interner: #needs_interner,
                 heap_size_fn: #(#heap_size_fn)*,
@@ -302,6 +309,28 @@ impl Macro {
         }
     }
 
+    fn catch_panics(&self) -> syn::Result<bool> {
+        let Some(token) = &self.args.catch_panics else {
+            return Ok(false);
+        };
+        if self.args.cycle_fn.is_some()
+            || self.args.cycle_initial.is_some()
+            || self.args.cycle_result.is_some()
+        {
+            return Err(syn::Error::new_spanned(
+                token,
+                "the `catch_panics` option cannot be used with cycle recovery",
+            ));
+        }
+        if self.args.persist.is_some() {
+            return Err(syn::Error::new_spanned(
+                token,
+                "the `catch_panics` and `persist` options cannot be used together",
+            ));
+        }
+        Ok(true)
+    }
+
     fn input_ids(&self, item: &ItemFn) -> Vec<syn::Ident> {
         fn_util::input_ids(&self.hygiene, &item.sig, 1)
     }
@@ -417,6 +417,16 @@ impl Macro {
         args: &FnArgs,
         db_lt: Option<&syn::Lifetime>,
     ) -> syn::Result<()> {
+        if args.catch_panics.is_some() {
+            match &mut sig.output {
+                syn::ReturnType::Type(_, t) => {
+                    **t = parse_quote!(::core::result::Result<#t, ::salsa::QueryPanicked>)
+                }
+                syn::ReturnType::Default => {
+                    sig.output = parse_quote!(-> ::core::result::Result<(), ::salsa::QueryPanicked>)
+                }
+            }
+        }
         if let Some(returns) = &args.returns {
             if let syn::ReturnType::Type(_, t) = &mut sig.output {
                 if returns == "copy" || returns == "clone" {
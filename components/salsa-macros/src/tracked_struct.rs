@@ -65,6 +65,8 @@ impl AllowedOptions for TrackedStruct {
 
     const SELF_TY: bool = false;
 
+    const CATCH_PANICS: bool = false;
+
     const PERSIST: AllowedPersistOptions = AllowedPersistOptions::AllowedValue;
 }
 
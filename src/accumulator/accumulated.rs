@@ -1,5 +1,6 @@
 use std::any::Any;
 use std::fmt::Debug;
+use std::panic::UnwindSafe;
 
 use crate::accumulator::Accumulator;
 
@@ -8,9 +9,13 @@ pub(crate) struct Accumulated<A: Accumulator> {
     values: Vec<A>,
 }
 
-pub(crate) trait AnyAccumulated: Any + Send + Sync {
+pub(crate) trait AnyAccumulated: Any + Send + Sync + UnwindSafe {
     fn as_dyn_any(&self) -> &dyn Any;
     fn as_dyn_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Appends the values of `other`, which must accumulate the same type.
+    #[cfg(feature = "rayon")]
+    fn append(&mut self, other: Box<dyn AnyAccumulated>);
 }
 
 impl<A: Accumulator> Accumulated<A> {
@@ -42,6 +47,12 @@ where
     fn as_dyn_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    #[cfg(feature = "rayon")]
+    fn append(&mut self, mut other: Box<dyn AnyAccumulated>) {
+        let other = other.as_dyn_any_mut().downcast_mut::<Self>().unwrap();
+        self.values.append(&mut other.values);
+    }
 }
 
 impl dyn AnyAccumulated {
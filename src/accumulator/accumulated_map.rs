@@ -43,6 +43,21 @@ impl AccumulatedMap {
             .extend_with_accumulated(output);
     }
 
+    /// Appends the values accumulated in `other` after the ones accumulated in `self`.
+    #[cfg(feature = "rayon")]
+    pub fn append(&mut self, other: AccumulatedMap) {
+        for (index, accumulated) in other.map {
+            match self.map.entry(index) {
+                hashbrown::hash_map::Entry::Occupied(mut entry) => {
+                    entry.get_mut().append(accumulated)
+                }
+                hashbrown::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(accumulated);
+                }
+            }
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.map.is_empty()
     }
@@ -10,8 +10,10 @@ use crate::hash::FxIndexSet;
 use crate::key::DatabaseKeyIndex;
 use crate::runtime::Stamp;
 use crate::sync::atomic::AtomicBool;
-use crate::tracked_struct::{Disambiguator, DisambiguatorMap, IdentityHash, IdentityMap};
-use crate::zalsa_local::{OriginAndExtra, QueryEdge, QueryRevisions, QueryRevisionsExtra};
+use crate::tracked_struct::{DisambiguatorMap, IdentityHash, IdentityMap};
+use crate::zalsa_local::{
+    OriginAndExtra, QueryEdge, QueryEdgeKind, QueryRevisions, QueryRevisionsExtra,
+};
 use crate::{
     Id,
     cycle::{CycleHeads, IterationStamp},
@@ -65,6 +67,50 @@ pub(crate) struct ActiveQuery {
 
     /// Provisional cycle results that this query depends on.
     cycle_heads: CycleHeads,
+
+    /// The query backtrace at the point a panic occurred in a query this query called,
+    /// captured when the first query unwound.
+    panic_backtrace: Option<Backtrace>,
+
+    /// Non-zero if this is a branch of a forked query (see [`crate::par_map`]).
+    /// Mixed into the identity of tracked structs created by the branch, so that
+    /// they don't collide with the ones created by other branches.
+    branch_salt: u64,
+
+    /// The number of times this query forked so far.
+    forks: u32,
+}
+
+/// A query that forked into branches running on other database handles.
+#[cfg(feature = "rayon")]
+#[derive(Debug)]
+pub(crate) struct ForkedQuery {
+    pub(crate) database_key_index: DatabaseKeyIndex,
+    salt: u64,
+    fork: u32,
+    tracked_struct_ids: Vec<(Identity, Id)>,
+
+    /// The queries claimed by the forking thread and the threads that forked it, which
+    /// can't complete before the branches do.
+    pub(crate) claimed: std::sync::Arc<[DatabaseKeyIndex]>,
+}
+
+/// The dependencies a branch of a forked query collected, to be merged into the
+/// query once the branch completed.
+#[cfg(feature = "rayon")]
+#[derive(Debug)]
+pub(crate) struct QueryBranch {
+    durability: Durability,
+    changed_at: Revision,
+    input_outputs: FxIndexSet<QueryEdge>,
+    untracked_read: bool,
+    disambiguator_map: DisambiguatorMap,
+    tracked_struct_ids: IdentityMap,
+    #[cfg(feature = "accumulator")]
+    accumulated: AccumulatedMap,
+    #[cfg(feature = "accumulator")]
+    accumulated_inputs: InputAccumulatedValues,
+    cycle_heads: CycleHeads,
 }
 
 impl ActiveQuery {
@@ -151,9 +197,92 @@ impl ActiveQuery {
         self.input_outputs.insert(QueryEdge::output(key));
     }
 
-    /// True if the given key was output by this query.
-    pub(super) fn disambiguate(&mut self, key: IdentityHash) -> Disambiguator {
-        self.disambiguator_map.disambiguate(key)
+    pub(super) fn disambiguate(&mut self, key: IdentityHash) -> Identity {
+        let key = match self.branch_salt {
+            0 => key,
+            salt => key.salted(salt),
+        };
+        let disambiguator = self.disambiguator_map.disambiguate(key);
+        Identity::new(key, disambiguator)
+    }
+
+    /// Forks this query into branches, see [`ActiveQuery::seed_branch`].
+    #[cfg(feature = "rayon")]
+    pub(super) fn fork(&mut self, claimed: std::sync::Arc<[DatabaseKeyIndex]>) -> ForkedQuery {
+        let fork = ForkedQuery {
+            database_key_index: self.database_key_index,
+            salt: self.branch_salt,
+            fork: self.forks,
+            tracked_struct_ids: self.tracked_struct_ids.inactive(),
+            claimed,
+        };
+        self.forks += 1;
+        fork
+    }
+
+    /// The queries whose claims the thread running this query holds: the query itself
+    /// and the heads of the cycles it iterates.
+    #[cfg(feature = "rayon")]
+    pub(super) fn claimed_queries(&self) -> impl Iterator<Item = DatabaseKeyIndex> + '_ {
+        std::iter::once(self.database_key_index)
+            .chain(self.cycle_heads.iter().map(|head| head.database_key_index))
+    }
+
+    /// Prepares this query to run branch `index` of `fork`.
+    #[cfg(feature = "rayon")]
+    pub(super) fn seed_branch(&mut self, fork: &ForkedQuery, index: usize) {
+        // Zero marks queries that aren't branches.
+        self.branch_salt = crate::hash::hash(&(fork.salt, fork.fork, index)).max(1);
+        // Branches may re-create the tracked structs of the previous execution.
+        self.tracked_struct_ids.seed(&fork.tracked_struct_ids);
+    }
+
+    /// Takes the dependencies collected by this branch of a forked query.
+    #[cfg(feature = "rayon")]
+    pub(super) fn take_branch(&mut self) -> QueryBranch {
+        QueryBranch {
+            durability: self.durability,
+            changed_at: self.changed_at,
+            input_outputs: mem::take(&mut self.input_outputs),
+            untracked_read: self.untracked_read,
+            disambiguator_map: mem::take(&mut self.disambiguator_map),
+            tracked_struct_ids: mem::take(&mut self.tracked_struct_ids),
+            #[cfg(feature = "accumulator")]
+            accumulated: mem::take(&mut self.accumulated),
+            #[cfg(feature = "accumulator")]
+            accumulated_inputs: self.accumulated_inputs,
+            cycle_heads: mem::take(&mut self.cycle_heads),
+        }
+    }
+
+    /// Adds the dependencies collected by a branch of a fork of this query.
+    #[cfg(feature = "rayon")]
+    pub(super) fn merge_branch(&mut self, branch: QueryBranch) {
+        let QueryBranch {
+            durability,
+            changed_at,
+            input_outputs,
+            untracked_read,
+            disambiguator_map,
+            tracked_struct_ids,
+            #[cfg(feature = "accumulator")]
+            accumulated,
+            #[cfg(feature = "accumulator")]
+            accumulated_inputs,
+            cycle_heads,
+        } = branch;
+        self.durability = self.durability.min(durability);
+        self.changed_at = self.changed_at.max(changed_at);
+        self.untracked_read |= untracked_read;
+        self.input_outputs.extend(input_outputs);
+        self.disambiguator_map.merge(disambiguator_map);
+        self.tracked_struct_ids.merge_active(tracked_struct_ids);
+        #[cfg(feature = "accumulator")]
+        {
+            self.accumulated.append(accumulated);
+            self.accumulated_inputs |= accumulated_inputs;
+        }
+        self.cycle_heads.extend(&cycle_heads);
     }
 
     pub(super) fn stamp(&self) -> Stamp {
@@ -170,6 +299,10 @@ impl ActiveQuery {
     pub(crate) fn tracked_struct_ids_mut(&mut self) -> &mut IdentityMap {
         &mut self.tracked_struct_ids
     }
+
+    pub(crate) fn take_panic_backtrace(&mut self) -> Option<Backtrace> {
+        self.panic_backtrace.take()
+    }
 }
 
 impl ActiveQuery {
@@ -187,6 +320,9 @@ impl ActiveQuery {
             accumulated: Default::default(),
             #[cfg(feature = "accumulator")]
             accumulated_inputs: Default::default(),
+            panic_backtrace: None,
+            branch_salt: 0,
+            forks: 0,
         }
     }
 
@@ -208,6 +344,9 @@ impl ActiveQuery {
             ref mut accumulated,
             #[cfg(feature = "accumulator")]
             accumulated_inputs,
+            panic_backtrace: _,
+            branch_salt: _,
+            forks: _,
         } = self;
 
         disambiguator_map.clear();
@@ -251,11 +390,17 @@ impl ActiveQuery {
             accumulated,
             #[cfg(feature = "accumulator")]
                 accumulated_inputs: _,
+            panic_backtrace,
+            branch_salt,
+            forks,
         } = self;
         input_outputs.clear();
         disambiguator_map.clear();
         tracked_struct_ids.clear();
         *cycle_heads = Default::default();
+        *panic_backtrace = None;
+        *branch_salt = 0;
+        *forks = 0;
         #[cfg(feature = "accumulator")]
         accumulated.clear();
     }
@@ -274,11 +419,17 @@ impl ActiveQuery {
             accumulated,
             #[cfg(feature = "accumulator")]
             accumulated_inputs,
+            panic_backtrace,
+            branch_salt,
+            forks,
         } = self;
         *database_key_index = new_database_key_index;
         *durability = Durability::MAX;
         *changed_at = Revision::start();
         *untracked_read = false;
+        *panic_backtrace = None;
+        *branch_salt = 0;
+        *forks = 0;
         debug_assert!(
             input_outputs.is_empty(),
             "`ActiveQuery::clear` or `ActiveQuery::into_revisions` should've been called"
@@ -410,6 +561,60 @@ impl QueryStack {
         .clear()
     }
 
+    /// Pops a query that is unwinding because of a panic that a query memoizes (see `catch_panics`).
+    ///
+    /// The inputs the query read so far are added to the calling query, so that the memoized
+    /// panic depends on them, and the query backtrace at the panic is handed to it.
+    pub(crate) fn pop_unwinding(
+        &mut self,
+        key: DatabaseKeyIndex,
+        #[cfg(debug_assertions)] push_len: usize,
+    ) {
+        let backtrace = match self.stack[self.len - 1].panic_backtrace.take() {
+            Some(backtrace) => backtrace,
+            None => Backtrace::from_queries(self),
+        };
+        self.pop_active_query(
+            key,
+            #[cfg(debug_assertions)]
+            push_len,
+        );
+
+        let (queries, unused) = self.stack.split_at_mut(self.len);
+        let popped = &mut unused[0];
+        if let Some(caller) = queries.last_mut() {
+            caller.durability = caller.durability.min(popped.durability);
+            caller.changed_at = caller.changed_at.max(popped.changed_at);
+            caller.untracked_read |= popped.untracked_read;
+            caller.input_outputs.extend(
+                popped
+                    .input_outputs
+                    .iter()
+                    .filter(|edge| matches!(edge.kind(), QueryEdgeKind::Input))
+                    .copied(),
+            );
+            caller.panic_backtrace = Some(backtrace);
+        }
+        popped.clear();
+    }
+
+    /// Pops the frame of a branch of a forked query, returning the dependencies it collected.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn pop_branch(
+        &mut self,
+        key: DatabaseKeyIndex,
+        #[cfg(debug_assertions)] push_len: usize,
+    ) -> QueryBranch {
+        let popped = self.pop_active_query(
+            key,
+            #[cfg(debug_assertions)]
+            push_len,
+        );
+        let branch = popped.take_branch();
+        popped.clear();
+        branch
+    }
+
     fn pop_active_query(
         &mut self,
         key: DatabaseKeyIndex,
@@ -496,22 +701,25 @@ pub struct Backtrace(Box<[CapturedQuery]>);
 impl Backtrace {
     pub fn capture() -> Option<Self> {
         crate::with_attached_database(|db| {
-            db.zalsa_local().try_with_query_stack(|stack| {
-                Backtrace(
-                    stack
-                        .iter()
-                        .rev()
-                        .map(|query| CapturedQuery {
-                            database_key_index: query.database_key_index,
-                            durability: query.durability,
-                            changed_at: query.changed_at,
-                            cycle_heads: query.cycle_heads.clone(),
-                        })
-                        .collect(),
-                )
-            })
+            db.zalsa_local()
+                .try_with_query_stack(|stack| Self::from_queries(stack))
         })?
     }
+
+    pub(crate) fn from_queries(queries: &[ActiveQuery]) -> Self {
+        Backtrace(
+            queries
+                .iter()
+                .rev()
+                .map(|query| CapturedQuery {
+                    database_key_index: query.database_key_index,
+                    durability: query.durability,
+                    changed_at: query.changed_at,
+                    cycle_heads: query.cycle_heads.clone(),
+                })
+                .collect(),
+        )
+    }
 }
 
 impl fmt::Debug for Backtrace {
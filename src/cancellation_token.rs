@@ -0,0 +1,334 @@
+use std::panic::RefUnwindSafe;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, Weak};
+use std::time::{Duration, Instant};
+
+use crate::Cancelled;
+use crate::sync::Mutex;
+
+/// A cancellation token that can be used to cancel a query computation for a specific local `Database`.
+///
+/// Besides cancelling explicitly, a token can be cancelled with a reason, cancel itself
+/// once a deadline passes, or be linked to a parent token whose cancellation it inherits.
+/// This allows cancelling all database handles used by a request through a single token.
+#[derive(Default, Clone, Debug)]
+pub struct CancellationToken(Arc<TokenState>);
+
+#[derive(Debug)]
+struct TokenState {
+    flags: AtomicU8,
+
+    /// The earliest deadline of this token and its ancestors, see [`deadline_nanos`],
+    /// or [`NO_DEADLINE`].
+    earliest_deadline: AtomicU64,
+
+    inner: Mutex<TokenInner>,
+}
+
+impl Default for TokenState {
+    fn default() -> Self {
+        Self {
+            flags: AtomicU8::new(0),
+            earliest_deadline: AtomicU64::new(NO_DEADLINE),
+            inner: Mutex::default(),
+        }
+    }
+}
+
+// No user code runs while `inner` is locked, so a panic can't leave it in a broken state.
+impl RefUnwindSafe for TokenState {}
+
+#[derive(Default, Debug)]
+struct TokenInner {
+    /// Why the token was cancelled, `None` if it isn't.
+    cause: Option<CancellationCause>,
+    deadline: Option<Instant>,
+    parent: Option<Arc<TokenState>>,
+    children: Vec<Weak<TokenState>>,
+}
+
+#[derive(Clone, Debug)]
+enum CancellationCause {
+    Local,
+    Reason(String),
+    DeadlineExceeded,
+}
+
+const NO_DEADLINE: u64 = u64::MAX;
+
+/// Serializes the changes to the parents and deadlines of tokens, so that cycles are
+/// detected reliably and the earliest deadlines of the descendants are updated in order.
+static UPDATE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Returns `instant` in nanoseconds since the first deadline was computed,
+/// so that deadlines fit in an atomic.
+fn deadline_nanos(instant: Instant) -> u64 {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    let epoch = *EPOCH.get_or_init(Instant::now);
+    let nanos = instant.saturating_duration_since(epoch).as_nanos();
+    u64::try_from(nanos).unwrap_or(NO_DEADLINE - 1)
+}
+
+impl CancellationToken {
+    const CANCELLED_MASK: u8 = 0b01;
+    const DISABLED_MASK: u8 = 0b10;
+
+    /// Creates a new token that is not linked to any database.
+    ///
+    /// Link database handles to it with [`CancellationToken::set_parent`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new token that is cancelled when this token is cancelled,
+    /// and that inherits this token's deadline.
+    pub fn child_token(&self) -> Self {
+        let child = Self::new();
+        child.set_parent(self);
+        child
+    }
+
+    /// Links this token to `parent`: cancelling `parent` cancels this token with the same cause
+    /// and the deadline of `parent` also applies to this token.
+    ///
+    /// If `parent` is already cancelled, this token is cancelled immediately.
+    ///
+    /// # Panics
+    ///
+    /// If this token already has a parent, or if it is `parent` or one of its ancestors.
+    pub fn set_parent(&self, parent: &CancellationToken) {
+        let _update = UPDATE_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+
+        let mut ancestor = Some(parent.0.clone());
+        while let Some(token) = ancestor {
+            assert!(
+                !Arc::ptr_eq(&token, &self.0),
+                "cancellation token can't be its own ancestor"
+            );
+            ancestor = token.inner.lock().parent.clone();
+        }
+
+        {
+            let mut inner = self.0.inner.lock();
+            assert!(
+                inner.parent.is_none(),
+                "cancellation token already has a parent"
+            );
+            inner.parent = Some(parent.0.clone());
+        }
+        let cause = {
+            let mut parent_inner = parent.0.inner.lock();
+            parent_inner
+                .children
+                .retain(|child| child.strong_count() > 0);
+            parent_inner.children.push(Arc::downgrade(&self.0));
+            parent_inner.cause.clone()
+        };
+        self.0.update_earliest_deadline();
+        if let Some(cause) = cause {
+            self.0.cancel(cause);
+        }
+    }
+
+    /// Inform the database to cancel the current query computation.
+    pub fn cancel(&self) {
+        self.0.cancel(CancellationCause::Local);
+    }
+
+    /// Inform the database to cancel the current query computation, unwinding
+    /// with [`Cancelled::Reason`] carrying `reason`.
+    pub fn cancel_with_reason(&self, reason: impl Into<String>) {
+        self.0.cancel(CancellationCause::Reason(reason.into()));
+    }
+
+    /// Cancels the query computation once `deadline` has passed, unwinding with
+    /// [`Cancelled::DeadlineExceeded`].
+    ///
+    /// The deadline is checked whenever salsa checks for cancellation. It is consumed
+    /// once it cancels the token.
+    pub fn set_deadline(&self, deadline: Instant) {
+        let _update = UPDATE_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        self.0.inner.lock().deadline = Some(deadline);
+        self.0.update_earliest_deadline();
+    }
+
+    /// Cancels the query computation once `timeout` has elapsed from now.
+    pub fn cancel_after(&self, timeout: Duration) {
+        self.set_deadline(Instant::now() + timeout);
+    }
+
+    /// Removes the deadline of this token. Deadlines of parent tokens still apply.
+    pub fn clear_deadline(&self) {
+        let _update = UPDATE_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        self.0.inner.lock().deadline = None;
+        self.0.update_earliest_deadline();
+    }
+
+    /// Returns the deadline of this token, not taking parent tokens into account.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.0.inner.lock().deadline
+    }
+
+    /// Check if the query computation has been requested to be cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        let flags = self.0.flags.load(Ordering::Relaxed);
+        flags & Self::CANCELLED_MASK != 0 || self.0.deadline_exceeded()
+    }
+
+    #[inline]
+    pub(crate) fn set_cancellation_disabled(&self, disabled: bool) -> bool {
+        let previous_disabled_bit = if disabled {
+            self.0
+                .flags
+                .fetch_or(Self::DISABLED_MASK, Ordering::Relaxed)
+        } else {
+            self.0
+                .flags
+                .fetch_and(!Self::DISABLED_MASK, Ordering::Relaxed)
+        };
+        previous_disabled_bit & Self::DISABLED_MASK != 0
+    }
+
+    #[inline]
+    pub(crate) fn should_trigger_local_cancellation(&self) -> bool {
+        let flags = self.0.flags.load(Ordering::Relaxed);
+        if flags & Self::DISABLED_MASK != 0 {
+            return false;
+        }
+        flags & Self::CANCELLED_MASK != 0 || self.0.deadline_exceeded()
+    }
+
+    /// Returns the panic payload to unwind with for this token's cancellation.
+    #[cold]
+    pub(crate) fn to_cancelled(&self) -> Cancelled {
+        match &self.0.inner.lock().cause {
+            None | Some(CancellationCause::Local) => Cancelled::Local,
+            Some(CancellationCause::Reason(reason)) => Cancelled::Reason(reason.clone()),
+            Some(CancellationCause::DeadlineExceeded) => Cancelled::DeadlineExceeded,
+        }
+    }
+
+    /// Resets the cancellation of this token, unless a parent token is still cancelled.
+    pub(crate) fn reset(&self) {
+        let flags = self.0.flags.load(Ordering::Relaxed);
+        if flags & (Self::CANCELLED_MASK | Self::DISABLED_MASK) == 0 {
+            return;
+        }
+        let mut inner = self.0.inner.lock();
+        let parent_cause = inner
+            .parent
+            .as_ref()
+            .and_then(|parent| parent.inner.lock().cause.clone());
+        let cancelled = parent_cause.is_some();
+        inner.cause = parent_cause;
+        self.0.flags.fetch_and(
+            !(Self::CANCELLED_MASK | Self::DISABLED_MASK),
+            Ordering::Relaxed,
+        );
+        if cancelled {
+            self.0
+                .flags
+                .fetch_or(Self::CANCELLED_MASK, Ordering::Relaxed);
+        }
+    }
+}
+
+impl TokenState {
+    fn cancel(&self, cause: CancellationCause) {
+        let children = {
+            let mut inner = self.inner.lock();
+            if inner.cause.is_some() {
+                return;
+            }
+            if let CancellationCause::DeadlineExceeded = cause {
+                inner.deadline = None;
+            }
+            inner.cause = Some(cause.clone());
+            self.flags
+                .fetch_or(CancellationToken::CANCELLED_MASK, Ordering::Relaxed);
+            inner.children.retain(|child| child.strong_count() > 0);
+            inner
+                .children
+                .iter()
+                .filter_map(Weak::upgrade)
+                .collect::<Vec<_>>()
+        };
+
+        for child in children {
+            child.cancel(cause.clone());
+        }
+    }
+
+    /// Recomputes the earliest deadline of this token and its ancestors, and those
+    /// of its descendants. Must be called with `UPDATE_LOCK` held.
+    fn update_earliest_deadline(&self) {
+        let children = {
+            let mut inner = self.inner.lock();
+            let own = inner.deadline.map_or(NO_DEADLINE, deadline_nanos);
+            let inherited = inner.parent.as_ref().map_or(NO_DEADLINE, |parent| {
+                parent.earliest_deadline.load(Ordering::Relaxed)
+            });
+            self.earliest_deadline
+                .store(own.min(inherited), Ordering::Relaxed);
+
+            inner.children.retain(|child| child.strong_count() > 0);
+            inner
+                .children
+                .iter()
+                .filter_map(Weak::upgrade)
+                .collect::<Vec<_>>()
+        };
+        for child in children {
+            child.update_earliest_deadline();
+        }
+    }
+
+    /// Returns the token with the earliest deadline among this token and its ancestors.
+    fn earliest_deadline(self: &Arc<Self>) -> Option<(Instant, Arc<TokenState>)> {
+        let mut earliest: Option<(Instant, Arc<TokenState>)> = None;
+        let mut token = Some(self.clone());
+        while let Some(current) = token {
+            let inner = current.inner.lock();
+            let parent = inner.parent.clone();
+            if let Some(deadline) = inner.deadline {
+                if earliest
+                    .as_ref()
+                    .is_none_or(|(earliest, _)| deadline < *earliest)
+                {
+                    drop(inner);
+                    earliest = Some((deadline, current));
+                }
+            }
+            token = parent;
+        }
+        earliest
+    }
+
+    /// Returns `true` if the earliest deadline of this token and its ancestors passed.
+    #[inline]
+    fn deadline_exceeded(self: &Arc<Self>) -> bool {
+        let earliest_deadline = self.earliest_deadline.load(Ordering::Relaxed);
+        earliest_deadline != NO_DEADLINE && self.deadline_passed(earliest_deadline)
+    }
+
+    /// Cancels the token whose deadline passed, if any, along with its descendants.
+    #[cold]
+    #[inline(never)]
+    fn deadline_passed(self: &Arc<Self>, earliest_deadline: u64) -> bool {
+        if deadline_nanos(Instant::now()) < earliest_deadline {
+            return false;
+        }
+
+        let _update = UPDATE_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        match self.earliest_deadline() {
+            Some((deadline, token)) if deadline <= Instant::now() => {
+                token.cancel(CancellationCause::DeadlineExceeded);
+                // The deadline is consumed by cancelling the token.
+                token.update_earliest_deadline();
+                true
+            }
+            // The deadline was changed concurrently.
+            _ => false,
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use std::fmt;
 use std::panic::{self, UnwindSafe};
 
+use crate::sync::Arc;
+use crate::{Backtrace, DatabaseKeyIndex};
+
 /// A panic payload indicating that execution of a salsa query was cancelled.
 ///
 /// This can occur for a few reasons:
@@ -17,7 +20,19 @@ pub enum Cancelled {
     PendingWrite,
 
     /// The query was blocked on another thread, and that thread panicked.
-    PropagatedPanic,
+    ///
+    /// Carries the original panic, unless the panic happened outside of a query function.
+    PropagatedPanic(Option<QueryPanicInfo>),
+
+    /// The query was cancelled with [`CancellationToken::cancel_with_reason`].
+    ///
+    /// [`CancellationToken::cancel_with_reason`]: crate::CancellationToken::cancel_with_reason
+    Reason(String),
+
+    /// The deadline of the database's [`CancellationToken`] passed.
+    ///
+    /// [`CancellationToken`]: crate::CancellationToken
+    DeadlineExceeded,
 }
 
 impl Cancelled {
@@ -48,11 +63,74 @@ impl std::fmt::Display for Cancelled {
         let why = match self {
             Cancelled::Local => "local cancellation request",
             Cancelled::PendingWrite => "pending write",
-            Cancelled::PropagatedPanic => "propagated panic",
+            Cancelled::PropagatedPanic(_) => "propagated panic",
+            Cancelled::Reason(reason) => reason,
+            Cancelled::DeadlineExceeded => "exceeded deadline",
         };
         f.write_str("cancelled because of ")?;
-        f.write_str(why)
+        f.write_str(why)?;
+        if let Cancelled::PropagatedPanic(Some(info)) = self {
+            write!(f, ": {info}")?;
+        }
+        Ok(())
     }
 }
 
 impl std::error::Error for Cancelled {}
+
+/// The panic of a query on another thread that a blocked query was waiting on.
+#[derive(Clone)]
+pub struct QueryPanicInfo {
+    message: Arc<str>,
+    database_key: DatabaseKeyIndex,
+    backtrace: Arc<Backtrace>,
+}
+
+impl QueryPanicInfo {
+    pub(crate) fn new(
+        message: Arc<str>,
+        database_key: DatabaseKeyIndex,
+        backtrace: Backtrace,
+    ) -> Self {
+        Self {
+            message,
+            database_key,
+            backtrace: Arc::new(backtrace),
+        }
+    }
+
+    /// Returns the message the query panicked with, if the panic payload was a string.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the query that panicked.
+    pub fn database_key(&self) -> DatabaseKeyIndex {
+        self.database_key
+    }
+
+    /// Returns the query stack of the panicking thread at the point of the panic,
+    /// innermost query first.
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+}
+
+impl fmt::Debug for QueryPanicInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryPanicInfo")
+            .field("message", &self.message)
+            .field("database_key", &self.database_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for QueryPanicInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "query `{:?}` panicked: {}",
+            self.database_key, self.message
+        )
+    }
+}
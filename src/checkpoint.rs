@@ -0,0 +1,103 @@
+use std::ops::Deref;
+
+use crate::input::setter::{FieldWriter, did_set_field};
+use crate::runtime::JournalPosition;
+use crate::zalsa::Zalsa;
+use crate::{Database, DatabaseKeyIndex, Durability};
+
+/// A checkpoint that input writes can be undone to, see [`DatabaseExt::checkpoint`](`crate::DatabaseExt::checkpoint`).
+///
+/// Input fields are set through the checkpoint while it is held, which requires their
+/// type to implement `Clone`: undoing a write restores a copy of the previous value of
+/// the field. The checkpoint dereferences to the database, e.g. to call tracked functions
+/// with `&*checkpoint`.
+///
+/// Dropping the checkpoint keeps the writes made since it was taken, just like
+/// [`Checkpoint::release`]. The journal of writes is discarded once no checkpoint is held.
+pub struct Checkpoint<'db, Db: ?Sized + Database> {
+    db: &'db mut Db,
+    position: JournalPosition,
+}
+
+impl<'db, Db: ?Sized + Database> Checkpoint<'db, Db> {
+    pub(crate) fn new(db: &'db mut Db) -> Self {
+        let position = db.zalsa_mut().checkpoint();
+        Self { db, position }
+    }
+
+    /// Returns the database the checkpoint was taken on.
+    pub fn db(&self) -> &Db {
+        self.db
+    }
+
+    /// Takes a nested checkpoint, which can be undone without undoing the writes
+    /// made through this checkpoint before.
+    ///
+    /// **WARNING:** Just like an ordinary write, this method triggers
+    /// cancellation. If you invoke it while a snapshot exists, it
+    /// will block until that snapshot is dropped -- if that snapshot
+    /// is owned by the current thread, this could trigger deadlock.
+    pub fn checkpoint(&mut self) -> Checkpoint<'_, Db> {
+        Checkpoint::new(self.db)
+    }
+
+    /// Undoes all input writes made since the checkpoint was taken.
+    ///
+    /// Every field written since is restored to its previous value. If no query ran since
+    /// the checkpoint, the fields also get their previous revision and durability back,
+    /// so that memoized results remain valid as if the writes never happened. Otherwise,
+    /// the restored values are recorded as a new change; queries that are re-executed
+    /// and produce the same result as before are then backdated, avoiding the
+    /// re-execution of their dependents.
+    ///
+    /// **WARNING:** Just like an ordinary write, this method triggers
+    /// cancellation. If you invoke it while a snapshot exists, it
+    /// will block until that snapshot is dropped -- if that snapshot
+    /// is owned by the current thread, this could trigger deadlock.
+    pub fn undo(self) {
+        self.db.zalsa_mut().undo_to(self.position);
+        crate::watch::after_input_write(&*self.db);
+    }
+
+    /// Releases the checkpoint, keeping the writes made since it was taken.
+    ///
+    /// The writes can still be undone by undoing an enclosing checkpoint.
+    pub fn release(self) {}
+}
+
+impl<Db: ?Sized + Database> Drop for Checkpoint<'_, Db> {
+    fn drop(&mut self) {
+        self.db.zalsa().runtime().release_checkpoint();
+    }
+}
+
+impl<Db: ?Sized + Database> Deref for Checkpoint<'_, Db> {
+    type Target = Db;
+
+    fn deref(&self) -> &Db {
+        self.db
+    }
+}
+
+impl<Db, F> FieldWriter<F> for Checkpoint<'_, Db>
+where
+    Db: ?Sized + Database,
+    F: Clone,
+{
+    fn field_zalsa(&self) -> &Zalsa {
+        self.db.zalsa()
+    }
+
+    fn field_zalsa_mut(&mut self, durability: Durability) -> &mut Zalsa {
+        self.db.zalsa_mut_for_write(durability)
+    }
+
+    fn field_written(&mut self, database_key: DatabaseKeyIndex, durability: Durability) {
+        did_set_field(&*self.db, database_key, durability);
+        crate::watch::after_input_write(&*self.db);
+    }
+
+    fn clone_fn() -> Option<fn(&F) -> F> {
+        Some(F::clone)
+    }
+}
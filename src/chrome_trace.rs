@@ -0,0 +1,210 @@
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::time::{Duration, Instant};
+
+use crate::DatabaseKeyIndex;
+use crate::query_graph::{Escaped, KeyLabel};
+use crate::sync::Mutex;
+use crate::sync::atomic::{AtomicBool, Ordering};
+use crate::sync::thread::{self, ThreadId};
+use crate::zalsa::Zalsa;
+
+/// Records query executions, blocking and cycle iterations in the
+/// [Chrome trace-event format](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU),
+/// which can be loaded into `chrome://tracing` or [Perfetto](https://ui.perfetto.dev).
+///
+/// Recording is started with [`DatabaseExt::start_chrome_trace`](`crate::DatabaseExt::start_chrome_trace`).
+#[derive(Default)]
+pub(crate) struct ChromeTrace {
+    enabled: AtomicBool,
+    state: Mutex<TraceState>,
+}
+
+/// The recorded events are only appended to while the lock is held, and no code
+/// that could panic runs in between, so the state can't be observed half-updated.
+impl RefUnwindSafe for ChromeTrace {}
+
+#[derive(Default)]
+struct TraceState {
+    start: Option<Instant>,
+
+    /// The threads that recorded events or were blocked on, with their names if known.
+    /// Events refer to threads by their index in this list.
+    threads: Vec<(ThreadId, Option<String>)>,
+
+    events: Vec<TraceEvent>,
+}
+
+struct TraceEvent {
+    kind: TraceEventKind,
+    thread: usize,
+    timestamp: Duration,
+}
+
+enum TraceEventKind {
+    BeginQuery(DatabaseKeyIndex),
+    EndQuery(DatabaseKeyIndex),
+    BeginBlock {
+        database_key: DatabaseKeyIndex,
+        other_thread: usize,
+    },
+    EndBlock(DatabaseKeyIndex),
+    IterateCycle {
+        database_key: DatabaseKeyIndex,
+        iteration: u8,
+    },
+}
+
+impl ChromeTrace {
+    /// Discards any previously recorded events and starts recording.
+    pub(crate) fn start(&self) {
+        let mut state = self.state.lock();
+        *state = TraceState {
+            start: Some(Instant::now()),
+            ..TraceState::default()
+        };
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops recording, keeping the events recorded so far.
+    pub(crate) fn stop(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns the trace if recording is enabled.
+    #[inline]
+    pub(crate) fn if_enabled(&self) -> Option<&Self> {
+        if self.enabled.load(Ordering::Relaxed) {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn begin_query(&self, database_key: DatabaseKeyIndex) {
+        self.record(TraceEventKind::BeginQuery(database_key));
+    }
+
+    pub(crate) fn end_query(&self, database_key: DatabaseKeyIndex) {
+        self.record(TraceEventKind::EndQuery(database_key));
+    }
+
+    pub(crate) fn begin_block(&self, database_key: DatabaseKeyIndex, other_thread: ThreadId) {
+        let mut state = self.state.lock();
+        let other_thread = state.thread_index(other_thread, None);
+        state.record(TraceEventKind::BeginBlock {
+            database_key,
+            other_thread,
+        });
+    }
+
+    pub(crate) fn end_block(&self, database_key: DatabaseKeyIndex) {
+        self.record(TraceEventKind::EndBlock(database_key));
+    }
+
+    pub(crate) fn iterate_cycle(&self, database_key: DatabaseKeyIndex, iteration: u8) {
+        self.record(TraceEventKind::IterateCycle {
+            database_key,
+            iteration,
+        });
+    }
+
+    fn record(&self, kind: TraceEventKind) {
+        self.state.lock().record(kind);
+    }
+
+    /// Writes the recorded events as a JSON object with a `traceEvents` array.
+    pub(crate) fn write_json(&self, zalsa: &Zalsa, writer: &mut dyn io::Write) -> io::Result<()> {
+        let state = self.state.lock();
+
+        write!(writer, "{{\"traceEvents\":[")?;
+        for (tid, (id, name)) in state.threads.iter().enumerate() {
+            if tid > 0 {
+                write!(writer, ",")?;
+            }
+            let name = name.clone().unwrap_or_else(|| format!("{id:?}"));
+            write!(
+                writer,
+                "\n{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{tid},\"args\":{{\"name\":\"{}\"}}}}",
+                Escaped(&name)
+            )?;
+        }
+
+        for event in &state.events {
+            let tid = event.thread;
+            let ts = event.timestamp.as_nanos() as f64 / 1000.0;
+            let (phase, name, category, database_key) = match event.kind {
+                TraceEventKind::BeginQuery(database_key) => ("B", "", None, database_key),
+                TraceEventKind::EndQuery(database_key) => ("E", "", None, database_key),
+                TraceEventKind::BeginBlock { database_key, .. } => {
+                    ("B", "blocked on ", Some("blocking"), database_key)
+                }
+                TraceEventKind::EndBlock(database_key) => {
+                    ("E", "blocked on ", Some("blocking"), database_key)
+                }
+                TraceEventKind::IterateCycle { database_key, .. } => {
+                    ("i", "iterate cycle ", Some("cycle"), database_key)
+                }
+            };
+            let category = category.unwrap_or_else(|| {
+                zalsa
+                    .lookup_ingredient(database_key.ingredient_index())
+                    .debug_name()
+            });
+            let label = KeyLabel {
+                zalsa,
+                key: database_key,
+            }
+            .to_string();
+
+            write!(
+                writer,
+                ",\n{{\"name\":\"{name}{}\",\"cat\":\"{}\",\"ph\":\"{phase}\",\"ts\":{ts:.3},\"pid\":1,\"tid\":{tid}",
+                Escaped(&label),
+                Escaped(category)
+            )?;
+            match event.kind {
+                TraceEventKind::BeginBlock { other_thread, .. } => {
+                    write!(writer, ",\"args\":{{\"other_thread\":{other_thread}}}")?
+                }
+                TraceEventKind::IterateCycle { iteration, .. } => write!(
+                    writer,
+                    ",\"s\":\"t\",\"args\":{{\"iteration\":{iteration}}}"
+                )?,
+                _ => {}
+            }
+            write!(writer, "}}")?;
+        }
+        writeln!(writer, "\n]}}")
+    }
+}
+
+impl TraceState {
+    fn record(&mut self, kind: TraceEventKind) {
+        let current = thread::current();
+        let thread = self.thread_index(current.id(), current.name());
+        let timestamp = self.start.map(|start| start.elapsed()).unwrap_or_default();
+        self.events.push(TraceEvent {
+            kind,
+            thread,
+            timestamp,
+        });
+    }
+
+    /// Returns the index of the thread `id`, naming it `name` if it wasn't named yet.
+    fn thread_index(&mut self, id: ThreadId, name: Option<&str>) -> usize {
+        match self.threads.iter().position(|(thread, _)| *thread == id) {
+            Some(index) => {
+                let known_name = &mut self.threads[index].1;
+                if known_name.is_none() {
+                    *known_name = name.map(str::to_string);
+                }
+                index
+            }
+            None => {
+                self.threads.push((id, name.map(str::to_string)));
+                self.threads.len() - 1
+            }
+        }
+    }
+}
@@ -1,10 +1,14 @@
 use std::borrow::Cow;
 use std::ptr::NonNull;
 
+use crate::CancellationToken;
 use crate::views::DatabaseDownCaster;
 use crate::zalsa::{IngredientIndex, ZalsaDatabase};
-use crate::zalsa_local::CancellationToken;
-use crate::{Durability, Revision};
+use crate::{
+    Checkpoint, DatabaseKeyIndex, Durability, Event, EventFilter, EventListenerId, ExecutionReason,
+    ExternalRead, InvalidationPreview, QueryGraph, QueryInfo, ReverseDependencies, Revision,
+    Transaction, WaitGraph, WatchId,
+};
 
 #[derive(Copy, Clone)]
 pub struct RawDatabase<'db> {
@@ -55,9 +59,10 @@ pub trait Database: Send + ZalsaDatabase + AsDynDatabase {
     /// will block until that snapshot is dropped -- if that snapshot
     /// is owned by the current thread, this could trigger deadlock.
     fn synthetic_write(&mut self, durability: Durability) {
-        let zalsa_mut = self.zalsa_mut();
+        let zalsa_mut = self.zalsa_mut_for_write(durability);
         zalsa_mut.new_revision();
         zalsa_mut.runtime_mut().report_tracked_write(durability);
+        crate::watch::after_input_write(self);
     }
 
     /// This method cancels all outstanding computations.
@@ -82,6 +87,22 @@ pub trait Database: Send + ZalsaDatabase + AsDynDatabase {
         zalsa_local.report_untracked_read(zalsa.current_revision())
     }
 
+    /// Reports that the query depends on some state outside of the database,
+    /// such as the modification time of a file.
+    ///
+    /// Unlike [`report_untracked_read`](`Self::report_untracked_read`), the query is only
+    /// re-executed if the validator of `read` observes a different value when the query is
+    /// verified in a later revision. Salsa has no way to tell that the external state changed,
+    /// so an external change is only noticed once a new revision has started, e.g. after
+    /// [`synthetic_write`](`Self::synthetic_write`).
+    ///
+    /// Reads with equal keys share their state; only the validator passed with the
+    /// first read of a key is retained.
+    fn report_external_read(&self, read: ExternalRead) {
+        let (zalsa, zalsa_local) = self.zalsas();
+        zalsa.external_reads().report_read(zalsa, zalsa_local, read)
+    }
+
     /// Return the "debug name" (i.e., the struct name, etc) for an "ingredient",
     /// which are the fine-grained components we use to track data. This is intended
     /// for debugging and the contents of the returned string are not semver-guaranteed.
@@ -154,6 +175,240 @@ impl<T: Database> AsDynDatabase for T {
     }
 }
 
+/// Methods to make atomic writes to a database and to observe and inspect its queries,
+/// implemented for all databases.
+pub trait DatabaseExt: Database {
+    /// Runs `op` as an atomic input transaction.
+    ///
+    /// All input writes performed by `op` share a single new revision, and other
+    /// database handles are cancelled only once, when the transaction is opened.
+    /// If `op` returns an error or panics, all writes are rolled back.
+    ///
+    /// Tracked functions may be called inside the transaction; they observe the
+    /// writes performed so far. Any write following such a call starts another
+    /// revision so that the results don't become stale within a revision.
+    ///
+    /// Input fields are set through the [`Transaction`] passed to `op`. Rolling back a field
+    /// requires a copy of its previous value, so only fields whose type implements `Clone`
+    /// can be set inside a transaction.
+    ///
+    /// **WARNING:** Just like an ordinary write, this method triggers
+    /// cancellation. If you invoke it while a snapshot exists, it
+    /// will block until that snapshot is dropped -- if that snapshot
+    /// is owned by the current thread, this could trigger deadlock.
+    ///
+    /// # Panics
+    ///
+    /// If a transaction is already open on this database.
+    fn transaction<T, E>(
+        &mut self,
+        op: impl FnOnce(&mut Transaction<'_, Self>) -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        Self: Sized,
+    {
+        self.zalsa_mut().begin_transaction();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            op(&mut Transaction::new(self))
+        }));
+
+        let zalsa = self.zalsa_mut();
+        match result {
+            Ok(Ok(value)) => {
+                zalsa.commit_transaction();
+                crate::watch::after_input_write(self);
+                Ok(value)
+            }
+            Ok(Err(error)) => {
+                zalsa.rollback_transaction();
+                Err(error)
+            }
+            Err(payload) => {
+                zalsa.rollback_transaction();
+                std::panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    /// Takes a checkpoint that input writes can later be undone to with [`Checkpoint::undo`].
+    ///
+    /// Taking the first checkpoint starts recording a journal of the input writes made
+    /// through the checkpoint, which is discarded once no checkpoint is held anymore.
+    /// Each entry keeps a copy of the previous value of the field, so only fields whose
+    /// type implements `Clone` can be set through a checkpoint.
+    ///
+    /// **WARNING:** Just like an ordinary write, this method triggers
+    /// cancellation. If you invoke it while a snapshot exists, it
+    /// will block until that snapshot is dropped -- if that snapshot
+    /// is owned by the current thread, this could trigger deadlock.
+    fn checkpoint(&mut self) -> Checkpoint<'_, Self>
+    where
+        Self: Sized,
+    {
+        Checkpoint::new(self)
+    }
+
+    /// Registers interest in the result of `query` for `key`, which is typically
+    /// a call to a tracked function.
+    ///
+    /// The query isn't executed until the watches are polled with
+    /// [`poll_watches`](`Self::poll_watches`), which invokes `callback` with its first result.
+    /// Afterwards, the watches are revalidated after every input write (or once a transaction
+    /// or a batch of writes is committed) and `callback` is only invoked with the new result
+    /// when the result changed.
+    fn watch<K, V>(
+        &self,
+        query: impl Fn(&Self, K) -> V + Send + 'static,
+        key: K,
+        callback: impl FnMut(V) + Send + 'static,
+    ) -> WatchId
+    where
+        Self: Sized,
+        K: Clone + Send + 'static,
+        V: Send + 'static,
+    {
+        self.zalsa().watches().watch(query, key, callback)
+    }
+
+    /// Removes a watch registered with [`watch`](`Self::watch`).
+    fn unwatch(&self, watch: WatchId) {
+        self.zalsa().watches().unwatch(watch)
+    }
+
+    /// Revalidates the watched queries and invokes the callbacks of those whose result changed.
+    ///
+    /// Only the dependencies of watched queries are verified, and a query is only re-executed
+    /// if one of them changed. Watches are verified at most once per revision.
+    ///
+    /// Input writes poll the watches automatically; this only needs to be called to
+    /// execute newly registered watches or to notice changes to external state.
+    ///
+    /// # Panics
+    ///
+    /// If called from inside a query.
+    fn poll_watches(&self) {
+        let (zalsa, zalsa_local) = self.zalsas();
+        // SAFETY: The watches of a database are registered with the database itself.
+        unsafe {
+            zalsa
+                .watches()
+                .poll(zalsa, zalsa_local, RawDatabase::from(self))
+        }
+    }
+
+    /// Returns information about the memoized value of the query `key`, such as its
+    /// dependencies and the revisions in which it was last verified and changed.
+    ///
+    /// Returns `None` if `key` doesn't identify a tracked function or if no value has
+    /// been memoized for it.
+    fn query_info(&self, key: DatabaseKeyIndex) -> Option<QueryInfo> {
+        let zalsa = self.zalsa();
+        zalsa
+            .lookup_ingredient(key.ingredient_index())
+            .query_info(zalsa, key.key_index())
+    }
+
+    /// Builds an index from each input, tracked struct field and query to the memoized
+    /// queries that read it, e.g. to find the queries affected by a change to an input.
+    /// Memoized queries that haven't been verified in the current revision are left out,
+    /// as the inputs they read may be out of date.
+    ///
+    /// Building the index walks all memoized values, so it is best built once and used
+    /// for multiple lookups.
+    fn reverse_dependencies(&self) -> ReverseDependencies {
+        ReverseDependencies::new(self.zalsa())
+    }
+
+    /// Previews which memoized queries would be affected if the input fields `changes`
+    /// were set in a new revision, without changing anything.
+    ///
+    /// The keys of input fields are returned by the `<field>_database_key` methods of
+    /// input structs. Changes to other keys are assumed to affect queries of all durabilities.
+    fn preview_invalidation(&self, changes: &[DatabaseKeyIndex]) -> InvalidationPreview {
+        InvalidationPreview::new(self.zalsa(), changes)
+    }
+
+    /// Enables or disables recording why queries are executed, which can then be inspected
+    /// with [`last_execution_reason`](`Self::last_execution_reason`).
+    ///
+    /// Recording is disabled by default, because it keeps the reason of every executed query.
+    /// Disabling recording discards the recorded reasons.
+    fn record_execution_reasons(&self, enabled: bool) {
+        self.zalsa().execution_reasons().set_enabled(enabled);
+    }
+
+    /// Returns why the query `key` was last executed, e.g. which input field changed.
+    ///
+    /// Returns `None` if the query hasn't been executed while recording was enabled with
+    /// [`record_execution_reasons`](`Self::record_execution_reasons`).
+    fn last_execution_reason(&self, key: DatabaseKeyIndex) -> Option<ExecutionReason> {
+        self.zalsa().execution_reasons().last(key)
+    }
+
+    /// Exports the dependency graph of the memoized queries, e.g. to render it with
+    /// [`QueryGraph::to_dot`].
+    ///
+    /// If `root` is given, the graph only contains `root` and the keys it depends on,
+    /// directly or indirectly. Otherwise, it contains all memoized queries.
+    fn query_graph(&self, root: Option<DatabaseKeyIndex>) -> QueryGraph {
+        QueryGraph::new(self.zalsa(), root)
+    }
+
+    /// Returns a snapshot of the threads that are blocked on queries running on other
+    /// threads, e.g. to find out why a query doesn't make progress.
+    fn wait_graph(&self) -> WaitGraph {
+        self.zalsa().runtime().wait_graph()
+    }
+
+    /// Starts recording query executions, blocking on other threads and cycle iterations
+    /// as a trace that can be loaded into `chrome://tracing` or [Perfetto](https://ui.perfetto.dev).
+    ///
+    /// Discards any previously recorded trace. The trace is shared by all handles to the database.
+    fn start_chrome_trace(&self) {
+        self.zalsa().chrome_trace().start();
+    }
+
+    /// Stops recording the trace started with [`start_chrome_trace`](`Self::start_chrome_trace`),
+    /// keeping the events recorded so far.
+    fn stop_chrome_trace(&self) {
+        self.zalsa().chrome_trace().stop();
+    }
+
+    /// Writes the recorded trace to `writer` in the Chrome trace-event JSON format.
+    ///
+    /// Executed queries are duration events named after the query and categorized by
+    /// the tracked function, on the thread that executed them.
+    fn write_chrome_trace(&self, writer: &mut dyn std::io::Write) -> std::io::Result<()> {
+        let zalsa = self.zalsa();
+        zalsa.chrome_trace().write_json(zalsa, writer)
+    }
+
+    /// Registers `callback` to be invoked for the events selected by `filter`,
+    /// in addition to the event callback given when the storage was created.
+    ///
+    /// Listeners are shared by all handles to the database. Events of kinds no listener
+    /// is interested in are never created.
+    fn add_event_listener(
+        &self,
+        filter: EventFilter,
+        callback: Box<dyn Fn(Event) + Send + Sync>,
+    ) -> EventListenerId {
+        self.zalsa()
+            .runtime()
+            .event_listeners()
+            .add(filter, callback)
+    }
+
+    /// Unregisters a listener registered with [`add_event_listener`](`Self::add_event_listener`),
+    /// returning `false` if it was already unregistered.
+    fn remove_event_listener(&self, id: EventListenerId) -> bool {
+        self.zalsa().runtime().event_listeners().remove(id)
+    }
+}
+
+impl<Db: ?Sized + Database> DatabaseExt for Db {}
+
 pub fn current_revision<Db: ?Sized + Database>(db: &Db) -> Revision {
     db.zalsa().current_revision()
 }
@@ -524,3 +779,37 @@ mod memory_usage {
         pub(crate) output: SlotInfo,
     }
 }
+
+#[cfg(feature = "salsa_unstable")]
+mod query_profile {
+    use crate::Database;
+    use crate::function::QueryProfile;
+
+    impl dyn Database {
+        /// Enables or disables recording execution and cache statistics for all tracked functions.
+        ///
+        /// Profiling is disabled by default. The statistics are available with `query_profile`.
+        pub fn set_profiling(&self, enabled: bool) {
+            self.zalsa().set_profiling(enabled);
+        }
+
+        /// Returns the execution and cache statistics recorded while profiling was enabled,
+        /// one entry per tracked function.
+        ///
+        /// Tracked functions with the same name, e.g. in different modules, are told apart
+        /// by their [`QueryProfile::ingredient_index`].
+        pub fn query_profile(&self) -> Vec<QueryProfile> {
+            self.zalsa()
+                .ingredients()
+                .filter_map(|ingredient| ingredient.query_profile())
+                .collect()
+        }
+
+        /// Resets the statistics of all tracked functions.
+        pub fn reset_query_profile(&self) {
+            for ingredient in self.zalsa().ingredients() {
+                ingredient.reset_query_profile();
+            }
+        }
+    }
+}
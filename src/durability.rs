@@ -16,6 +16,9 @@
 /// frequently editing. Medium or high durabilities are used for
 /// configuration, the source from library crates, or other things
 /// that are unlikely to be edited.
+///
+/// Databases that need more fine-grained tiers can be configured with up to
+/// eight durability levels, see [`Durability::new`].
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Durability(DurabilityVal);
 
@@ -25,7 +28,7 @@ impl serde::Serialize for Durability {
     where
         S: serde::Serializer,
     {
-        serde::Serialize::serialize(&(self.0 as u8), serializer)
+        serde::Serialize::serialize(&self.to_persisted(), serializer)
     }
 }
 
@@ -35,17 +38,24 @@ impl<'de> serde::Deserialize<'de> for Durability {
     where
         D: serde::Deserializer<'de>,
     {
-        u8::deserialize(deserializer).map(|value| Self(DurabilityVal::from(value)))
+        let value = u8::deserialize(deserializer)?;
+        Self::from_persisted(value).ok_or_else(|| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Unsigned(value.into()),
+                &"a durability between 0 and 7",
+            )
+        })
     }
 }
 
 impl std::fmt::Debug for Durability {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if f.alternate() {
-            match self.0 {
-                DurabilityVal::Low => f.write_str("Durability::LOW"),
-                DurabilityVal::Medium => f.write_str("Durability::MEDIUM"),
-                DurabilityVal::High => f.write_str("Durability::HIGH"),
+            match *self {
+                Durability::LOW => f.write_str("Durability::LOW"),
+                Durability::MEDIUM => f.write_str("Durability::MEDIUM"),
+                Durability::HIGH => f.write_str("Durability::HIGH"),
+                _ => write!(f, "Durability::new({})", self.0 as u8),
             }
         } else {
             f.debug_tuple("Durability")
@@ -58,38 +68,57 @@ impl std::fmt::Debug for Durability {
 // We use an enum here instead of a u8 for niches.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 enum DurabilityVal {
-    Low = 0,
-    Medium = 1,
-    High = 2,
+    Level0 = 0,
+    Level1 = 1,
+    Level2 = 2,
+    Level3 = 3,
+    Level4 = 4,
+    Level5 = 5,
+    Level6 = 6,
+    Level7 = 7,
 }
 
-impl From<u8> for DurabilityVal {
-    fn from(value: u8) -> Self {
+impl DurabilityVal {
+    const fn new(value: u8) -> Self {
         match value {
-            0 => DurabilityVal::Low,
-            1 => DurabilityVal::Medium,
-            2 => DurabilityVal::High,
+            0 => DurabilityVal::Level0,
+            1 => DurabilityVal::Level1,
+            2 => DurabilityVal::Level2,
+            3 => DurabilityVal::Level3,
+            4 => DurabilityVal::Level4,
+            5 => DurabilityVal::Level5,
+            6 => DurabilityVal::Level6,
+            7 => DurabilityVal::Level7,
             _ => panic!("invalid durability"),
         }
     }
 }
 
+impl From<u8> for DurabilityVal {
+    fn from(value: u8) -> Self {
+        Self::new(value)
+    }
+}
+
 impl Durability {
     /// Low durability: things that change frequently.
     ///
     /// Example: part of the crate being edited
-    pub const LOW: Durability = Durability(DurabilityVal::Low);
+    pub const LOW: Durability = Durability(DurabilityVal::Level0);
 
     /// Medium durability: things that change sometimes, but rarely.
     ///
     /// Example: a Cargo.toml file
-    pub const MEDIUM: Durability = Durability(DurabilityVal::Medium);
+    pub const MEDIUM: Durability = Durability(DurabilityVal::Level1);
 
     /// High durability: things that are not expected to change under
     /// common usage.
     ///
+    /// This is always the highest durability level, regardless of the
+    /// number of levels the database is configured with.
+    ///
     /// Example: the standard library or something from crates.io
-    pub const HIGH: Durability = Durability(DurabilityVal::High);
+    pub const HIGH: Durability = Durability(DurabilityVal::Level7);
 
     /// The minimum possible durability; equivalent to LOW but
     /// "conceptually" distinct (i.e., if we add more durability
@@ -101,12 +130,55 @@ impl Durability {
     /// levels, this could change).
     pub(crate) const MAX: Durability = Self::HIGH;
 
-    /// Number of durability levels.
-    pub(crate) const LEN: usize = Self::HIGH.0 as usize + 1;
+    /// Maximum number of durability levels.
+    pub(crate) const LEN: usize = Self::MAX.0 as usize + 1;
+
+    /// The default number of durability levels: `LOW`, `MEDIUM` and `HIGH`.
+    pub(crate) const DEFAULT_LEVELS: usize = 3;
+
+    /// Creates the durability of the given level, where `0` is the lowest and `7` the
+    /// highest level.
+    ///
+    /// A database tracks changes for the number of levels configured with
+    /// [`Storage::builder`](`crate::Storage::builder`).
+    /// Levels beyond that number are treated like the highest configured level.
+    /// `Durability::new(0)` is [`Durability::LOW`], `Durability::new(1)` is
+    /// [`Durability::MEDIUM`] and `Durability::new(7)` is [`Durability::HIGH`].
+    ///
+    /// # Panics
+    ///
+    /// If `level` is greater than `7`.
+    pub const fn new(level: u8) -> Durability {
+        Durability(DurabilityVal::new(level))
+    }
 
     pub(crate) fn index(self) -> usize {
         self.0 as usize
     }
+
+    // Databases used to have exactly three durability levels, persisted as `0` (`LOW`),
+    // `1` (`MEDIUM`) and `2` (`HIGH`). To keep reading them, `HIGH` is still persisted as
+    // `2` and the levels in between `MEDIUM` and `HIGH` are persisted as `3` to `7`.
+
+    #[cfg(feature = "persistence")]
+    fn to_persisted(self) -> u8 {
+        match self.0 as u8 {
+            7 => 2,
+            level @ 2..=6 => level + 1,
+            level => level,
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn from_persisted(value: u8) -> Option<Durability> {
+        let level = match value {
+            2 => 7,
+            3..=7 => value - 1,
+            0 | 1 => value,
+            _ => return None,
+        };
+        Some(Durability::new(level))
+    }
 }
 
 impl Default for Durability {
@@ -114,3 +186,30 @@ impl Default for Durability {
         Durability::LOW
     }
 }
+
+#[cfg(all(test, feature = "persistence"))]
+mod tests {
+    use super::Durability;
+
+    #[test]
+    fn persisted_encoding_round_trips() {
+        for level in 0..Durability::LEN as u8 {
+            let durability = Durability::new(level);
+            let persisted = serde_json::to_string(&durability).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Durability>(&persisted).unwrap(),
+                durability
+            );
+        }
+    }
+
+    #[test]
+    fn reads_three_level_encoding() {
+        let durabilities: Vec<Durability> = serde_json::from_str("[0, 1, 2]").unwrap();
+        assert_eq!(
+            durabilities,
+            [Durability::LOW, Durability::MEDIUM, Durability::HIGH]
+        );
+        assert!(serde_json::from_str::<Durability>("8").is_err());
+    }
+}
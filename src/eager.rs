@@ -0,0 +1,188 @@
+use std::panic::AssertUnwindSafe;
+
+use crate::database::{RawDatabase, current_revision};
+use crate::sync::atomic::{AtomicBool, Ordering};
+use crate::sync::{Arc, Mutex, thread};
+use crate::{CancellationToken, Cancelled, Database, Revision};
+
+type RootQuery<Db> = Arc<dyn Fn(&Db) + Send + Sync>;
+
+/// Eagerly re-executes a set of root queries on background threads after the
+/// inputs changed, so that later reads find their results memoized.
+///
+/// The root queries are recomputed after every input write to the database the
+/// scheduler was created for (or once a transaction or a batch of writes is committed).
+/// Each background thread works on its own clone of the database. Once a write
+/// cancels the other handles of the database, the threads stop at the next query
+/// they execute and drop their clone, so that the write can proceed.
+///
+/// Dropping the scheduler cancels the recomputations in progress and waits for them to stop.
+pub struct EagerScheduler<Db> {
+    inner: Arc<Inner<Db>>,
+}
+
+struct Inner<Db> {
+    queries: Mutex<Vec<RootQuery<Db>>>,
+    threads: usize,
+    state: Mutex<State>,
+}
+
+struct State {
+    /// The revision the root queries were last scheduled in.
+    scheduled_at: Option<Revision>,
+
+    /// The workers that haven't been joined yet.
+    workers: Vec<Worker>,
+}
+
+struct Worker {
+    handle: thread::JoinHandle<()>,
+    token: CancellationToken,
+    finished: Arc<AtomicBool>,
+}
+
+impl<Db> EagerScheduler<Db>
+where
+    Db: Database + Clone + 'static,
+{
+    /// Creates a scheduler that recomputes the root queries on up to `threads` threads
+    /// after every input write to `db`.
+    ///
+    /// # Panics
+    ///
+    /// If `threads` is zero.
+    pub fn new(db: &Db, threads: usize) -> Self {
+        assert!(threads > 0, "the scheduler needs at least one thread");
+
+        let inner = Arc::new(Inner {
+            queries: Mutex::new(Vec::new()),
+            threads,
+            state: Mutex::new(State {
+                scheduled_at: None,
+                workers: Vec::new(),
+            }),
+        });
+
+        let weak = Arc::downgrade(&inner);
+        db.zalsa().write_hooks().add(move |db: RawDatabase<'_>| {
+            let Some(inner) = weak.upgrade() else {
+                return false;
+            };
+            // SAFETY: The hook is registered with the database of type `Db`, which
+            // is the database it is invoked with.
+            let db = unsafe { db.ptr.cast::<Db>().as_ref() };
+            inner.schedule(db);
+            true
+        });
+
+        Self { inner }
+    }
+
+    /// Registers a root query to recompute, typically a call to a tracked function.
+    pub fn register(&mut self, query: impl Fn(&Db) + Send + Sync + 'static) {
+        self.inner.queries.lock().push(Arc::new(query));
+    }
+
+    /// Starts recomputing the registered root queries in the current revision of `db`,
+    /// unless they were already scheduled in this revision.
+    ///
+    /// This happens automatically after input writes; call this to recompute the root
+    /// queries before the first write.
+    pub fn schedule(&self, db: &Db) {
+        self.inner.schedule(db);
+    }
+
+    /// Blocks until all scheduled recomputations finished or were cancelled.
+    ///
+    /// # Panics
+    ///
+    /// If a root query panicked for a reason other than cancellation.
+    pub fn wait(&self) {
+        let workers = std::mem::take(&mut self.inner.state.lock().workers);
+        for worker in workers {
+            if let Err(payload) = worker.handle.join() {
+                std::panic::resume_unwind(payload);
+            }
+        }
+    }
+}
+
+impl<Db> Inner<Db>
+where
+    Db: Database + Clone + 'static,
+{
+    fn schedule(&self, db: &Db) {
+        let revision = current_revision(db);
+        let mut state = self.state.lock();
+        if state.scheduled_at == Some(revision) {
+            return;
+        }
+        state.scheduled_at = Some(revision);
+
+        // Workers of earlier revisions have usually been cancelled by the write already,
+        // make sure that they don't keep computing stale results.
+        state
+            .workers
+            .retain(|worker| !worker.finished.load(Ordering::Acquire));
+        for worker in &state.workers {
+            worker.token.cancel();
+        }
+
+        let queries = self.queries.lock();
+        let threads = self.threads.min(queries.len());
+        for thread in 0..threads {
+            let queries = queries
+                .iter()
+                .skip(thread)
+                .step_by(threads)
+                .cloned()
+                .collect::<Vec<_>>();
+            let db = db.clone();
+            let token = db.cancellation_token();
+            let finished = Arc::new(AtomicBool::new(false));
+
+            let handle = thread::spawn({
+                let finished = finished.clone();
+                move || {
+                    for query in queries {
+                        if Cancelled::catch(AssertUnwindSafe(|| query(&db))).is_err() {
+                            crate::tracing::debug!("eager recomputation cancelled");
+                            break;
+                        }
+                    }
+                    finished.store(true, Ordering::Release);
+                }
+            });
+            state.workers.push(Worker {
+                handle,
+                token,
+                finished,
+            });
+        }
+    }
+}
+
+impl<Db> Drop for EagerScheduler<Db> {
+    fn drop(&mut self) {
+        let workers = std::mem::take(&mut self.inner.state.lock().workers);
+        for worker in &workers {
+            worker.token.cancel();
+        }
+        for worker in workers {
+            // Panics of the root queries are only propagated by `wait`.
+            let _ = worker.handle.join();
+        }
+    }
+}
+
+impl<Db> std::fmt::Debug for EagerScheduler<Db> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.inner.state.lock();
+        f.debug_struct("EagerScheduler")
+            .field("queries", &self.inner.queries.lock().len())
+            .field("threads", &self.inner.threads)
+            .field("scheduled_at", &state.scheduled_at)
+            .field("workers", &state.workers.len())
+            .finish()
+    }
+}
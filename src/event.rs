@@ -1,6 +1,12 @@
-use crate::Revision;
+use std::time::Duration;
+
+use crossbeam_epoch::{self as epoch, Atomic, Owned};
+
 use crate::key::DatabaseKeyIndex;
+use crate::sync::atomic::{AtomicU32, Ordering};
 use crate::sync::thread::{self, ThreadId};
+use crate::sync::{Arc, Mutex};
+use crate::{BlockedThread, Durability, Revision};
 
 /// The `Event` struct identifies various notable things that can
 /// occur during salsa execution. Instances of this struct are given
@@ -50,6 +56,38 @@ pub enum EventKind {
         database_key: DatabaseKeyIndex,
     },
 
+    /// Indicates that a thread that was blocked on another thread (see `WillBlockOn`)
+    /// resumed, because the other thread completed or abandoned the query.
+    DidUnblock {
+        /// The id of the thread we were blocked on.
+        other_thread_id: ThreadId,
+
+        /// The database-key for the affected value. Implements `Debug`.
+        database_key: DatabaseKeyIndex,
+
+        /// How long the thread was blocked.
+        waited: Duration,
+    },
+
+    /// Indicates that a thread has been blocked on another thread (see `WillBlockOn`)
+    /// for longer than the threshold of the block watchdog, which is configured with
+    /// [`Storage::builder`](`crate::Storage::builder`).
+    ///
+    /// Reported once per wait, while the thread is still blocked.
+    ///
+    /// The wait chain only has the backtraces of the blocked threads. The thread at the
+    /// end of the chain is running, so its query stack is changing and can't be captured
+    /// without it publishing every query it pushes; only the query the last blocked thread
+    /// waits for is known to be on its stack.
+    DidExceedBlockThreshold {
+        /// The database-key for the query the thread is waiting for. Implements `Debug`.
+        database_key: DatabaseKeyIndex,
+
+        /// The thread, the thread it is blocked on, the thread that one is blocked on
+        /// and so on. See [`WaitGraph::wait_chain`](`crate::WaitGraph::wait_chain`).
+        wait_chain: Vec<BlockedThread>,
+    },
+
     /// Indicates that the function for this query will be executed.
     /// This is either because it has never executed before or because
     /// its inputs may be out of date.
@@ -58,6 +96,27 @@ pub enum EventKind {
         database_key: DatabaseKeyIndex,
     },
 
+    /// Indicates that the function for this query finished executing
+    /// and its new value was memoized.
+    DidExecute {
+        /// The database-key for the affected value. Implements `Debug`.
+        database_key: DatabaseKeyIndex,
+
+        /// How long the execution took, including the queries it called
+        /// and all iterations of a cycle it is the head of.
+        duration: Duration,
+
+        /// Whether the new value was equal to the previous one, in which case
+        /// its `changed_at` revision was kept ("backdated").
+        backdated: bool,
+    },
+
+    /// The value memoized for a query was evicted by its LRU capacity.
+    DidEvictMemo {
+        /// The database-key for the affected value. Implements `Debug`.
+        database_key: DatabaseKeyIndex,
+    },
+
     /// Salsa starts a new fixpoint iteration for the cycle with `database_key` as its
     /// outermost cycle.
     WillIterateCycle {
@@ -73,6 +132,21 @@ pub enum EventKind {
         iteration: u8,
     },
 
+    /// Indicates that a field of an input was set with its setter.
+    DidSetInputField {
+        /// The database-key for the field. Implements `Debug`.
+        database_key: DatabaseKeyIndex,
+
+        /// The durability of the new value.
+        durability: Durability,
+    },
+
+    /// A new revision started, because an input was changed.
+    DidStartRevision {
+        /// The new revision.
+        revision: Revision,
+    },
+
     /// Indicates that `unwind_if_cancelled` was called and salsa will check if
     /// the current revision has been cancelled.
     WillCheckCancellation,
@@ -133,3 +207,266 @@ pub enum EventKind {
         revision: Revision,
     },
 }
+
+impl EventKind {
+    /// Returns which kind of event this is, without its data.
+    pub fn tag(&self) -> EventKindTag {
+        match self {
+            EventKind::DidValidateMemoizedValue { .. } => EventKindTag::DidValidateMemoizedValue,
+            EventKind::WillBlockOn { .. } => EventKindTag::WillBlockOn,
+            EventKind::DidUnblock { .. } => EventKindTag::DidUnblock,
+            EventKind::DidExceedBlockThreshold { .. } => EventKindTag::DidExceedBlockThreshold,
+            EventKind::WillExecute { .. } => EventKindTag::WillExecute,
+            EventKind::DidExecute { .. } => EventKindTag::DidExecute,
+            EventKind::DidEvictMemo { .. } => EventKindTag::DidEvictMemo,
+            EventKind::WillIterateCycle { .. } => EventKindTag::WillIterateCycle,
+            EventKind::DidFinalizeCycle { .. } => EventKindTag::DidFinalizeCycle,
+            EventKind::DidSetInputField { .. } => EventKindTag::DidSetInputField,
+            EventKind::DidStartRevision { .. } => EventKindTag::DidStartRevision,
+            EventKind::WillCheckCancellation => EventKindTag::WillCheckCancellation,
+            EventKind::DidSetCancellationFlag => EventKindTag::DidSetCancellationFlag,
+            EventKind::WillDiscardStaleOutput { .. } => EventKindTag::WillDiscardStaleOutput,
+            EventKind::DidDiscard { .. } => EventKindTag::DidDiscard,
+            EventKind::DidDiscardAccumulated { .. } => EventKindTag::DidDiscardAccumulated,
+            EventKind::DidInternValue { .. } => EventKindTag::DidInternValue,
+            EventKind::DidReuseInternedValue { .. } => EventKindTag::DidReuseInternedValue,
+            EventKind::DidValidateInternedValue { .. } => EventKindTag::DidValidateInternedValue,
+        }
+    }
+}
+
+/// Identifies a variant of [`EventKind`], to select the events an event listener is interested in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum EventKindTag {
+    /// Identifies [`EventKind::DidValidateMemoizedValue`] events.
+    DidValidateMemoizedValue,
+
+    /// Identifies [`EventKind::WillBlockOn`] events.
+    WillBlockOn,
+
+    /// Identifies [`EventKind::DidUnblock`] events.
+    DidUnblock,
+
+    /// Identifies [`EventKind::DidExceedBlockThreshold`] events.
+    DidExceedBlockThreshold,
+
+    /// Identifies [`EventKind::WillExecute`] events.
+    WillExecute,
+
+    /// Identifies [`EventKind::DidExecute`] events.
+    DidExecute,
+
+    /// Identifies [`EventKind::DidEvictMemo`] events.
+    DidEvictMemo,
+
+    /// Identifies [`EventKind::WillIterateCycle`] events.
+    WillIterateCycle,
+
+    /// Identifies [`EventKind::DidFinalizeCycle`] events.
+    DidFinalizeCycle,
+
+    /// Identifies [`EventKind::DidSetInputField`] events.
+    DidSetInputField,
+
+    /// Identifies [`EventKind::DidStartRevision`] events.
+    DidStartRevision,
+
+    /// Identifies [`EventKind::WillCheckCancellation`] events.
+    WillCheckCancellation,
+
+    /// Identifies [`EventKind::DidSetCancellationFlag`] events.
+    DidSetCancellationFlag,
+
+    /// Identifies [`EventKind::WillDiscardStaleOutput`] events.
+    WillDiscardStaleOutput,
+
+    /// Identifies [`EventKind::DidDiscard`] events.
+    DidDiscard,
+
+    /// Identifies [`EventKind::DidDiscardAccumulated`] events.
+    DidDiscardAccumulated,
+
+    /// Identifies [`EventKind::DidInternValue`] events.
+    DidInternValue,
+
+    /// Identifies [`EventKind::DidReuseInternedValue`] events.
+    DidReuseInternedValue,
+
+    /// Identifies [`EventKind::DidValidateInternedValue`] events.
+    DidValidateInternedValue,
+}
+
+impl EventKindTag {
+    const fn bit(self) -> u32 {
+        1 << self as u8
+    }
+}
+
+/// A set of [`EventKindTag`]s an event listener is interested in.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    kinds: u32,
+}
+
+impl EventFilter {
+    /// Selects all events.
+    pub const ALL: Self = Self { kinds: u32::MAX };
+
+    /// Selects no events.
+    pub const NONE: Self = Self { kinds: 0 };
+
+    /// Returns this filter with the events of kind `tag` selected as well.
+    pub const fn with(self, tag: EventKindTag) -> Self {
+        Self {
+            kinds: self.kinds | tag.bit(),
+        }
+    }
+
+    /// Returns `true` if the events of kind `tag` are selected.
+    pub const fn contains(self, tag: EventKindTag) -> bool {
+        self.kinds & tag.bit() != 0
+    }
+}
+
+impl From<EventKindTag> for EventFilter {
+    fn from(tag: EventKindTag) -> Self {
+        Self::NONE.with(tag)
+    }
+}
+
+impl FromIterator<EventKindTag> for EventFilter {
+    fn from_iter<I: IntoIterator<Item = EventKindTag>>(tags: I) -> Self {
+        tags.into_iter().fold(Self::NONE, Self::with)
+    }
+}
+
+/// Identifies an event listener registered with
+/// [`DatabaseExt::add_event_listener`](`crate::DatabaseExt::add_event_listener`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EventListenerId(u64);
+
+/// The event listeners of a database, which can be registered and unregistered at any time.
+#[derive(Default)]
+pub(crate) struct EventListeners {
+    /// The union of the filters of all listeners. Checked before an event is created,
+    /// so that events nobody listens to cost a single atomic load.
+    kinds: AtomicU32,
+
+    /// The registered listeners, null if there are none.
+    ///
+    /// The list is replaced rather than mutated when listeners change, and the replaced list
+    /// is only freed once no thread dispatches events to it. Events are therefore dispatched
+    /// without taking a lock, and listeners can register or unregister listeners.
+    listeners: Atomic<Vec<Listener>>,
+
+    /// The id of the next listener, locked while the listeners are changed.
+    next_id: Mutex<u64>,
+}
+
+#[derive(Clone)]
+struct Listener {
+    id: EventListenerId,
+    filter: EventFilter,
+    callback: Arc<dyn Fn(Event) + Send + Sync>,
+}
+
+impl EventListeners {
+    pub(crate) fn add(
+        &self,
+        filter: EventFilter,
+        callback: Box<dyn Fn(Event) + Send + Sync>,
+    ) -> EventListenerId {
+        let mut next_id = self.next_id.lock();
+        let id = EventListenerId(*next_id);
+        *next_id += 1;
+        self.update(|listeners| {
+            listeners.push(Listener {
+                id,
+                filter,
+                callback: Arc::from(callback),
+            });
+            true
+        });
+        self.kinds.fetch_or(filter.kinds, Ordering::Relaxed);
+        id
+    }
+
+    /// Unregisters the listener `id`, returning `false` if it wasn't registered.
+    pub(crate) fn remove(&self, id: EventListenerId) -> bool {
+        let _next_id = self.next_id.lock();
+        let mut kinds = 0;
+        let removed = self.update(|listeners| {
+            let Some(index) = listeners.iter().position(|listener| listener.id == id) else {
+                return false;
+            };
+            listeners.remove(index);
+            kinds = listeners
+                .iter()
+                .fold(0, |kinds, listener| kinds | listener.filter.kinds);
+            true
+        });
+        if removed {
+            self.kinds.store(kinds, Ordering::Relaxed);
+        }
+        removed
+    }
+
+    /// Publishes a copy of the listeners changed by `change`, unless it returns `false`.
+    ///
+    /// Must be called with `next_id` locked, so that concurrent changes aren't lost.
+    fn update(&self, change: impl FnOnce(&mut Vec<Listener>) -> bool) -> bool {
+        let guard = &epoch::pin();
+        let current = self.listeners.load(Ordering::Acquire, guard);
+        // SAFETY: The list is only freed once no thread is pinned that could have loaded it.
+        let mut listeners = unsafe { current.as_ref() }.cloned().unwrap_or_default();
+        if !change(&mut listeners) {
+            return false;
+        }
+
+        let replaced = self
+            .listeners
+            .swap(Owned::new(listeners), Ordering::AcqRel, guard);
+        if !replaced.is_null() {
+            // SAFETY: The replaced list can no longer be loaded, and is freed once the threads
+            // that may have loaded it are unpinned.
+            unsafe { guard.defer_destroy(replaced) };
+        }
+        true
+    }
+
+    /// Returns `true` if any listener is interested in the events of kind `tag`.
+    #[inline]
+    pub(crate) fn is_listening(&self, tag: EventKindTag) -> bool {
+        self.kinds.load(Ordering::Relaxed) & tag.bit() != 0
+    }
+
+    /// Creates the event and passes it to each listener interested in the events of kind `tag`.
+    pub(crate) fn dispatch(&self, tag: EventKindTag, event: &dyn Fn() -> Event) {
+        let guard = &epoch::pin();
+        let listeners = self.listeners.load(Ordering::Acquire, guard);
+        // SAFETY: The list is only freed once no thread is pinned that could have loaded it.
+        let Some(listeners) = (unsafe { listeners.as_ref() }) else {
+            return;
+        };
+        for listener in listeners {
+            if listener.filter.contains(tag) {
+                let event = event();
+                debug_assert_eq!(event.kind.tag(), tag);
+                (listener.callback)(event);
+            }
+        }
+    }
+}
+
+impl Drop for EventListeners {
+    fn drop(&mut self) {
+        // SAFETY: No other thread can access the listeners anymore.
+        unsafe {
+            let listeners = self.listeners.load(Ordering::Relaxed, epoch::unprotected());
+            if !listeners.is_null() {
+                drop(listeners.into_owned());
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::sync::atomic::{AtomicBool, Ordering};
+
+use rustc_hash::FxHashMap;
+
+use crate::sync::Mutex;
+use crate::zalsa::Zalsa;
+use crate::{DatabaseKeyIndex, Revision};
+
+/// Why a query was executed, as returned by
+/// [`DatabaseExt::last_execution_reason`](`crate::DatabaseExt::last_execution_reason`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExecutionReason {
+    /// The query had no memoized value.
+    New,
+
+    /// The memoized value of the query had been evicted.
+    Evicted,
+
+    /// An input of the memoized value changed.
+    ///
+    /// The first entry is the input of the query that changed. If that input is itself
+    /// a query that was re-executed, the following entries explain why, down to the
+    /// input field or other key that caused the change.
+    InputChanged(Vec<ChangedInput>),
+
+    /// The memoized value read untracked state and can't be verified.
+    Untracked,
+
+    /// The memoized value was assigned by another query in an earlier revision,
+    /// but hasn't been assigned again.
+    Assigned,
+
+    /// The memoized value was the provisional result of a cycle, or the query is executed
+    /// again to iterate towards the fixpoint of a cycle.
+    Cycle,
+
+    /// The memoized value couldn't be verified for a reason that isn't recorded, e.g. because
+    /// the memo of an input was discarded when a new revision started.
+    Unknown,
+}
+
+impl ExecutionReason {
+    /// Returns the key that caused the re-execution, if it was caused by a changed input.
+    pub fn root_cause(&self) -> Option<&ChangedInput> {
+        match self {
+            ExecutionReason::InputChanged(chain) => chain.last(),
+            _ => None,
+        }
+    }
+}
+
+/// An input that changed since the memoized value of a query was last verified.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChangedInput {
+    key: DatabaseKeyIndex,
+    changed_at: Option<Revision>,
+}
+
+impl ChangedInput {
+    fn new(zalsa: &Zalsa, key: DatabaseKeyIndex) -> Self {
+        let ingredient = zalsa.lookup_ingredient(key.ingredient_index());
+        let changed_at = match ingredient.input_stamp(zalsa, key.key_index()) {
+            Some(stamp) => Some(stamp.changed_at),
+            None => ingredient
+                .query_info(zalsa, key.key_index())
+                .map(|info| info.changed_at()),
+        };
+
+        Self { key, changed_at }
+    }
+
+    /// Returns the key of the input, e.g. the field of an input struct or a query.
+    pub fn key(&self) -> DatabaseKeyIndex {
+        self.key
+    }
+
+    /// Returns the revision in which the input last changed, if known.
+    ///
+    /// The revision is known for input fields and queries.
+    pub fn changed_at(&self) -> Option<Revision> {
+        self.changed_at
+    }
+}
+
+/// Records why queries executed, if enabled with
+/// [`DatabaseExt::record_execution_reasons`](`crate::DatabaseExt::record_execution_reasons`).
+#[derive(Default)]
+pub(crate) struct ExecutionReasons {
+    enabled: AtomicBool,
+
+    /// The first changed input found when deep-verifying a memo, with the revision it was found in.
+    changed_inputs: Mutex<FxHashMap<DatabaseKeyIndex, (Revision, DatabaseKeyIndex)>>,
+
+    /// The reason of the last execution of each query.
+    reasons: Mutex<FxHashMap<DatabaseKeyIndex, ExecutionReason>>,
+}
+
+impl ExecutionReasons {
+    /// Enables or disables recording, discarding the recorded reasons when disabled.
+    pub(crate) fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.changed_inputs.lock().clear();
+            self.reasons.lock().clear();
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Records that deep-verifying the memo of `query` found that `input` changed.
+    pub(crate) fn record_changed_input(
+        &self,
+        zalsa: &Zalsa,
+        query: DatabaseKeyIndex,
+        input: DatabaseKeyIndex,
+    ) {
+        self.changed_inputs
+            .lock()
+            .insert(query, (zalsa.current_revision(), input));
+    }
+
+    /// Records that `query` is about to execute.
+    ///
+    /// If deep-verifying its memo found a changed input in this revision, that is the reason.
+    /// Otherwise, the reason is computed by `reason` from the previous memo.
+    pub(crate) fn record_execution(
+        &self,
+        zalsa: &Zalsa,
+        query: DatabaseKeyIndex,
+        reason: impl FnOnce() -> ExecutionReason,
+    ) {
+        let changed_input = self.changed_inputs.lock().remove(&query);
+        let reason = match changed_input {
+            Some((revision, input)) if revision == zalsa.current_revision() => {
+                let mut chain = vec![ChangedInput::new(zalsa, input)];
+                if let Some(ExecutionReason::InputChanged(causes)) = self.reasons.lock().get(&input)
+                {
+                    chain.extend_from_slice(causes);
+                }
+                ExecutionReason::InputChanged(chain)
+            }
+            _ => reason(),
+        };
+
+        self.reasons.lock().insert(query, reason);
+    }
+
+    /// Records that `query` is executed again to iterate towards the fixpoint of a cycle.
+    pub(crate) fn record_iteration(&self, query: DatabaseKeyIndex) {
+        self.reasons.lock().insert(query, ExecutionReason::Cycle);
+    }
+
+    pub(crate) fn last(&self, query: DatabaseKeyIndex) -> Option<ExecutionReason> {
+        self.reasons.lock().get(&query).cloned()
+    }
+}
@@ -0,0 +1,398 @@
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::hash::Hash;
+
+use crate::durability::Durability;
+use crate::function::VerifyResult;
+use crate::hash::{FxHashSet, FxIndexSet};
+use crate::ingredient::{Ingredient, Jar, Location};
+use crate::sync::{Arc, Mutex, OnceLock};
+use crate::table::memo::MemoTableTypes;
+use crate::zalsa::{HasJar, IngredientIndex, JarKind, Zalsa};
+use crate::zalsa_local::{QueryEdge, ZalsaLocal};
+use crate::{DatabaseKeyIndex, Id, Revision};
+
+/// A dependency of a query on state outside of the database, such as an environment
+/// variable, the modification time of a file or the clock.
+///
+/// See [`Database::report_external_read`](`crate::Database::report_external_read`).
+pub struct ExternalRead {
+    key: Box<dyn ExternalKey>,
+    validator: Box<dyn Validate>,
+    durability: Durability,
+}
+
+impl ExternalRead {
+    /// Creates an external dependency on the state identified by `key`.
+    ///
+    /// `validator` observes the current state for `key`. It is invoked when the dependency
+    /// is first reported and whenever a query depending on it is verified in a new revision;
+    /// the dependency is considered changed if the observed value differs from the previous one.
+    /// As it can be invoked frequently, it should be cheap.
+    pub fn new<K, V>(key: K, validator: impl Fn(&K) -> V + Send + Sync + 'static) -> Self
+    where
+        K: Hash + Eq + fmt::Debug + Send + Sync + 'static,
+        V: PartialEq + Send + Sync + 'static,
+    {
+        Self {
+            key: Box::new(key),
+            validator: Box::new(Validator {
+                validator,
+                value: None,
+                phantom: std::marker::PhantomData::<fn(&K)>,
+            }),
+            durability: Durability::LOW,
+        }
+    }
+
+    /// Sets how often the external state is expected to change, [`Durability::LOW`] by default.
+    ///
+    /// Like a query reading an input of the same durability, a query depending on the external
+    /// state is only re-validated (and the validator only invoked) in revisions in which a value
+    /// of at least this durability changed. Use [`Database::synthetic_write`](`crate::Database::synthetic_write`)
+    /// to force re-validation after such state changed.
+    ///
+    /// Only the durability reported with the first read of a key is retained.
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+}
+
+impl fmt::Debug for ExternalRead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ExternalRead").field(&self.key).finish()
+    }
+}
+
+/// A type-erased key identifying external state.
+trait ExternalKey: Any + fmt::Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn key_eq(&self, other: &dyn ExternalKey) -> bool;
+    fn key_hash(&self) -> u64;
+}
+
+impl<K> ExternalKey for K
+where
+    K: Hash + Eq + fmt::Debug + Send + Sync + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn key_eq(&self, other: &dyn ExternalKey) -> bool {
+        other.as_any().downcast_ref::<K>() == Some(self)
+    }
+
+    fn key_hash(&self) -> u64 {
+        crate::hash::hash(&(TypeId::of::<K>(), self))
+    }
+}
+
+/// A type-erased validator of external state.
+trait Validate: Send + Sync {
+    /// Observes the current state for `key` and returns `true` if it differs
+    /// from the previously observed state.
+    fn validate(&mut self, key: &dyn Any) -> bool;
+}
+
+struct Validator<K, V, F> {
+    validator: F,
+    value: Option<V>,
+    phantom: std::marker::PhantomData<fn(&K)>,
+}
+
+impl<K, V, F> Validate for Validator<K, V, F>
+where
+    K: 'static,
+    V: PartialEq + Send + Sync,
+    F: Fn(&K) -> V + Send + Sync,
+{
+    fn validate(&mut self, key: &dyn Any) -> bool {
+        let key = key.downcast_ref::<K>().expect("key of an unexpected type");
+        let value = (self.validator)(key);
+        let changed = self.value.as_ref() != Some(&value);
+        self.value = Some(value);
+        changed
+    }
+}
+
+/// The state of an external dependency.
+struct ExternalSlot {
+    key: Box<dyn ExternalKey>,
+    durability: Durability,
+    state: Mutex<ExternalState>,
+}
+
+struct ExternalState {
+    validator: Box<dyn Validate>,
+
+    /// The last revision in which the observed state changed.
+    changed_at: Revision,
+
+    /// The last revision in which the state was observed.
+    verified_at: Revision,
+}
+
+/// Jar of the [`ExternalIngredient`], which is registered with every database.
+pub(crate) struct ExternalJar;
+
+impl HasJar for ExternalJar {
+    type Jar = ExternalJar;
+    const KIND: JarKind = JarKind::Struct;
+}
+
+impl Jar for ExternalJar {
+    fn create_ingredients(
+        _zalsa: &mut Zalsa,
+        first_index: IngredientIndex,
+    ) -> Vec<Box<dyn Ingredient>> {
+        vec![Box::new(ExternalIngredient::new(first_index))]
+    }
+
+    fn id_struct_type_id() -> TypeId {
+        TypeId::of::<ExternalJar>()
+    }
+}
+
+/// Ingredient tracking the external dependencies reported by queries.
+///
+/// Each distinct key is assigned an [`Id`]; queries depend on it through an ordinary
+/// input edge, which is verified by invoking the validator registered for the key
+/// (at most once per revision). Keys no memo depends on anymore are freed when
+/// a new revision starts, see [`ExternalIngredient::collect_unreferenced`].
+pub(crate) struct ExternalIngredient {
+    index: IngredientIndex,
+
+    /// Maps the hash of each key to its id.
+    key_map: Mutex<hashbrown::HashTable<Id>>,
+
+    /// The external dependencies, indexed by id. Freed slots are empty.
+    slots: boxcar::Vec<OnceLock<ExternalSlot>>,
+
+    /// The ids of the freed slots, which are reused for new keys.
+    free_ids: Mutex<Vec<Id>>,
+
+    /// The number of keys at which the next collection happens.
+    collect_at: usize,
+}
+
+/// The minimum number of keys before unreferenced keys are collected.
+const MIN_COLLECT_AT: usize = 64;
+
+impl ExternalIngredient {
+    fn new(index: IngredientIndex) -> Self {
+        Self {
+            index,
+            key_map: Mutex::new(hashbrown::HashTable::new()),
+            slots: boxcar::Vec::new(),
+            free_ids: Mutex::new(Vec::new()),
+            collect_at: MIN_COLLECT_AT,
+        }
+    }
+
+    fn try_slot(&self, id: Id) -> Option<&ExternalSlot> {
+        self.slots.get(id.index() as usize)?.get()
+    }
+
+    fn slot(&self, id: Id) -> &ExternalSlot {
+        self.try_slot(id)
+            .unwrap_or_else(|| panic!("external read {id:?} has been freed"))
+    }
+
+    /// Records `read` as a dependency of the active query.
+    pub(crate) fn report_read(&self, zalsa: &Zalsa, zalsa_local: &ZalsaLocal, read: ExternalRead) {
+        let ExternalRead {
+            key,
+            mut validator,
+            durability,
+        } = read;
+        let current_revision = zalsa.current_revision();
+        let hash = key.key_hash();
+
+        let existing = self.find(hash, &*key);
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                // Observe the initial state without holding the lock, the validator may be slow.
+                validator.validate(key.as_any());
+
+                let mut key_map = self.key_map.lock();
+                // Another thread may have reported the same key in the meantime.
+                let existing = key_map
+                    .find(hash, |&id| self.slot(id).key.key_eq(&*key))
+                    .copied();
+                match existing {
+                    Some(id) => id,
+                    None => {
+                        let slot = ExternalSlot {
+                            key,
+                            durability,
+                            state: Mutex::new(ExternalState {
+                                validator,
+                                changed_at: current_revision,
+                                verified_at: current_revision,
+                            }),
+                        };
+                        let id = self.allocate(slot);
+                        key_map.insert_unique(hash, id, |&id| self.slot(id).key.key_hash());
+                        id
+                    }
+                }
+            }
+        };
+
+        let slot = self.slot(id);
+        let changed_at = self.verify(id, current_revision);
+        zalsa_local.report_tracked_read_simple(
+            DatabaseKeyIndex::new(self.index, id),
+            slot.durability,
+            changed_at,
+        );
+    }
+
+    fn find(&self, hash: u64, key: &dyn ExternalKey) -> Option<Id> {
+        let key_map = self.key_map.lock();
+        key_map
+            .find(hash, |&id| self.slot(id).key.key_eq(key))
+            .copied()
+    }
+
+    /// Stores `slot` in a freed slot or a new one and returns its id.
+    fn allocate(&self, slot: ExternalSlot) -> Id {
+        if let Some(id) = self.free_ids.lock().pop() {
+            self.slots[id.index() as usize].get_or_init(|| slot);
+            return id;
+        }
+
+        let index = self.slots.push(OnceLock::from(slot));
+        // SAFETY: The index is smaller than `Id::MAX_U32` as long as the number of
+        // reported keys is.
+        unsafe { Id::from_index(index as u32) }
+    }
+
+    /// Returns `true` if enough keys were reported since the last collection to warrant
+    /// looking for unreferenced ones.
+    pub(crate) fn should_collect(&mut self) -> bool {
+        self.key_map.get_mut().len() >= self.collect_at
+    }
+
+    /// Frees the keys that are not in `referenced`, the keys some memo depends on.
+    pub(crate) fn collect_unreferenced(&mut self, referenced: &FxHashSet<Id>) {
+        let key_map = self.key_map.get_mut();
+        let free_ids = self.free_ids.get_mut();
+        for index in 0..self.slots.count() {
+            let slot = self.slots.get_mut(index).expect("slot out of bounds");
+            let Some(external) = slot.get() else {
+                continue;
+            };
+            // SAFETY: `index` was returned by `push`.
+            let id = unsafe { Id::from_index(index as u32) };
+            if referenced.contains(&id) {
+                continue;
+            }
+
+            crate::tracing::debug!("freeing external state {:?}", external.key);
+            let hash = external.key.key_hash();
+            key_map
+                .find_entry(hash, |&other| other == id)
+                .expect("external key missing from the key map")
+                .remove();
+            *slot = OnceLock::new();
+            free_ids.push(id);
+        }
+
+        self.collect_at = (key_map.len() * 2).max(MIN_COLLECT_AT);
+    }
+
+    /// Validates the external state of `id` in the current revision and returns
+    /// the revision in which it last changed.
+    fn verify(&self, id: Id, current_revision: Revision) -> Revision {
+        let slot = self.slot(id);
+        let mut state = slot.state.lock();
+        if state.verified_at < current_revision {
+            if state.validator.validate(slot.key.as_any()) {
+                crate::tracing::debug!("external state {:?} changed", slot.key);
+                state.changed_at = current_revision;
+            }
+            state.verified_at = current_revision;
+        }
+        state.changed_at
+    }
+}
+
+impl Ingredient for ExternalIngredient {
+    fn debug_name(&self) -> &'static str {
+        "external"
+    }
+
+    fn location(&self) -> &'static Location {
+        &Location {
+            file: file!(),
+            line: line!(),
+        }
+    }
+
+    fn jar_kind(&self) -> JarKind {
+        JarKind::Struct
+    }
+
+    unsafe fn maybe_changed_after(
+        &self,
+        zalsa: &Zalsa,
+        _db: crate::database::RawDatabase<'_>,
+        input: Id,
+        revision: Revision,
+    ) -> VerifyResult {
+        VerifyResult::changed_if(self.verify(input, zalsa.current_revision()) > revision)
+    }
+
+    fn collect_minimum_serialized_edges(
+        &self,
+        _zalsa: &Zalsa,
+        _edge: QueryEdge,
+        _serialized_edges: &mut FxIndexSet<QueryEdge>,
+        _visited_edges: &mut FxHashSet<QueryEdge>,
+    ) {
+        panic!(
+            "the inputs of a persistable tracked function must be persistable: external reads are not persistable"
+        )
+    }
+
+    fn flatten_cycle_head_dependencies(
+        &self,
+        _zalsa: &Zalsa,
+        id: Id,
+        flattened_input_outputs: &mut FxIndexSet<QueryEdge>,
+        _seen: &mut FxHashSet<DatabaseKeyIndex>,
+    ) {
+        flattened_input_outputs.insert(QueryEdge::input(DatabaseKeyIndex::new(self.index, id)));
+    }
+
+    fn ingredient_index(&self) -> IngredientIndex {
+        self.index
+    }
+
+    fn memo_table_types(&self) -> &Arc<MemoTableTypes> {
+        unreachable!("external reads do not allocate pages")
+    }
+
+    fn memo_table_types_mut(&mut self) -> &mut Arc<MemoTableTypes> {
+        unreachable!("external reads do not allocate pages")
+    }
+
+    fn fmt_index(&self, index: Id, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.try_slot(index) {
+            Some(slot) => write!(fmt, "external({:?})", slot.key),
+            None => write!(fmt, "external(<freed>)"),
+        }
+    }
+}
+
+impl fmt::Debug for ExternalIngredient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("index", &self.index)
+            .finish()
+    }
+}
@@ -16,12 +16,11 @@ use crate::key::DatabaseKeyIndex;
 use crate::plumbing::{self, MemoIngredientMap};
 use crate::salsa_struct::SalsaStructInDb;
 use crate::sync::Arc;
-use crate::table::Table;
 use crate::table::memo::MemoTableTypes;
 use crate::views::DatabaseDownCaster;
 use crate::zalsa::{IngredientIndex, JarKind, MemoIngredientIndex, Zalsa};
 use crate::zalsa_local::{QueryEdge, QueryOriginRef};
-use crate::{Cycle, Id, Revision};
+use crate::{Cycle, Event, EventKind, EventKindTag, Id, Revision, Runtime};
 
 #[cfg(feature = "accumulator")]
 mod accumulated;
@@ -34,10 +33,14 @@ mod fetch;
 mod inputs;
 mod maybe_changed_after;
 mod memo;
+#[cfg(feature = "salsa_unstable")]
+mod profile;
 mod specify;
 mod sync;
 
 pub use eviction::{EvictionPolicy, HasCapacity, Lru, NoopEviction};
+#[cfg(feature = "salsa_unstable")]
+pub use profile::QueryProfile;
 
 pub type Memo<C> = memo::Memo<'static, C>;
 
@@ -200,6 +203,10 @@ pub struct IngredientImpl<C: Configuration> {
     /// we don't know that we can trust the database to give us the same runtime
     /// everytime and so forth.
     deleted_entries: DeletedEntries<C>,
+
+    /// Execution and cache statistics, recorded while profiling is enabled.
+    #[cfg(feature = "salsa_unstable")]
+    profile: profile::Profile,
 }
 
 impl<C> IngredientImpl<C>
@@ -218,6 +225,8 @@ where
             deleted_entries: Default::default(),
             view_caster: OnceLock::new(),
             sync_table: SyncTable::new(index),
+            #[cfg(feature = "salsa_unstable")]
+            profile: Default::default(),
         }
     }
 
@@ -504,6 +513,24 @@ where
         self.origin(zalsa, key)
     }
 
+    fn query_info(&self, zalsa: &Zalsa, key: Id) -> Option<crate::QueryInfo> {
+        self.query_info(zalsa, key)
+    }
+
+    fn memoized_keys(&self, zalsa: &Zalsa) -> Vec<Id> {
+        self.memoized_keys(zalsa)
+    }
+
+    #[cfg(feature = "salsa_unstable")]
+    fn query_profile(&self) -> Option<QueryProfile> {
+        Some(self.profile.snapshot(self.index, C::DEBUG_NAME))
+    }
+
+    #[cfg(feature = "salsa_unstable")]
+    fn reset_query_profile(&self) {
+        self.profile.reset();
+    }
+
     fn mark_validated_output(
         &self,
         zalsa: &Zalsa,
@@ -528,15 +555,30 @@ where
         true
     }
 
-    fn reset_for_new_revision(&mut self, table: &mut Table) {
+    fn reset_for_new_revision(&mut self, runtime: &mut Runtime) {
+        let report_evictions = runtime.is_listening(EventKindTag::DidEvictMemo);
+        let mut evicted = Vec::new();
+
+        let table = runtime.table_mut();
         self.eviction.for_each_evicted(|evict| {
             let ingredient_index = table.ingredient_index(evict);
-            Self::evict_value_from_memo_for(
+            if Self::evict_value_from_memo_for(
                 table.memos_mut(evict),
                 self.memo_ingredient_indices.get(ingredient_index),
-            )
+            ) && report_evictions
+            {
+                evicted.push(evict);
+            }
         });
 
+        for id in evicted {
+            runtime.event(EventKindTag::DidEvictMemo, &|| {
+                Event::new(EventKind::DidEvictMemo {
+                    database_key: self.database_key_index(id),
+                })
+            });
+        }
+
         self.deleted_entries.clear();
     }
 
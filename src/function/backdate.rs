@@ -12,20 +12,22 @@ where
     /// If the value/durability of this memo is equal to what is found in `revisions`/`value`,
     /// then update `revisions.changed_at` to match `self.revisions.changed_at`. This is invoked
     /// on an old memo when a new memo has been produced to check whether there have been changed.
+    ///
+    /// Returns `true` if the memo was backdated.
     pub(super) fn backdate_if_appropriate<'db>(
         &self,
         old_memo: &Memo<'db, C>,
         index: DatabaseKeyIndex,
         revisions: &mut QueryRevisions,
         value: &C::Output<'db>,
-    ) {
+    ) -> bool {
         // We've seen issues where queries weren't re-validated when backdating provisional values
         // in ty. This is more of a bandaid because we're close to a release and don't have the time to prove
         // right now whether backdating could be made safe for queries participating in queries.
         // TODO: Write a test that demonstrates that backdating queries participating in a cycle isn't safe
         // OR write many tests showing that it is (and fixing the case where it didn't correctly account for today).
         if !revisions.cycle_heads().is_empty() || old_memo.may_be_provisional() {
-            return;
+            return false;
         }
 
         if let Some(old_value) = &old_memo.value {
@@ -50,8 +52,11 @@ where
                 }
 
                 revisions.changed_at = old_memo.revisions.changed_at;
+                return true;
             }
         }
+
+        false
     }
 }
 
@@ -4,7 +4,7 @@ use crate::function::{Configuration, IngredientImpl};
 use crate::hash::FxIndexSet;
 use crate::zalsa::Zalsa;
 use crate::zalsa_local::{QueryOriginRef, QueryRevisions, output_edges};
-use crate::{DatabaseKeyIndex, Event, EventKind};
+use crate::{DatabaseKeyIndex, Event, EventKind, EventKindTag};
 
 impl<C> IngredientImpl<C>
 where
@@ -60,7 +60,7 @@ fn diff_outputs_on_revision(
 }
 
 fn report_stale_output(zalsa: &Zalsa, key: DatabaseKeyIndex, output: DatabaseKeyIndex) {
-    zalsa.event(&|| {
+    zalsa.event(EventKindTag::WillDiscardStaleOutput, &|| {
         Event::new(EventKind::WillDiscardStaleOutput {
             execute_key: key,
             output_key: output,
@@ -1,3 +1,6 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Instant;
+
 use smallvec::SmallVec;
 
 use crate::active_query::CompletedQuery;
@@ -11,9 +14,11 @@ use crate::plumbing::ZalsaLocal;
 use crate::sync::thread;
 use crate::tracked_struct::Identity;
 use crate::zalsa::{MemoIngredientIndex, Zalsa};
-use crate::zalsa_local::{ActiveQueryGuard, QueryEdge, QueryEdgeKind, QueryRevisions};
+use crate::zalsa_local::{
+    ActiveQueryGuard, QueryEdge, QueryEdgeKind, QueryOriginRef, QueryRevisions,
+};
 use crate::{Cancelled, Cycle, tracing};
-use crate::{DatabaseKeyIndex, Event, EventKind, Id};
+use crate::{DatabaseKeyIndex, Event, EventKind, EventKindTag, ExecutionReason, Id};
 
 impl<C> IngredientImpl<C>
 where
@@ -48,18 +53,44 @@ where
 
         crate::tracing::info!("{:?}: executing query", database_key_index);
 
-        zalsa.event(&|| {
+        zalsa.event(EventKindTag::WillExecute, &|| {
             Event::new(EventKind::WillExecute {
                 database_key: database_key_index,
             })
         });
 
+        let execution_reasons = zalsa.execution_reasons();
+        if execution_reasons.is_enabled() {
+            execution_reasons.record_execution(zalsa, database_key_index, || match opt_old_memo {
+                None => ExecutionReason::New,
+                Some(old_memo) if old_memo.value.is_none() => ExecutionReason::Evicted,
+                Some(old_memo) => match old_memo.revisions.origin() {
+                    QueryOriginRef::Assigned(_) => ExecutionReason::Assigned,
+                    QueryOriginRef::DerivedUntracked(_) => ExecutionReason::Untracked,
+                    QueryOriginRef::Derived(_) if old_memo.may_be_provisional() => {
+                        ExecutionReason::Cycle
+                    }
+                    QueryOriginRef::Derived(_) => ExecutionReason::Unknown,
+                },
+            });
+        }
+
+        // Only measure the execution time if it is reported.
+        let started = zalsa
+            .runtime()
+            .is_listening(EventKindTag::DidExecute)
+            .then(Instant::now);
+        #[cfg(feature = "salsa_unstable")]
+        let profile = self.profile(zalsa).map(|profile| (profile, Instant::now()));
+
         let (new_value, mut completed_query) = match C::CYCLE_STRATEGY {
             CycleRecoveryStrategy::Panic => {
                 let (new_value, active_query) = Self::execute_query(
                     db,
                     zalsa,
-                    claim_guard.zalsa_local().push_query(database_key_index),
+                    claim_guard
+                        .zalsa_local()
+                        .push_query(zalsa, database_key_index),
                     opt_old_memo,
                 );
 
@@ -84,17 +115,30 @@ where
             }
         };
 
+        let duration = started.map(|started| started.elapsed());
+        #[cfg(feature = "salsa_unstable")]
+        if let Some((profile, started)) = profile {
+            profile.record_execution(started.elapsed());
+        }
+
+        let mut backdated = false;
         if let Some(old_memo) = opt_old_memo {
             // If the new value is equal to the old one, then it didn't
             // really change, even if some of its inputs have. So we can
             // "backdate" its `changed_at` revision to be the same as the
             // old value.
-            self.backdate_if_appropriate(
+            backdated = self.backdate_if_appropriate(
                 old_memo,
                 database_key_index,
                 &mut completed_query.revisions,
                 &new_value,
             );
+            if backdated {
+                #[cfg(feature = "salsa_unstable")]
+                if let Some(profile) = self.profile(zalsa) {
+                    profile.record_backdate();
+                }
+            }
 
             // Diff the new outputs with the old, to discard any no-longer-emitted
             // outputs and update the tracked struct IDs for seeding the next revision.
@@ -112,6 +156,16 @@ where
             memo_ingredient_index,
         );
 
+        if let Some(duration) = duration {
+            zalsa.event(EventKindTag::DidExecute, &|| {
+                Event::new(EventKind::DidExecute {
+                    database_key: database_key_index,
+                    duration,
+                    backdated,
+                })
+            });
+        }
+
         if claim_guard.drop() { None } else { Some(memo) }
     }
 
@@ -153,7 +207,7 @@ where
                     tracing::warn!(
                         "Propagating panic for cycle head that panicked in an earlier execution in that revision"
                     );
-                    Cancelled::PropagatedPanic.throw();
+                    Cancelled::PropagatedPanic(None).throw();
                 }
 
                 // Only use the last provisional memo if it was a cycle head in the last iteration. This is to
@@ -170,7 +224,9 @@ where
             PoisonProvisionalIfPanicking::new(self, zalsa, id, memo_ingredient_index);
 
         let (new_value, completed_query) = loop {
-            let active_query = claim_guard.zalsa_local().push_query(database_key_index);
+            let active_query = claim_guard
+                .zalsa_local()
+                .push_query(zalsa, database_key_index);
 
             // Tracked struct ids that existed in the previous revision
             // but weren't recreated in the last iteration. It's important that we seed the next
@@ -332,6 +388,16 @@ where
                     break (new_value, completed_query);
                 }
                 Err((completed_query, new_iteration)) => {
+                    #[cfg(feature = "salsa_unstable")]
+                    if let Some(profile) = self.profile(zalsa) {
+                        profile.record_cycle_iteration();
+                    }
+
+                    let execution_reasons = zalsa.execution_reasons();
+                    if execution_reasons.is_enabled() {
+                        execution_reasons.record_iteration(database_key_index);
+                    }
+
                     iteration = new_iteration;
                     completed_query
                 }
@@ -389,10 +455,13 @@ where
 
         // Query was not previously executed, or value is potentially
         // stale, or value is absent. Let's execute!
-        let new_value = C::execute(
-            db,
-            C::id_to_input(zalsa, active_query.database_key_index.key_index()),
-        );
+        let input = C::id_to_input(zalsa, active_query.database_key_index.key_index());
+        let new_value = panic::catch_unwind(AssertUnwindSafe(|| C::execute(db, input)))
+            .unwrap_or_else(|payload| {
+                // Remember the panic so threads blocked on this query can report it.
+                active_query.record_panic(&*payload);
+                panic::resume_unwind(payload)
+            });
 
         (new_value, active_query)
     }
@@ -713,7 +782,7 @@ fn try_complete_cycle_head(
 
         *completed_query.revisions.verified_final.get_mut() = true;
 
-        zalsa.event(&|| {
+        zalsa.event(EventKindTag::DidFinalizeCycle, &|| {
             Event::new(EventKind::DidFinalizeCycle {
                 database_key: me,
                 iteration: max_iteration.iteration(),
@@ -729,12 +798,15 @@ fn try_complete_cycle_head(
         panic!("{me:?}: execute: too many cycle iterations")
     });
 
-    zalsa.event(&|| {
+    zalsa.event(EventKindTag::WillIterateCycle, &|| {
         Event::new(EventKind::WillIterateCycle {
             database_key: me,
             iteration: iteration.iteration(),
         })
     });
+    if let Some(chrome_trace) = zalsa.chrome_trace().if_enabled() {
+        chrome_trace.iterate_cycle(me, iteration.iteration());
+    }
 
     tracing::info!(
         "{me:?}: execute: iterate again ({iteration:?})...",
@@ -85,6 +85,11 @@ where
         if can_shallow_update.yes() && !memo.may_be_provisional() {
             self.update_shallow(zalsa, database_key_index, memo, can_shallow_update);
 
+            #[cfg(feature = "salsa_unstable")]
+            if let Some(profile) = self.profile(zalsa) {
+                profile.record_hit();
+            }
+
             // SAFETY: memo is present in memo_map and we have verified that it is
             // still valid for the current revision.
             unsafe { Some(self.extend_memo_lifetime(memo)) }
@@ -141,6 +146,11 @@ where
                 {
                     self.update_shallow(zalsa, database_key_index, old_memo, can_shallow_update);
 
+                    #[cfg(feature = "salsa_unstable")]
+                    if let Some(profile) = self.profile(zalsa) {
+                        profile.record_hit();
+                    }
+
                     // SAFETY: memo is present in memo_map and we have verified that it is
                     // still valid for the current revision.
                     return unsafe { Some(self.extend_memo_lifetime(old_memo)) };
@@ -149,6 +159,11 @@ where
                 let verify_result = self.deep_verify_memo(db, zalsa, old_memo, database_key_index);
 
                 if verify_result.is_unchanged() {
+                    #[cfg(feature = "salsa_unstable")]
+                    if let Some(profile) = self.profile(zalsa) {
+                        profile.record_hit();
+                    }
+
                     // SAFETY: memo is present in memo_map and we have verified that it is
                     // still valid for the current revision.
                     return unsafe { Some(self.extend_memo_lifetime(old_memo)) };
@@ -1,7 +1,8 @@
-use crate::Id;
 use crate::function::{Configuration, IngredientImpl};
+use crate::plumbing::{MemoIngredientMap, SalsaStructInDb};
 use crate::zalsa::Zalsa;
 use crate::zalsa_local::QueryOriginRef;
+use crate::{Id, QueryInfo, QueryOriginKind};
 
 impl<C> IngredientImpl<C>
 where
@@ -12,4 +13,44 @@ where
         self.get_memo_from_table_for(zalsa, key, memo_ingredient_index)
             .map(|m| m.revisions.origin())
     }
+
+    pub(super) fn query_info(&self, zalsa: &Zalsa, key: Id) -> Option<QueryInfo> {
+        let memo_ingredient_index = self.memo_ingredient_index(zalsa, key);
+        let memo = self.get_memo_from_table_for(zalsa, key, memo_ingredient_index)?;
+        let origin = memo.revisions.origin();
+
+        let (origin_kind, assigned_by) = match origin {
+            QueryOriginRef::Assigned(by) => (QueryOriginKind::Assigned, Some(by)),
+            QueryOriginRef::Derived(_) => (QueryOriginKind::Derived, None),
+            QueryOriginRef::DerivedUntracked(_) => (QueryOriginKind::DerivedUntracked, None),
+        };
+
+        Some(QueryInfo {
+            origin: origin_kind,
+            assigned_by,
+            inputs: origin.inputs().collect(),
+            outputs: origin.outputs().collect(),
+            verified_at: memo.verified_at.load(),
+            changed_at: memo.revisions.changed_at,
+            durability: memo.revisions.durability,
+            cycle_heads: memo
+                .cycle_heads()
+                .iter()
+                .map(|head| head.database_key_index)
+                .collect(),
+            has_value: memo.value.is_some(),
+        })
+    }
+
+    pub(super) fn memoized_keys(&self, zalsa: &Zalsa) -> Vec<Id> {
+        <C::SalsaStruct<'_> as SalsaStructInDb>::entries(zalsa)
+            .filter(|entry| {
+                let memo_ingredient_index =
+                    self.memo_ingredient_indices.get(entry.ingredient_index());
+                self.get_memo_from_table_for(zalsa, entry.key_index(), memo_ingredient_index)
+                    .is_some()
+            })
+            .map(|entry| entry.key_index())
+            .collect()
+    }
 }
@@ -388,6 +388,11 @@ where
                     return VerifyResult::changed();
                 }
 
+                #[cfg(feature = "salsa_unstable")]
+                if let Some(profile) = self.profile(zalsa) {
+                    profile.record_deep_verification();
+                }
+
                 let verified_at = old_memo.verified_at.load();
 
                 let result = deep_verify_edges(
@@ -484,6 +489,14 @@ fn deep_verify_edges(
 
                 match input_result {
                     VerifyResult::Changed => {
+                        let execution_reasons = zalsa.execution_reasons();
+                        if execution_reasons.is_enabled() {
+                            execution_reasons.record_changed_input(
+                                zalsa,
+                                database_key_index,
+                                dependency_index,
+                            );
+                        }
                         return VerifyResult::changed();
                     }
                     #[cfg(feature = "accumulator")]
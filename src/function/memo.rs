@@ -14,7 +14,7 @@ use crate::sync::atomic::Ordering;
 use crate::table::memo::MemoTableWithTypesMut;
 use crate::zalsa::{MemoIngredientIndex, Zalsa};
 use crate::zalsa_local::{QueryOriginRef, QueryRevisions};
-use crate::{Event, EventKind, Id, Revision};
+use crate::{Event, EventKind, EventKindTag, Id, Revision};
 
 impl<C: Configuration> IngredientImpl<C> {
     /// Inserts the memo for the given key; (atomically) overwrites and returns any previously existing memo
@@ -59,10 +59,13 @@ impl<C: Configuration> IngredientImpl<C> {
     /// Evicts the existing memo for the given key, replacing it
     /// with an equivalent memo that has no value. If the memo is untracked
     /// or has values assigned as output of another query, this has no effect.
+    ///
+    /// Returns `true` if a value was evicted.
     pub(super) fn evict_value_from_memo_for(
         table: MemoTableWithTypesMut<'_>,
         memo_ingredient_index: MemoIngredientIndex,
-    ) {
+    ) -> bool {
+        let mut evicted = false;
         let map = |memo: &mut Memo<'static, C>| {
             match memo.revisions.origin() {
                 QueryOriginRef::Assigned(_) | QueryOriginRef::DerivedUntracked(_) => {
@@ -73,12 +76,13 @@ impl<C: Configuration> IngredientImpl<C> {
                 }
                 QueryOriginRef::Derived(_) => {
                     // Set the memo value to `None`.
-                    memo.value = None;
+                    evicted = memo.value.take().is_some();
                 }
             }
         };
 
-        table.map_memo(memo_ingredient_index, map)
+        table.map_memo(memo_ingredient_index, map);
+        evicted
     }
 }
 
@@ -151,7 +155,7 @@ impl<'db, C: Configuration> Memo<'db, C> {
     /// values have changed since.
     #[inline]
     pub(super) fn mark_as_verified(&self, zalsa: &Zalsa, database_key_index: DatabaseKeyIndex) {
-        zalsa.event(&|| {
+        zalsa.event(EventKindTag::DidValidateMemoizedValue, &|| {
             Event::new(EventKind::DidValidateMemoizedValue {
                 database_key: database_key_index,
             })
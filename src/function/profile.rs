@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+use crate::function::{Configuration, IngredientImpl};
+use crate::sync::atomic::{AtomicU64, Ordering};
+use crate::zalsa::{IngredientIndex, Zalsa};
+
+impl<C> IngredientImpl<C>
+where
+    C: Configuration,
+{
+    /// Returns the statistics of this function, if profiling is enabled.
+    #[inline]
+    pub(super) fn profile(&self, zalsa: &Zalsa) -> Option<&Profile> {
+        if zalsa.is_profiling() {
+            Some(&self.profile)
+        } else {
+            None
+        }
+    }
+}
+
+/// Execution and cache statistics of a tracked function, collected while profiling is enabled.
+#[derive(Default)]
+pub(super) struct Profile {
+    executions: AtomicU64,
+    total_execution_nanos: AtomicU64,
+    max_execution_nanos: AtomicU64,
+    hits: AtomicU64,
+    deep_verifications: AtomicU64,
+    backdates: AtomicU64,
+    cycle_iterations: AtomicU64,
+}
+
+impl Profile {
+    pub(super) fn record_execution(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.executions.fetch_add(1, Ordering::Relaxed);
+        self.total_execution_nanos
+            .fetch_add(nanos, Ordering::Relaxed);
+        self.max_execution_nanos.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_deep_verification(&self) {
+        self.deep_verifications.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_backdate(&self) {
+        self.backdates.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_cycle_iteration(&self) {
+        self.cycle_iterations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn snapshot(
+        &self,
+        ingredient_index: IngredientIndex,
+        debug_name: &'static str,
+    ) -> QueryProfile {
+        QueryProfile {
+            ingredient_index,
+            debug_name,
+            executions: self.executions.load(Ordering::Relaxed),
+            total_execution_time: Duration::from_nanos(
+                self.total_execution_nanos.load(Ordering::Relaxed),
+            ),
+            max_execution_time: Duration::from_nanos(
+                self.max_execution_nanos.load(Ordering::Relaxed),
+            ),
+            hits: self.hits.load(Ordering::Relaxed),
+            deep_verifications: self.deep_verifications.load(Ordering::Relaxed),
+            backdates: self.backdates.load(Ordering::Relaxed),
+            cycle_iterations: self.cycle_iterations.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(super) fn reset(&self) {
+        for counter in [
+            &self.executions,
+            &self.total_execution_nanos,
+            &self.max_execution_nanos,
+            &self.hits,
+            &self.deep_verifications,
+            &self.backdates,
+            &self.cycle_iterations,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Profiling information about a tracked function, as returned by `<dyn Database>::query_profile`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct QueryProfile {
+    ingredient_index: IngredientIndex,
+    debug_name: &'static str,
+    executions: u64,
+    total_execution_time: Duration,
+    max_execution_time: Duration,
+    hits: u64,
+    deep_verifications: u64,
+    backdates: u64,
+    cycle_iterations: u64,
+}
+
+impl QueryProfile {
+    /// Returns the index of the tracked function's ingredient, which identifies the function
+    /// in the [`DatabaseKeyIndex`](`crate::DatabaseKeyIndex`) of its queries.
+    pub fn ingredient_index(&self) -> IngredientIndex {
+        self.ingredient_index
+    }
+
+    /// Returns the name of the tracked function.
+    pub fn debug_name(&self) -> &'static str {
+        self.debug_name
+    }
+
+    /// Returns how many times the function was executed.
+    pub fn executions(&self) -> u64 {
+        self.executions
+    }
+
+    /// Returns the total time spent executing the function, including the time
+    /// spent in the queries it called.
+    pub fn total_execution_time(&self) -> Duration {
+        self.total_execution_time
+    }
+
+    /// Returns the longest time a single execution of the function took.
+    pub fn max_execution_time(&self) -> Duration {
+        self.max_execution_time
+    }
+
+    /// Returns how many times a memoized value was returned without executing the function.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns how many times the dependencies of a memoized value were walked to verify it.
+    pub fn deep_verifications(&self) -> u64 {
+        self.deep_verifications
+    }
+
+    /// Returns how many times an execution produced a value equal to the memoized one,
+    /// which was therefore backdated.
+    pub fn backdates(&self) -> u64 {
+        self.backdates
+    }
+
+    /// Returns how many additional fixpoint iterations were run for cycles the function is the head of.
+    pub fn cycle_iterations(&self) -> u64 {
+        self.cycle_iterations
+    }
+}
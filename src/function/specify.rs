@@ -78,12 +78,17 @@ where
 
         let memo_ingredient_index = self.memo_ingredient_index(zalsa, key);
         if let Some(old_memo) = self.get_memo_from_table_for(zalsa, key, memo_ingredient_index) {
-            self.backdate_if_appropriate(
+            if self.backdate_if_appropriate(
                 old_memo,
                 database_key_index,
                 &mut completed_query.revisions,
                 &value,
-            );
+            ) {
+                #[cfg(feature = "salsa_unstable")]
+                if let Some(profile) = self.profile(zalsa) {
+                    profile.record_backdate();
+                }
+            }
             self.diff_outputs(zalsa, database_key_index, old_memo, &completed_query);
         }
 
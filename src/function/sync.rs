@@ -76,6 +76,10 @@ impl SyncTable {
         let mut write = self.syncs.lock();
         match write.entry(key_index) {
             std::collections::hash_map::Entry::Occupied(occupied_entry) => {
+                #[cfg(feature = "rayon")]
+                zalsa_local
+                    .assert_not_claimed_by_fork(DatabaseKeyIndex::new(self.ingredient, key_index));
+
                 let id = match occupied_entry.get().id {
                     SyncOwner::Thread(id) => id,
                     SyncOwner::Transferred => {
@@ -334,7 +338,7 @@ impl<'me> ClaimGuard<'me> {
         let result = if self.zalsa_local.should_trigger_local_cancellation() {
             WaitResult::Cancelled
         } else {
-            WaitResult::Panicked
+            WaitResult::Panicked(self.zalsa_local.query_panic(self.database_key_index()))
         };
         tracing::debug!(
             "Release claim on {:?} due to {:?}",
@@ -364,10 +368,11 @@ impl<'me> ClaimGuard<'me> {
             runtime.undo_transfer_lock(database_key_index);
         }
 
-        runtime.unblock_queries_blocked_on(database_key_index, wait_result);
-
         if is_transfer_target {
+            runtime.unblock_queries_blocked_on(database_key_index, wait_result.clone());
             runtime.unblock_transferred_queries_owned_by(database_key_index, wait_result);
+        } else {
+            runtime.unblock_queries_blocked_on(database_key_index, wait_result);
         }
     }
 
@@ -405,7 +410,7 @@ impl<'me> ClaimGuard<'me> {
                     .lock()
                     .remove(&self.key_index)
                     .expect("key should only be claimed/released once"),
-                WaitResult::Panicked,
+                WaitResult::Panicked(None),
             );
 
             panic!("new owner to be a locked query")
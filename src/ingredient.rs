@@ -1,13 +1,13 @@
 use std::any::{Any, TypeId};
 use std::fmt;
 
+use crate::Runtime;
 use crate::cycle::{IterationStamp, ProvisionalStatus};
 use crate::database::RawDatabase;
 use crate::function::VerifyResult;
 use crate::hash::{FxHashSet, FxIndexSet};
-use crate::runtime::Running;
+use crate::runtime::{Running, Stamp};
 use crate::sync::Arc;
-use crate::table::Table;
 use crate::table::memo::MemoTableTypes;
 use crate::zalsa::{IngredientIndex, JarKind, Zalsa, transmute_data_mut_ptr, transmute_data_ptr};
 use crate::zalsa_local::{QueryEdge, QueryOriginRef};
@@ -143,8 +143,8 @@ pub trait Ingredient: Any + fmt::Debug + Send + Sync {
     ///
     /// **Important:** to actually receive resets, the ingredient must set
     /// [`IngredientRequiresReset::RESET_ON_NEW_REVISION`] to true.
-    fn reset_for_new_revision(&mut self, table: &mut Table) {
-        _ = table;
+    fn reset_for_new_revision(&mut self, runtime: &mut Runtime) {
+        _ = runtime;
         panic!(
             "Ingredient `{}` set `Ingredient::requires_reset_for_new_revision` to true but does \
             not overwrite `Ingredient::reset_for_new_revision`",
@@ -210,6 +210,30 @@ pub trait Ingredient: Any + fmt::Debug + Send + Sync {
         unreachable!("only function ingredients have origins")
     }
 
+    /// Returns information about the memoized value at `key_index`, if any.
+    ///
+    /// Returns `None` for ingredients other than tracked functions.
+    fn query_info(&self, zalsa: &Zalsa, key_index: Id) -> Option<crate::QueryInfo> {
+        let _ = (zalsa, key_index);
+        None
+    }
+
+    /// Returns the durability of the input field at `input` and the revision in which it last changed.
+    ///
+    /// Returns `None` for ingredients other than input fields.
+    fn input_stamp(&self, zalsa: &Zalsa, input: Id) -> Option<Stamp> {
+        let _ = (zalsa, input);
+        None
+    }
+
+    /// Returns the keys of all memoized values of this ingredient.
+    ///
+    /// Returns an empty list for ingredients other than tracked functions.
+    fn memoized_keys(&self, zalsa: &Zalsa) -> Vec<Id> {
+        let _ = zalsa;
+        Vec::new()
+    }
+
     /// What values were accumulated during the creation of the value at `key_index`
     /// (if any).
     ///
@@ -239,6 +263,18 @@ pub trait Ingredient: Any + fmt::Debug + Send + Sync {
         None
     }
 
+    /// Returns the execution and cache statistics of a tracked function.
+    ///
+    /// Returns `None` for ingredients other than tracked functions.
+    #[cfg(feature = "salsa_unstable")]
+    fn query_profile(&self) -> Option<crate::function::QueryProfile> {
+        None
+    }
+
+    /// Resets the execution and cache statistics of a tracked function.
+    #[cfg(feature = "salsa_unstable")]
+    fn reset_query_profile(&self) {}
+
     /// Whether this ingredient will be persisted with the database.
     fn is_persistable(&self) -> bool {
         false
@@ -15,12 +15,13 @@ use crate::ingredient::Ingredient;
 use crate::input::singleton::{Singleton, SingletonChoice};
 use crate::key::DatabaseKeyIndex;
 use crate::plumbing::{self, Jar, ZalsaLocal};
-use crate::sync::Arc;
-use crate::table::memo::{MemoTable, MemoTableTypes};
+use crate::sync::atomic::{AtomicBool, Ordering};
+use crate::sync::{Arc, Mutex};
+use crate::table::memo::{MemoTable, MemoTableTypes, MemoTableWithTypesMut};
 use crate::table::{Slot, Table};
 use crate::zalsa::{IngredientIndex, JarKind, Zalsa};
 use crate::zalsa_local::QueryEdge;
-use crate::{Durability, Id, Revision, Runtime};
+use crate::{Durability, Event, EventKind, EventKindTag, Id, Revision, Runtime};
 
 pub trait Configuration: Any {
     const DEBUG_NAME: &'static str;
@@ -118,6 +119,13 @@ pub struct IngredientImpl<C: Configuration> {
     ingredient_index: IngredientIndex,
     singleton: C::Singleton,
     memo_table_types: Arc<MemoTableTypes>,
+
+    /// Ids of deleted inputs whose slots can be reused.
+    ///
+    /// Locked while a slot is reused, which keeps serialization from reading the
+    /// fields of deleted inputs concurrently.
+    free_list: Mutex<Vec<Id>>,
+
     _phantom: std::marker::PhantomData<C::Struct>,
 }
 
@@ -127,12 +135,37 @@ impl<C: Configuration> IngredientImpl<C> {
             ingredient_index: index,
             singleton: Default::default(),
             memo_table_types: Arc::new(MemoTableTypes::default()),
+            free_list: Default::default(),
             _phantom: std::marker::PhantomData,
         }
     }
 
-    fn data(zalsa: &Zalsa, id: Id) -> &Value<C> {
-        zalsa.table().get(id)
+    /// Returns the data for `id`, or `None` if the input has been deleted.
+    fn try_live_data(table: &Table, id: Id) -> Option<&Value<C>> {
+        Self::live_slot(table, id).filter(|value| value.id == id)
+    }
+
+    /// Returns the data stored in the slot of `id`, or `None` if the slot holds a deleted
+    /// input. Unlike [`Self::try_live_data`], this ignores the generation of `id`.
+    fn live_slot(table: &Table, id: Id) -> Option<&Value<C>> {
+        let data_raw = Self::data_raw(table, id);
+
+        // SAFETY: The slot was allocated by this ingredient, and `deleted` is only
+        // accessed atomically while the slot is shared.
+        let deleted = unsafe { &(*data_raw).deleted };
+        if deleted.load(Ordering::Acquire) {
+            return None;
+        }
+
+        // SAFETY: The slot holds a live input, which can only be deleted (and its slot
+        // reused) through `&mut Zalsa`, so it isn't written to while `table` is borrowed.
+        Some(unsafe { &*data_raw })
+    }
+
+    /// Returns the data for `id`, panicking if the input has been deleted.
+    fn live_data(zalsa: &Zalsa, id: Id) -> &Value<C> {
+        Self::try_live_data(zalsa.table(), id)
+            .unwrap_or_else(|| stale_input_read_failed(C::DEBUG_NAME, id))
     }
 
     fn data_raw(table: &Table, id: Id) -> *mut Value<C> {
@@ -152,21 +185,177 @@ impl<C: Configuration> IngredientImpl<C> {
         durabilities: C::Durabilities,
     ) -> C::Struct {
         let id = self.singleton.with_scope(|| {
-            zalsa_local
-                .allocate(zalsa, self.ingredient_index, |_| Value::<C> {
-                    fields,
-                    revisions,
-                    durabilities,
-                    // SAFETY: We only ever access the memos of a value that we allocated through
-                    // our `MemoTableTypes`.
-                    memos: unsafe { MemoTable::new(self.memo_table_types()) },
-                })
-                .0
+            let mut free_list = self.free_list.lock();
+            while let Some(id) = free_list.pop() {
+                // Increment the ID generation before reusing it, so that handles to the
+                // deleted input can be told apart from the new one.
+                //
+                // If the generation would overflow, we are forced to leak the slot.
+                let Some(id) = id.next_generation() else {
+                    crate::tracing::info!(
+                        "leaking input {:?} due to generation overflow",
+                        self.database_key_index(id)
+                    );
+
+                    continue;
+                };
+
+                let data_raw = Self::data_raw(zalsa.table(), id);
+
+                // SAFETY: The slot was deleted, so other threads only read its `deleted` flag
+                // until we clear it, and we hold the free list lock. Its memos have been
+                // cleared when it was deleted.
+                let old_fields = unsafe {
+                    debug_assert!(
+                        (*data_raw).deleted.load(Ordering::Relaxed),
+                        "free list entry for `{id:?}` is not deleted"
+                    );
+
+                    (*data_raw).revisions = revisions;
+                    (*data_raw).durabilities = durabilities;
+                    (*data_raw).id = id;
+                    let old_fields = std::mem::replace(&mut (*data_raw).fields, fields);
+
+                    // Publish the new input, see `try_live_data`.
+                    (*data_raw).deleted.store(false, Ordering::Release);
+                    old_fields
+                };
+
+                // Drop the fields of the deleted input outside of the lock, as their
+                // destructors may create inputs.
+                drop(free_list);
+                drop(old_fields);
+                return id;
+            }
+            drop(free_list);
+
+            let value = |id| Value::<C> {
+                fields,
+                revisions,
+                durabilities,
+                id,
+                deleted: AtomicBool::new(false),
+                // SAFETY: We only ever access the memos of a value that we allocated through
+                // our `MemoTableTypes`.
+                memos: unsafe { MemoTable::new(self.memo_table_types()) },
+            };
+
+            zalsa_local.allocate(zalsa, self.ingredient_index, value).0
         });
 
         FromIdWithDb::from_id(id, zalsa)
     }
 
+    /// Deletes the input `id`.
+    ///
+    /// This drops all memos of tracked functions that take the input as argument,
+    /// and makes its slot available for reuse. Queries that read a field of the
+    /// input are considered changed in the new revision; reading a field of the
+    /// deleted input panics.
+    ///
+    /// # Panics
+    ///
+    /// If the input has already been deleted, or if called inside a transaction.
+    pub fn delete_input(zalsa_mut: &mut Zalsa, id: C::Struct) {
+        assert!(
+            !zalsa_mut.runtime().records_undo(),
+            "cannot delete inputs inside a transaction"
+        );
+
+        // Check before starting a new revision, so that deleting an input twice leaves
+        // the database untouched.
+        let id = id.as_id();
+        if !Self::is_live(zalsa_mut.runtime(), id) {
+            stale_input_read_failed(C::DEBUG_NAME, id);
+        }
+
+        let index = zalsa_mut.lookup_jar_by_type::<JarImpl<C>>();
+        zalsa_mut.new_revision();
+
+        let current_revision = zalsa_mut.current_revision();
+        let runtime = zalsa_mut.runtime_mut();
+
+        // SAFETY: We hold `&mut` on the runtime so no `&`-references can be active.
+        let data = unsafe { &mut *Self::data_raw(runtime.table(), id) };
+
+        // Dependents on any of the fields must observe the deletion as a change.
+        let mut durability = Durability::MIN;
+        for field_index in 0..C::FIELD_DEBUG_NAMES.len() {
+            data.revisions[field_index] = current_revision;
+            durability = durability.max(data.durabilities[field_index]);
+        }
+        if durability != Durability::MIN {
+            runtime.report_tracked_write(durability);
+        }
+        data.deleted.store(true, Ordering::Release);
+
+        let zalsa = &*zalsa_mut;
+        let ingredient = zalsa.lookup_ingredient(index).assert_type::<Self>();
+        ingredient.singleton.reset();
+
+        zalsa.event(EventKindTag::DidDiscard, &|| {
+            Event::new(EventKind::DidDiscard {
+                key: ingredient.database_key_index(id),
+            })
+        });
+
+        // SAFETY: We hold `&mut` on the database, so no references to the memos exist,
+        // and the memo table belongs to a value that we allocated.
+        unsafe {
+            let memo_table = &mut (*Self::data_raw(zalsa.table(), id)).memos;
+            ingredient.clear_memos(zalsa, memo_table, id);
+        }
+
+        // now that all cleanup has occurred, make available for re-use
+        ingredient.free_list.lock().push(id);
+    }
+
+    /// Clears the given memo table.
+    ///
+    /// # Safety
+    ///
+    /// The `MemoTable` must belong to a `Value` of the correct type, and no references
+    /// to its memos may exist.
+    unsafe fn clear_memos(&self, zalsa: &Zalsa, memo_table: &mut MemoTable, id: Id) {
+        // SAFETY: The caller guarantees this is the correct types table.
+        let table = unsafe { self.memo_table_types.attach_memos_mut(memo_table) };
+
+        // `Database::salsa_event` is a user supplied callback which may panic
+        // in that case we need a drop guard to free the memo table
+        struct TableDropGuard<'a>(MemoTableWithTypesMut<'a>);
+        impl Drop for TableDropGuard<'_> {
+            fn drop(&mut self) {
+                // SAFETY: We have `&mut MemoTable`, so no more references to these memos exist and we are good
+                // to drop them.
+                unsafe { self.0.drop() };
+            }
+        }
+
+        let mut table_guard = TableDropGuard(table);
+
+        // SAFETY: We have `&mut MemoTable`, so no more references to these memos exist and we are good
+        // to drop them.
+        unsafe {
+            table_guard.0.take_memos(|memo_ingredient_index, memo| {
+                let ingredient_index =
+                    zalsa.ingredient_index_for_memo(self.ingredient_index, memo_ingredient_index);
+
+                let executor = DatabaseKeyIndex::new(ingredient_index, id);
+
+                zalsa.event(EventKindTag::DidDiscard, &|| {
+                    Event::new(EventKind::DidDiscard { key: executor })
+                });
+
+                memo.remove_outputs(zalsa, executor);
+            })
+        };
+
+        std::mem::forget(table_guard);
+
+        // Reset the table after having dropped any memos.
+        memo_table.reset();
+    }
+
     /// Change the value of the field `field_index` to a new value.
     ///
     /// # Parameters
@@ -186,6 +375,10 @@ impl<C: Configuration> IngredientImpl<C> {
     ) -> R {
         let id: Id = id.as_id();
 
+        if !Self::is_live(runtime, id) {
+            stale_input_read_failed(C::DEBUG_NAME, id);
+        }
+
         let data_raw = Self::data_raw(runtime.table(), id);
 
         // SAFETY: We hold `&mut` on the runtime so no `&`-references can be active.
@@ -203,6 +396,52 @@ impl<C: Configuration> IngredientImpl<C> {
         setter(&mut data.fields)
     }
 
+    /// Returns the revision and durability of the field `field_index`.
+    fn field_stamp(runtime: &Runtime, id: Id, field_index: usize) -> (Revision, Durability) {
+        let data = Self::try_live_data(runtime.table(), id)
+            .unwrap_or_else(|| stale_input_read_failed(C::DEBUG_NAME, id));
+        (data.revisions[field_index], data.durabilities[field_index])
+    }
+
+    /// Returns `true` if `id` refers to an input that has not been deleted.
+    fn is_live(runtime: &Runtime, id: Id) -> bool {
+        Self::try_live_data(runtime.table(), id).is_some()
+    }
+
+    /// Returns the revision and durability of the field `field_index` of `id`,
+    /// or `None` if the input has been deleted.
+    fn try_field_stamp(
+        zalsa: &Zalsa,
+        id: Id,
+        field_index: usize,
+    ) -> Option<(Revision, Durability)> {
+        let data = Self::try_live_data(zalsa.table(), id)?;
+        Some((data.revisions[field_index], data.durabilities[field_index]))
+    }
+
+    /// Restores the field `field_index` to a previous state without recording a write.
+    ///
+    /// Used to undo writes that have not been observed by any query.
+    fn restore_field(
+        &mut self,
+        runtime: &mut Runtime,
+        id: C::Struct,
+        field_index: usize,
+        revision: Revision,
+        durability: Durability,
+        restore: impl FnOnce(&mut C::Fields),
+    ) {
+        let data_raw = Self::data_raw(runtime.table(), id.as_id());
+
+        // SAFETY: We hold `&mut` on the runtime so no `&`-references can be active.
+        // Also, we don't access any other data from the table while `r` is active.
+        let data = unsafe { &mut *data_raw };
+
+        data.revisions[field_index] = revision;
+        data.durabilities[field_index] = durability;
+        restore(&mut data.fields);
+    }
+
     /// Get the singleton input previously created (if any).
     #[doc(hidden)]
     pub fn get_singleton_input(&self, zalsa: &Zalsa) -> Option<C::Struct>
@@ -226,7 +465,7 @@ impl<C: Configuration> IngredientImpl<C> {
     ) -> &'db C::Fields {
         let field_ingredient_index = self.ingredient_index.successor(field_index);
         let id = id.as_id();
-        let value = Self::data(zalsa, id);
+        let value = Self::live_data(zalsa, id);
         let durability = value.durabilities[field_index];
         let revision = value.revisions[field_index];
         zalsa_local.report_tracked_read_simple(
@@ -237,14 +476,20 @@ impl<C: Configuration> IngredientImpl<C> {
         &value.fields
     }
 
+    /// Returns the key identifying the field at `field_index` of `id` in the dependency graph.
+    pub fn field_database_key_index(&self, id: C::Struct, field_index: usize) -> DatabaseKeyIndex {
+        DatabaseKeyIndex::new(self.ingredient_index.successor(field_index), id.as_id())
+    }
+
     /// Returns all data corresponding to the input struct.
     pub fn entries<'db>(&'db self, zalsa: &'db Zalsa) -> impl Iterator<Item = StructEntry<'db, C>> {
         zalsa
             .table()
-            .slots_of::<Value<C>>()
-            .map(|(id, value)| StructEntry {
+            .ids_of::<Value<C>>()
+            .filter_map(|id| Self::live_slot(zalsa.table(), id))
+            .map(|value| StructEntry {
                 value,
-                key: self.database_key_index(id),
+                key: self.database_key_index(value.id),
             })
     }
 
@@ -252,7 +497,7 @@ impl<C: Configuration> IngredientImpl<C> {
     /// Used for debug printouts.
     pub fn leak_fields<'db>(&'db self, zalsa: &'db Zalsa, id: C::Struct) -> &'db C::Fields {
         let id = id.as_id();
-        let value = Self::data(zalsa, id);
+        let value = Self::live_data(zalsa, id);
         &value.fields
     }
 }
@@ -372,7 +617,7 @@ impl<C: Configuration> Ingredient for IngredientImpl<C> {
     ) {
         f(&persistence::SerializeIngredient {
             zalsa,
-            _ingredient: self,
+            ingredient: self,
         })
     }
 
@@ -416,6 +661,18 @@ where
     /// Durabilities of the fields.
     durabilities: C::Durabilities,
 
+    /// The id of this input, including its generation.
+    ///
+    /// Slots of deleted inputs are reused with a new generation, which lets us
+    /// detect reads through stale handles.
+    id: Id,
+
+    /// Whether this input has been deleted.
+    ///
+    /// While set, the slot may be reused by another thread at any time, so the
+    /// other fields must not be read before checking it.
+    deleted: AtomicBool,
+
     /// Memos
     memos: MemoTable,
 }
@@ -458,6 +715,13 @@ pub trait HasBuilder {
     type Builder;
 }
 
+// Avoid inlining the panic into the hot path of field reads.
+#[cold]
+#[inline(never)]
+fn stale_input_read_failed(debug_name: &str, id: Id) -> ! {
+    panic!("attempted to access input `{debug_name}({id:?})`, which has been deleted")
+}
+
 // SAFETY: `Value<C>` is our private type branded over the unique configuration `C`.
 unsafe impl<C> Slot for Value<C>
 where
@@ -489,6 +753,7 @@ mod persistence {
     use crate::Id;
     use crate::input::singleton::SingletonChoice;
     use crate::plumbing::Ingredient;
+    use crate::sync::atomic::{AtomicBool, Ordering};
     use crate::table::memo::MemoTable;
     use crate::zalsa::Zalsa;
 
@@ -497,7 +762,7 @@ mod persistence {
         C: Configuration,
     {
         pub zalsa: &'db Zalsa,
-        pub _ingredient: &'db IngredientImpl<C>,
+        pub ingredient: &'db IngredientImpl<C>,
     }
 
     impl<C> serde::Serialize for SerializeIngredient<'_, C>
@@ -508,13 +773,16 @@ mod persistence {
         where
             S: serde::Serializer,
         {
-            let Self { zalsa, .. } = self;
+            let Self { zalsa, ingredient } = self;
+
+            // Keep the slots of deleted inputs from being reused while we read them.
+            let _free_list = ingredient.free_list.lock();
 
             let count = zalsa.table().slots_of::<Value<C>>().count();
             let mut map = serializer.serialize_map(Some(count))?;
 
-            for (id, value) in zalsa.table().slots_of::<Value<C>>() {
-                map.serialize_entry(&id.as_bits(), value)?;
+            for (_, value) in zalsa.table().slots_of::<Value<C>>() {
+                map.serialize_entry(&value.id.as_bits(), value)?;
             }
 
             map.end()
@@ -529,18 +797,21 @@ mod persistence {
         where
             S: serde::Serializer,
         {
-            let mut value = serializer.serialize_struct("Value", 3)?;
+            let mut value = serializer.serialize_struct("Value", 4)?;
 
             let Value {
                 fields,
                 revisions,
                 durabilities,
+                id: _,
+                deleted,
                 memos: _,
             } = self;
 
             value.serialize_field("durabilities", &durabilities)?;
             value.serialize_field("revisions", &revisions)?;
             value.serialize_field("fields", &SerializeFields::<C>(fields))?;
+            value.serialize_field("deleted", &deleted.load(Ordering::Relaxed))?;
 
             value.end()
         }
@@ -602,10 +873,13 @@ mod persistence {
                 let id = Id::from_bits(id);
                 let (page_idx, _) = crate::table::split_id(id);
 
+                let deleted = value.deleted;
                 let value = Value::<C> {
                     fields: value.fields.0,
                     revisions: value.revisions,
                     durabilities: value.durabilities,
+                    id,
+                    deleted: AtomicBool::new(deleted),
                     // SAFETY: We only ever access the memos of a value that we allocated through
                     // our `MemoTableTypes`.
                     memos: unsafe { MemoTable::new(ingredient.memo_table_types()) },
@@ -628,12 +902,18 @@ mod persistence {
                         .allocate(page_idx, |_| value)
                         .unwrap_or_else(|_| panic!("serialized an invalid `Id`: {id:?}"))
                         .0
+                        .with_generation(id.generation())
                 });
 
                 assert_eq!(
                     allocated_id, id,
                     "values are serialized in allocation order"
                 );
+
+                if deleted {
+                    ingredient.singleton.reset();
+                    ingredient.free_list.get_mut().push(id);
+                }
             }
 
             Ok(())
@@ -647,6 +927,8 @@ mod persistence {
         revisions: C::Revisions,
         #[serde(bound = "C: Configuration")]
         fields: DeserializeFields<C>,
+        #[serde(default)]
+        deleted: bool,
     }
 
     struct DeserializeFields<C: Configuration>(C::Fields);
@@ -5,11 +5,12 @@ use crate::function::VerifyResult;
 use crate::hash::{FxHashSet, FxIndexSet};
 use crate::ingredient::Ingredient;
 use crate::input::{Configuration, IngredientImpl, Value};
+use crate::runtime::Stamp;
 use crate::sync::Arc;
 use crate::table::memo::MemoTableTypes;
 use crate::zalsa::{IngredientIndex, JarKind, Zalsa};
 use crate::zalsa_local::QueryEdge;
-use crate::{DatabaseKeyIndex, Id, Revision};
+use crate::{DatabaseKeyIndex, Durability, Id, Revision};
 
 /// Ingredient used to represent the fields of a `#[salsa::input]`.
 ///
@@ -62,8 +63,22 @@ where
         input: Id,
         revision: Revision,
     ) -> VerifyResult {
-        let value = <IngredientImpl<C>>::data(zalsa, input);
-        VerifyResult::changed_if(value.revisions[self.field_index] > revision)
+        // Deleting an input changes all of its fields.
+        match <IngredientImpl<C>>::try_field_stamp(zalsa, input, self.field_index) {
+            Some((changed_at, _)) => VerifyResult::changed_if(changed_at > revision),
+            None => VerifyResult::changed(),
+        }
+    }
+
+    fn input_stamp(&self, zalsa: &Zalsa, input: Id) -> Option<Stamp> {
+        let (changed_at, durability) =
+            <IngredientImpl<C>>::try_field_stamp(zalsa, input, self.field_index)
+                // The field of a deleted input can't be changed anymore.
+                .unwrap_or((zalsa.current_revision(), Durability::MAX));
+        Some(Stamp {
+            durability,
+            changed_at,
+        })
     }
 
     fn collect_minimum_serialized_edges(
@@ -1,7 +1,10 @@
 use std::marker::PhantomData;
 
+use crate::id::{AsId, FromId};
 use crate::input::{Configuration, IngredientImpl};
-use crate::{Durability, Runtime};
+use crate::runtime::{RestoreMode, UndoEntry};
+use crate::zalsa::Zalsa;
+use crate::{Database, DatabaseKeyIndex, Durability, Event, EventKind, EventKindTag, Runtime};
 
 /// Setter for a field of an input.
 pub trait Setter: Sized {
@@ -10,34 +13,85 @@ pub trait Setter: Sized {
     fn to(self, value: Self::FieldTy) -> Self::FieldTy;
 }
 
+/// A handle through which fields of type `F` of inputs can be set.
+///
+/// Implemented for all databases and, if `F` implements `Clone`, for
+/// [`Transaction`](`crate::Transaction`)s and [`Checkpoint`](`crate::Checkpoint`)s.
+#[diagnostic::on_unimplemented(
+    message = "cannot set a field of type `{F}` through `{Self}`",
+    note = "fields set inside a transaction or through a checkpoint must implement `Clone`"
+)]
+pub trait FieldWriter<F> {
+    /// Returns the storage of the database.
+    fn field_zalsa(&self) -> &Zalsa;
+
+    /// Returns the storage of the database for setting a field of durability `durability`.
+    fn field_zalsa_mut(&mut self, durability: Durability) -> &mut Zalsa;
+
+    /// Called after the field `database_key` was set through this handle, to report
+    /// the write and revalidate the watches.
+    fn field_written(&mut self, database_key: DatabaseKeyIndex, durability: Durability);
+
+    /// Returns the function used to copy the previous value of a field so that the
+    /// write can be undone, if writes through this handle are undoable.
+    fn clone_fn() -> Option<fn(&F) -> F>;
+}
+
+impl<Db, F> FieldWriter<F> for Db
+where
+    Db: ?Sized + Database,
+{
+    fn field_zalsa(&self) -> &Zalsa {
+        self.zalsa()
+    }
+
+    fn field_zalsa_mut(&mut self, durability: Durability) -> &mut Zalsa {
+        self.zalsa_mut_for_write(durability)
+    }
+
+    fn field_written(&mut self, database_key: DatabaseKeyIndex, durability: Durability) {
+        did_set_field(self, database_key, durability);
+        crate::watch::after_input_write(self);
+    }
+
+    fn clone_fn() -> Option<fn(&F) -> F> {
+        None
+    }
+}
+
 #[must_use]
-pub struct SetterImpl<'setter, C: Configuration, S, F> {
-    runtime: &'setter mut Runtime,
+pub struct SetterImpl<'setter, C: Configuration, Db: ?Sized, S, F> {
+    db: &'setter mut Db,
     id: C::Struct,
-    ingredient: &'setter mut IngredientImpl<C>,
+    ingredient_mut: fn(&mut Zalsa) -> (&mut IngredientImpl<C>, &mut Runtime),
     durability: Option<Durability>,
     field_index: usize,
     setter: S,
     phantom: PhantomData<fn(F)>,
 }
 
-impl<'setter, C, S, F> SetterImpl<'setter, C, S, F>
+impl<'setter, C, Db, S, F> SetterImpl<'setter, C, Db, S, F>
 where
     C: Configuration,
+    Db: ?Sized + FieldWriter<F>,
     S: FnOnce(&mut C::Fields, F) -> F,
 {
+    /// Creates a setter for the field `field_index`.
+    ///
+    /// The field is set through `db` once a value is provided, see [`FieldWriter`].
+    /// `ingredient_mut` starts a new revision and returns the ingredient of the input.
     pub fn new(
-        runtime: &'setter mut Runtime,
+        db: &'setter mut Db,
         id: C::Struct,
         field_index: usize,
-        ingredient: &'setter mut IngredientImpl<C>,
+        ingredient_mut: fn(&mut Zalsa) -> (&mut IngredientImpl<C>, &mut Runtime),
         setter: S,
     ) -> Self {
         SetterImpl {
-            runtime,
+            db,
             id,
             field_index,
-            ingredient,
+            ingredient_mut,
             durability: None,
             setter,
             phantom: PhantomData,
@@ -45,10 +99,12 @@ where
     }
 }
 
-impl<C, S, F> Setter for SetterImpl<'_, C, S, F>
+impl<C, Db, S, F> Setter for SetterImpl<'_, C, Db, S, F>
 where
     C: Configuration,
-    S: FnOnce(&mut C::Fields, F) -> F,
+    Db: ?Sized + FieldWriter<F>,
+    S: FnOnce(&mut C::Fields, F) -> F + Copy + Send + Sync + 'static,
+    F: Send + Sync + 'static,
 {
     type FieldTy = F;
 
@@ -59,17 +115,189 @@ where
 
     fn to(self, value: F) -> F {
         let Self {
-            runtime,
+            db,
             id,
-            ingredient,
+            ingredient_mut,
             durability,
             field_index,
             setter,
             phantom: _,
         } = self;
 
-        ingredient.set_field(runtime, id, field_index, durability, |tuple| {
+        // Only queries that may have read the field's current value need to be cancelled.
+        let raw_id = id.as_id();
+        let (_, current_durability) =
+            IngredientImpl::<C>::field_stamp(db.field_zalsa().runtime(), raw_id, field_index);
+        let (ingredient, runtime) = ingredient_mut(db.field_zalsa_mut(current_durability));
+        let database_key =
+            DatabaseKeyIndex::new(ingredient.ingredient_index.successor(field_index), raw_id);
+        let old_value = set_field::<C, S, F>(
+            runtime,
+            ingredient,
+            id,
+            field_index,
+            durability,
+            setter,
+            value,
+            Db::clone_fn(),
+        );
+
+        let (_, durability) =
+            IngredientImpl::<C>::field_stamp(db.field_zalsa().runtime(), raw_id, field_index);
+        db.field_written(database_key, durability);
+        old_value
+    }
+}
+
+/// Reports that the field `database_key` of an input was set to a value of `durability`.
+///
+/// The database is attached while the event is dispatched so that the key can be formatted.
+pub(crate) fn did_set_field<Db: ?Sized + Database>(
+    db: &Db,
+    database_key: DatabaseKeyIndex,
+    durability: Durability,
+) {
+    let runtime = db.zalsa().runtime();
+    if !runtime.is_listening(EventKindTag::DidSetInputField) {
+        return;
+    }
+    crate::attach::attach(db, || {
+        runtime.event(EventKindTag::DidSetInputField, &|| {
+            Event::new(EventKind::DidSetInputField {
+                database_key,
+                durability,
+            })
+        })
+    });
+}
+
+/// Sets the field `field_index` of `id` to `value`, recording how to undo the write
+/// if a transaction is open or the input journal is recorded.
+#[allow(clippy::too_many_arguments)]
+fn set_field<C, S, F>(
+    runtime: &mut Runtime,
+    ingredient: &mut IngredientImpl<C>,
+    id: C::Struct,
+    field_index: usize,
+    durability: Option<Durability>,
+    setter: S,
+    value: F,
+    clone_fn: Option<fn(&F) -> F>,
+) -> F
+where
+    C: Configuration,
+    S: FnOnce(&mut C::Fields, F) -> F + Copy + Send + Sync + 'static,
+    F: Send + Sync + 'static,
+{
+    let in_transaction = runtime.records_undo();
+    let journaling = runtime.is_journaling();
+    if !in_transaction && !journaling {
+        return ingredient.set_field(runtime, id, field_index, durability, |tuple| {
             setter(tuple, value)
+        });
+    }
+
+    // Fields are only set through the open `Transaction` or `Checkpoint`, which
+    // requires their type to implement `Clone`.
+    let Some(clone_fn) = clone_fn else {
+        panic!(
+            "`{}.{}` set outside of the open transaction or checkpoint",
+            C::DEBUG_NAME,
+            C::FIELD_DEBUG_NAMES[field_index],
+        )
+    };
+
+    let raw_id = id.as_id();
+    let (old_revision, old_durability) =
+        IngredientImpl::<C>::field_stamp(runtime, raw_id, field_index);
+    let old_value = ingredient.set_field(runtime, id, field_index, durability, |tuple| {
+        setter(tuple, value)
+    });
+
+    let ingredient_index = ingredient.ingredient_index;
+    let undo_entry = |restored_value: F| -> UndoEntry {
+        Box::new(move |zalsa, mode| {
+            let (ingredient, runtime) = zalsa.lookup_ingredient_mut(ingredient_index);
+            let ingredient = ingredient.assert_type_mut::<IngredientImpl<C>>();
+
+            // Writes to inputs that were deleted since can't be undone.
+            if !IngredientImpl::<C>::is_live(runtime, raw_id) {
+                return;
+            }
+
+            let id = C::Struct::from_id(raw_id);
+            let restore = |tuple: &mut C::Fields| {
+                setter(tuple, restored_value);
+            };
+
+            match mode {
+                RestoreMode::Exact => ingredient.restore_field(
+                    runtime,
+                    id,
+                    field_index,
+                    old_revision,
+                    old_durability,
+                    restore,
+                ),
+                RestoreMode::AsWrite => {
+                    ingredient.set_field(runtime, id, field_index, Some(old_durability), restore)
+                }
+            }
         })
+    };
+
+    if in_transaction {
+        runtime.record_undo(undo_entry(clone_fn(&old_value)));
+    }
+    if journaling {
+        runtime.record_journal(undo_entry(clone_fn(&old_value)));
+    }
+
+    old_value
+}
+
+/// This is used by the macro generated code to clone a value if its type implements `Clone`,
+/// e.g. to create new handles to a database.
+///
+/// To use:
+///
+/// ```rust,ignore
+/// use crate::input::setter::helper::Fallback;
+/// setter::helper::Dispatch::<$ty>::clone_fn()
+/// ```
+///
+/// It is important that you specify the `$ty` explicitly.
+///
+/// This uses the ["method dispatch hack"](https://github.com/nvzqz/impls#how-it-works)
+/// to use the `Clone` trait if it is available and else returns `None`.
+pub mod helper {
+    use std::marker::PhantomData;
+
+    pub struct Dispatch<D>(PhantomData<D>);
+
+    #[allow(clippy::new_without_default)]
+    impl<D> Dispatch<D> {
+        pub fn new() -> Self {
+            Dispatch(PhantomData)
+        }
+    }
+
+    impl<D> Dispatch<D>
+    where
+        D: Clone,
+    {
+        pub fn clone_fn() -> Option<fn(&D) -> D> {
+            Some(D::clone)
+        }
+    }
+
+    pub trait Fallback<T> {
+        fn clone_fn() -> Option<fn(&T) -> T>;
+    }
+
+    impl<T> Fallback<T> for Dispatch<T> {
+        fn clone_fn() -> Option<fn(&T) -> T> {
+            None
+        }
     }
 }
@@ -8,6 +8,9 @@ mod sealed {
 pub trait SingletonChoice: sealed::Sealed + Default {
     fn with_scope(&self, cb: impl FnOnce() -> Id) -> Id;
     fn index(&self) -> Option<Id>;
+
+    /// Forgets the singleton instance, allowing a new one to be created.
+    fn reset(&self);
 }
 
 pub struct Singleton {
@@ -38,6 +41,10 @@ impl SingletonChoice for Singleton {
             id => Some(unsafe { Id::from_bits_unchecked(id) }),
         }
     }
+
+    fn reset(&self) {
+        self.index.store(0, Ordering::Release);
+    }
 }
 
 impl Default for Singleton {
@@ -57,4 +64,5 @@ impl SingletonChoice for NotSingleton {
     fn index(&self) -> Option<Id> {
         None
     }
+    fn reset(&self) {}
 }
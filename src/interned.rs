@@ -23,7 +23,7 @@ use crate::table::Slot;
 use crate::table::memo::{MemoTable, MemoTableTypes, MemoTableWithTypesMut};
 use crate::zalsa::{IngredientIndex, JarKind, Zalsa};
 use crate::zalsa_local::QueryEdge;
-use crate::{DatabaseKeyIndex, Event, EventKind, Id, Revision};
+use crate::{DatabaseKeyIndex, Event, EventKind, EventKindTag, Id, Revision};
 
 /// Trait that defines the key properties of an interned struct.
 ///
@@ -389,7 +389,7 @@ where
             if { value_shared.last_interned_at } < current_revision {
                 value_shared.last_interned_at = current_revision;
 
-                zalsa.event(&|| {
+                zalsa.event(EventKindTag::DidValidateInternedValue, &|| {
                     Event::new(EventKind::DidValidateInternedValue {
                         key: index,
                         revision: current_revision,
@@ -512,7 +512,7 @@ where
                 current_revision,
             );
 
-            zalsa.event(&|| {
+            zalsa.event(EventKindTag::DidReuseInternedValue, &|| {
                 Event::new(EventKind::DidReuseInternedValue {
                     key: index,
                     revision: current_revision,
@@ -639,7 +639,7 @@ where
         // across revisions.
         zalsa_local.report_tracked_read_simple(index, durability, current_revision);
 
-        zalsa.event(&|| {
+        zalsa.event(EventKindTag::DidInternValue, &|| {
             Event::new(EventKind::DidInternValue {
                 key: index,
                 revision: current_revision,
@@ -715,7 +715,9 @@ where
 
                 let executor = DatabaseKeyIndex::new(ingredient_index, id);
 
-                zalsa.event(&|| Event::new(EventKind::DidDiscard { key: executor }));
+                zalsa.event(EventKindTag::DidDiscard, &|| {
+                    Event::new(EventKind::DidDiscard { key: executor })
+                });
 
                 memo.remove_outputs(zalsa, executor);
             })
@@ -916,7 +918,7 @@ where
         // Validate the value for the current revision to avoid reuse.
         value_shared.last_interned_at = current_revision;
 
-        zalsa.event(&|| {
+        zalsa.event(EventKindTag::DidValidateInternedValue, &|| {
             let index = self.database_key_index(input);
 
             Event::new(EventKind::DidValidateInternedValue {
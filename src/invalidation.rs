@@ -0,0 +1,88 @@
+use crate::hash::FxIndexSet;
+use crate::reverse_dependencies::ReverseDependencies;
+use crate::zalsa::Zalsa;
+use crate::{DatabaseKeyIndex, Durability, Revision};
+
+/// The effect that changing a set of inputs would have on the memoized queries, as
+/// returned by [`DatabaseExt::preview_invalidation`](`crate::DatabaseExt::preview_invalidation`).
+///
+/// Every memoized query falls in exactly one of the following categories.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InvalidationPreview {
+    affected: Vec<DatabaseKeyIndex>,
+    revalidated: Vec<DatabaseKeyIndex>,
+    skipped: Vec<DatabaseKeyIndex>,
+}
+
+impl InvalidationPreview {
+    pub(crate) fn new(zalsa: &Zalsa, changes: &[DatabaseKeyIndex]) -> Self {
+        // Stale memos are verified again when they're next read, so they're previewed
+        // with the inputs of their last execution.
+        let dependencies = ReverseDependencies::verified_since(zalsa, Revision::start());
+
+        let mut affected = FxIndexSet::default();
+        let mut durability = Durability::MIN;
+        for &change in changes {
+            // Changing anything but an input field is assumed to affect all durabilities.
+            let change_durability = zalsa
+                .lookup_ingredient(change.ingredient_index())
+                .input_stamp(zalsa, change.key_index())
+                .map_or(Durability::MAX, |stamp| stamp.durability);
+            durability = durability.max(change_durability);
+            affected.extend(dependencies.transitive_dependents_of(change));
+        }
+
+        // The "last changed" revisions once the changes have been applied in a new revision.
+        let mut revisions = zalsa.runtime().durability_revisions();
+        revisions.set_current(zalsa.current_revision().next());
+        if !changes.is_empty() {
+            revisions.report_write(durability);
+        }
+
+        let mut revalidated = Vec::new();
+        let mut skipped = Vec::new();
+        for ingredient in zalsa.ingredients() {
+            for key in ingredient.memoized_keys(zalsa) {
+                let query = DatabaseKeyIndex::new(ingredient.ingredient_index(), key);
+                if affected.contains(&query) {
+                    continue;
+                }
+
+                let Some(info) = ingredient.query_info(zalsa, key) else {
+                    continue;
+                };
+                if revisions.last_changed(info.durability()) <= info.verified_at() {
+                    skipped.push(query);
+                } else {
+                    revalidated.push(query);
+                }
+            }
+        }
+
+        Self {
+            affected: affected.into_iter().collect(),
+            revalidated,
+            skipped,
+        }
+    }
+
+    /// Returns the queries that read one of the changed inputs, directly or indirectly.
+    ///
+    /// They are re-executed when next read, unless the queries they depend on
+    /// produce the same results as before.
+    pub fn affected(&self) -> &[DatabaseKeyIndex] {
+        &self.affected
+    }
+
+    /// Returns the queries that don't depend on the changed inputs but whose dependencies
+    /// have to be walked to verify them, as they have a low enough durability.
+    pub fn revalidated(&self) -> &[DatabaseKeyIndex] {
+        &self.revalidated
+    }
+
+    /// Returns the queries that are verified without walking their dependencies,
+    /// as none of the inputs of their durability changed.
+    pub fn skipped(&self) -> &[DatabaseKeyIndex] {
+        &self.skipped
+    }
+}
@@ -5,12 +5,18 @@
 mod accumulator;
 mod active_query;
 mod attach;
+mod cancellation_token;
 mod cancelled;
+mod checkpoint;
+mod chrome_trace;
 mod cycle;
 mod database;
 mod database_impl;
 mod durability;
+mod eager;
 mod event;
+mod execution_reason;
+mod external;
 mod function;
 mod hash;
 mod id;
@@ -18,9 +24,16 @@ mod ingredient;
 mod ingredient_cache;
 mod input;
 mod interned;
+mod invalidation;
 mod key;
 mod memo_ingredient_indices;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod query_graph;
+mod query_info;
+mod query_panicked;
 mod return_mode;
+mod reverse_dependencies;
 mod revision;
 mod runtime;
 mod salsa_struct;
@@ -29,8 +42,12 @@ mod sync;
 mod table;
 mod tracing;
 mod tracked_struct;
+mod transaction;
 mod update;
 mod views;
+mod wait_graph;
+mod watch;
+mod write_queue;
 mod zalsa;
 mod zalsa_local;
 
@@ -42,35 +59,53 @@ pub use salsa_macros::{Supertype, Update, accumulator, db, input, interned, trac
 
 #[cfg(feature = "salsa_unstable")]
 pub use self::database::IngredientInfo;
+#[cfg(feature = "salsa_unstable")]
+pub use self::function::QueryProfile;
 
 #[cfg(feature = "accumulator")]
 pub use self::accumulator::Accumulator;
 pub use self::active_query::Backtrace;
-pub use self::cancelled::Cancelled;
-
+pub use self::cancellation_token::CancellationToken;
+pub use self::cancelled::{Cancelled, QueryPanicInfo};
+pub use self::checkpoint::Checkpoint;
 pub use self::cycle::Cycle;
-pub use self::database::Database;
+pub use self::database::{Database, DatabaseExt};
 pub use self::database_impl::DatabaseImpl;
 pub use self::durability::Durability;
-pub use self::event::{Event, EventKind};
+pub use self::eager::EagerScheduler;
+pub use self::event::{Event, EventFilter, EventKind, EventKindTag, EventListenerId};
+pub use self::execution_reason::{ChangedInput, ExecutionReason};
+pub use self::external::ExternalRead;
 pub use self::id::Id;
 pub use self::input::setter::Setter;
+pub use self::invalidation::InvalidationPreview;
 pub use self::key::DatabaseKeyIndex;
+#[cfg(feature = "rayon")]
+pub use self::parallel::{join, par_map};
+pub use self::query_graph::{
+    QueryGraph, QueryGraphEdge, QueryGraphEdgeKind, QueryGraphNode, QueryGraphNodeKind,
+};
+pub use self::query_info::{QueryInfo, QueryOriginKind};
+pub use self::query_panicked::QueryPanicked;
 pub use self::return_mode::SalsaAsDeref;
 pub use self::return_mode::SalsaAsRef;
+pub use self::reverse_dependencies::ReverseDependencies;
 pub use self::revision::Revision;
 pub use self::runtime::Runtime;
 pub use self::storage::{Storage, StorageHandle};
+pub use self::transaction::Transaction;
 pub use self::update::Update;
+pub use self::wait_graph::{BlockedThread, TransferredLock, WaitGraph};
+pub use self::watch::WatchId;
+pub use self::write_queue::{WriteError, WriteQueue, WriteTicket};
 pub use self::zalsa::IngredientIndex;
-pub use self::zalsa_local::CancellationToken;
 pub use crate::attach::{attach, attach_allow_change, with_attached_database};
 pub use crate::interned::{HashEqLike, Lookup};
 
 pub mod prelude {
     #[cfg(feature = "accumulator")]
     pub use crate::accumulator::Accumulator;
-    pub use crate::{Database, Setter};
+    pub use crate::{Database, DatabaseExt, Setter};
 }
 
 /// Internal names used by salsa macros.
@@ -108,6 +143,7 @@ pub mod plumbing {
         IngredientIndices, MemoIngredientIndices, MemoIngredientMap, MemoIngredientSingletonIndex,
         NewMemoIngredientIndices,
     };
+    pub use crate::query_panicked::catch_query_panic;
     pub use crate::revision::{AtomicRevision, Revision};
     pub use crate::runtime::{Runtime, Stamp, stamp};
     pub use crate::salsa_struct::{SalsaStructInDb, assert_supertype_no_overlap};
@@ -117,6 +153,7 @@ pub mod plumbing {
     pub use crate::update::helper::{Dispatch as UpdateDispatch, Fallback as UpdateFallback};
     pub use crate::update::{Update, always_update};
     pub use crate::views::DatabaseDownCaster;
+    pub use crate::watch::after_input_write;
     pub use crate::zalsa::{
         ErasedJar, HasJar, IngredientIndex, JarKind, Zalsa, ZalsaDatabase, register_jar,
         transmute_data_ptr, views,
@@ -149,7 +186,10 @@ pub mod plumbing {
 
     pub mod input {
         pub use crate::input::input_field::FieldIngredientImpl;
-        pub use crate::input::setter::SetterImpl;
+        pub use crate::input::setter::helper::{
+            Dispatch as CloneDispatch, Fallback as CloneFallback,
+        };
+        pub use crate::input::setter::{FieldWriter, SetterImpl};
         pub use crate::input::singleton::{NotSingleton, Singleton};
         pub use crate::input::{Configuration, HasBuilder, IngredientImpl, JarImpl, Value};
     }
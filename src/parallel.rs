@@ -0,0 +1,232 @@
+use std::any::Any;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+
+use rayon::iter::{
+    FromParallelIterator, IndexedParallelIterator, IntoParallelIterator, ParallelIterator,
+};
+
+use crate::active_query::{ForkedQuery, QueryBranch};
+use crate::attach::attach_allow_change;
+use crate::database::RawDatabase;
+use crate::{CancellationToken, Cancelled, Database};
+
+/// Maps `inputs` with `op` in parallel on the rayon thread pool, collecting the results in order.
+///
+/// Each thread runs `op` on its own fork of `db`. When called from a tracked function,
+/// the reads, outputs, tracked structs and accumulated values of every call to `op`
+/// are recorded as if the tracked function had made them itself, in the order of `inputs`.
+///
+/// If a call to `op` panics, the remaining calls are cancelled and the panic is resumed
+/// once all calls returned. Cancelling `db` cancels all calls to `op`.
+///
+/// `op` must not call the tracked function calling `par_map`, any query it is
+/// (transitively) called by, or the head of a cycle it is part of: these queries can't
+/// complete before `op` does.
+///
+/// # Panics
+///
+/// If the database doesn't implement `Clone`, or if `Db` is a database trait that no
+/// tracked function uses as its database type.
+///
+/// If `op` calls one of the queries that can't complete before it does.
+pub fn par_map<Db, F, T, R, C>(
+    db: &Db,
+    inputs: impl IntoParallelIterator<Item = T, Iter: IndexedParallelIterator>,
+    op: F,
+) -> C
+where
+    Db: Database + ?Sized,
+    F: Fn(&Db, T) -> R + Sync + Send,
+    T: Send,
+    R: Send,
+    C: FromParallelIterator<R>,
+{
+    let fork = Fork::new(db);
+    let branches: Vec<Branch<R>> = inputs
+        .into_par_iter()
+        .enumerate()
+        .map_with(fork.fork_db(db), |db, (index, input)| {
+            fork.run_branch(db, index, |db| op(db, input))
+        })
+        .collect();
+
+    fork.merge(db, branches).into_par_iter().collect()
+}
+
+/// Runs `a` and `b` in parallel on the rayon thread pool, each on its own fork of `db`.
+///
+/// Like [`par_map`], the reads, outputs, tracked structs and accumulated values of `a`
+/// and `b` are recorded in the tracked function calling `join`, first those of `a`
+/// and then those of `b`. The same restrictions apply.
+pub fn join<Db, A, B, RA, RB>(db: &Db, a: A, b: B) -> (RA, RB)
+where
+    Db: Database + ?Sized,
+    A: FnOnce(&Db) -> RA + Send,
+    B: FnOnce(&Db) -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    let fork = Fork::new(db);
+    let (db_a, db_b) = (fork.fork_db(db), fork.fork_db(db));
+    let (branch_a, branch_b) = rayon::join(
+        || fork.run_branch(&{ db_a }, 0, a),
+        || fork.run_branch(&{ db_b }, 1, b),
+    );
+
+    let mut panic = None;
+    let a = fork.merge_one(db, branch_a, &mut panic);
+    let b = fork.merge_one(db, branch_b, &mut panic);
+    match (a, b, panic) {
+        (_, _, Some(panic)) => panic::resume_unwind(panic),
+        (Some(a), Some(b), None) => (a, b),
+        _ => unreachable!("a branch without a result didn't panic"),
+    }
+}
+
+/// The state shared by the branches of a call to [`par_map`] or [`join`].
+struct Fork {
+    /// The tracked function calling `par_map` or `join`, if any.
+    query: Option<ForkedQuery>,
+
+    /// The parent token of all the forked database handles, cancelled once a branch panics.
+    token: CancellationToken,
+}
+
+/// The result of running a branch, and the dependencies it collected.
+struct Branch<R> {
+    result: Result<R, Box<dyn Any + Send>>,
+    dependencies: Option<QueryBranch>,
+}
+
+impl Fork {
+    fn new<Db: Database + ?Sized>(db: &Db) -> Self {
+        Self {
+            query: db.zalsa_local().fork_active_query(),
+            token: db.cancellation_token().child_token(),
+        }
+    }
+
+    fn fork_db<Db: Database + ?Sized>(&self, db: &Db) -> ForkedDb<Db> {
+        ForkedDb::new(db.fork_db(), &self.token)
+    }
+
+    fn run_branch<Db, R>(
+        &self,
+        db: &ForkedDb<Db>,
+        index: usize,
+        op: impl FnOnce(&Db) -> R,
+    ) -> Branch<R>
+    where
+        Db: Database + ?Sized,
+    {
+        let db = db.as_view();
+        attach_allow_change(db, || {
+            let zalsa_local = db.zalsa_local();
+            let active_query = self
+                .query
+                .as_ref()
+                .map(|query| zalsa_local.push_branch(db.zalsa(), query, index));
+
+            let result = panic::catch_unwind(AssertUnwindSafe(|| op(db)));
+            if result.is_err() {
+                self.token.cancel();
+            }
+
+            Branch {
+                result,
+                dependencies: active_query.map(|active_query| active_query.pop_branch()),
+            }
+        })
+    }
+
+    /// Merges the dependencies of `branches` into the forked query and returns their results,
+    /// resuming the panic of the first branch that panicked.
+    fn merge<Db, R>(&self, db: &Db, branches: Vec<Branch<R>>) -> Vec<R>
+    where
+        Db: Database + ?Sized,
+    {
+        let mut panic = None;
+        let results = branches
+            .into_iter()
+            .filter_map(|branch| self.merge_one(db, branch, &mut panic))
+            .collect();
+        match panic {
+            Some(panic) => panic::resume_unwind(panic),
+            None => results,
+        }
+    }
+
+    /// Merges the dependencies of `branch` into the forked query and returns its result.
+    ///
+    /// If the branch panicked, stores the panic in `panic`, unless it already holds a
+    /// panic that is more relevant: branches are cancelled after another branch panicked,
+    /// so the first panic that isn't a cancellation is the one to resume.
+    fn merge_one<Db, R>(
+        &self,
+        db: &Db,
+        branch: Branch<R>,
+        panic: &mut Option<Box<dyn Any + Send>>,
+    ) -> Option<R>
+    where
+        Db: Database + ?Sized,
+    {
+        if let Some(dependencies) = branch.dependencies {
+            db.zalsa_local().merge_branch(dependencies);
+        }
+
+        match branch.result {
+            Ok(result) => Some(result),
+            Err(payload) => {
+                let replace = match panic {
+                    None => true,
+                    Some(current) => current.is::<Cancelled>() && !payload.is::<Cancelled>(),
+                };
+                if replace {
+                    *panic = Some(payload);
+                }
+                None
+            }
+        }
+    }
+}
+
+/// A fork of a database of type `Db`, with its own local state.
+///
+/// Cloning a `ForkedDb` forks the database again.
+struct ForkedDb<Db: ?Sized> {
+    db: Box<dyn Database>,
+    token: CancellationToken,
+    phantom: PhantomData<fn() -> Box<Db>>,
+}
+
+impl<Db: Database + ?Sized> ForkedDb<Db> {
+    fn new(db: Option<Box<dyn Database>>, token: &CancellationToken) -> Self {
+        let db = db.unwrap_or_else(|| {
+            panic!(
+                "running queries in parallel requires the database `{}` to implement `Clone`",
+                std::any::type_name::<Db>()
+            )
+        });
+        db.cancellation_token().set_parent(token);
+        Self {
+            db,
+            token: token.clone(),
+            phantom: PhantomData,
+        }
+    }
+
+    fn as_view(&self) -> &Db {
+        let db = &*self.db;
+        let caster = db.zalsa().views().downcaster_for::<Db>();
+        // SAFETY: The database was forked from a `Db`, so its underlying type is the
+        // one `Db` was created from.
+        unsafe { caster.downcast_unchecked(RawDatabase::from(db)) }
+    }
+}
+
+impl<Db: Database + ?Sized> Clone for ForkedDb<Db> {
+    fn clone(&self) -> Self {
+        Self::new(self.db.fork_db(), &self.token)
+    }
+}
@@ -0,0 +1,345 @@
+use std::fmt::{self, Write};
+
+use rustc_hash::FxHashMap;
+
+use crate::hash::FxIndexSet;
+use crate::zalsa::Zalsa;
+use crate::{DatabaseKeyIndex, Durability, Revision};
+
+/// A snapshot of the dependency graph between memoized queries and the inputs, tracked
+/// structs and interned values they read, as returned by
+/// [`DatabaseExt::query_graph`](`crate::DatabaseExt::query_graph`).
+///
+/// The graph can be rendered with [`QueryGraph::to_dot`] or [`QueryGraph::to_json`].
+#[derive(Clone, Debug)]
+pub struct QueryGraph {
+    nodes: Vec<QueryGraphNode>,
+    edges: Vec<QueryGraphEdge>,
+}
+
+/// A node of a [`QueryGraph`].
+#[derive(Clone, Debug)]
+pub struct QueryGraphNode {
+    key: DatabaseKeyIndex,
+    label: String,
+    kind: QueryGraphNodeKind,
+    durability: Option<Durability>,
+    changed_at: Option<Revision>,
+    verified_at: Option<Revision>,
+}
+
+/// The kind of a [`QueryGraphNode`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum QueryGraphNodeKind {
+    /// A memoized query.
+    Query,
+
+    /// A field of an input struct.
+    InputField,
+
+    /// Anything else a query can read, such as the field of a tracked struct or an interned value.
+    Other,
+}
+
+/// An edge of a [`QueryGraph`], from a query to a key it read or created.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct QueryGraphEdge {
+    from: DatabaseKeyIndex,
+    to: DatabaseKeyIndex,
+    kind: QueryGraphEdgeKind,
+}
+
+/// The kind of a [`QueryGraphEdge`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum QueryGraphEdgeKind {
+    /// The query read the target.
+    Input,
+
+    /// The query created the target, e.g. a tracked struct or a value specified for another query.
+    Output,
+}
+
+impl QueryGraph {
+    pub(crate) fn new(zalsa: &Zalsa, root: Option<DatabaseKeyIndex>) -> Self {
+        let mut keys = FxIndexSet::default();
+        match root {
+            Some(root) => {
+                keys.insert(root);
+            }
+            None => {
+                for ingredient in zalsa.ingredients() {
+                    keys.extend(
+                        ingredient
+                            .memoized_keys(zalsa)
+                            .into_iter()
+                            .map(|key| DatabaseKeyIndex::new(ingredient.ingredient_index(), key)),
+                    );
+                }
+            }
+        }
+
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+
+        // Visit the keys in order, adding the targets of their edges as they are discovered.
+        let mut next = 0;
+        while let Some(&key) = keys.get_index(next) {
+            next += 1;
+
+            let ingredient = zalsa.lookup_ingredient(key.ingredient_index());
+            let label = KeyLabel { zalsa, key }.to_string();
+
+            let node = if let Some(info) = ingredient.query_info(zalsa, key.key_index()) {
+                for (targets, kind) in [
+                    (info.inputs(), QueryGraphEdgeKind::Input),
+                    (info.outputs(), QueryGraphEdgeKind::Output),
+                ] {
+                    for &to in targets {
+                        edges.push(QueryGraphEdge {
+                            from: key,
+                            to,
+                            kind,
+                        });
+                        keys.insert(to);
+                    }
+                }
+
+                QueryGraphNode {
+                    key,
+                    label,
+                    kind: QueryGraphNodeKind::Query,
+                    durability: Some(info.durability()),
+                    changed_at: Some(info.changed_at()),
+                    verified_at: Some(info.verified_at()),
+                }
+            } else if let Some(stamp) = ingredient.input_stamp(zalsa, key.key_index()) {
+                QueryGraphNode {
+                    key,
+                    label,
+                    kind: QueryGraphNodeKind::InputField,
+                    durability: Some(stamp.durability),
+                    changed_at: Some(stamp.changed_at),
+                    verified_at: None,
+                }
+            } else {
+                QueryGraphNode {
+                    key,
+                    label,
+                    kind: QueryGraphNodeKind::Other,
+                    durability: None,
+                    changed_at: None,
+                    verified_at: None,
+                }
+            };
+            nodes.push(node);
+        }
+
+        Self { nodes, edges }
+    }
+
+    /// Returns the nodes of the graph.
+    pub fn nodes(&self) -> &[QueryGraphNode] {
+        &self.nodes
+    }
+
+    /// Returns the edges of the graph.
+    pub fn edges(&self) -> &[QueryGraphEdge] {
+        &self.edges
+    }
+
+    /// Renders the graph in the DOT format of Graphviz.
+    ///
+    /// Queries are drawn as boxes and output edges are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph salsa {\n");
+        let index_of = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.key, index))
+            .collect::<FxHashMap<_, _>>();
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            let mut label = node.label.clone();
+            if let (Some(durability), Some(changed_at)) = (node.durability, node.changed_at) {
+                write!(
+                    label,
+                    "\ndurability: {durability:#?}\nchanged_at: {changed_at:?}"
+                )
+                .unwrap();
+            }
+            if let Some(verified_at) = node.verified_at {
+                write!(label, "\nverified_at: {verified_at:?}").unwrap();
+            }
+
+            let shape = match node.kind {
+                QueryGraphNodeKind::Query => "box",
+                QueryGraphNodeKind::InputField | QueryGraphNodeKind::Other => "ellipse",
+            };
+            writeln!(
+                dot,
+                "    n{index} [label=\"{}\", shape={shape}];",
+                Escaped(&label)
+            )
+            .unwrap();
+        }
+
+        for edge in &self.edges {
+            let (Some(from), Some(to)) = (index_of.get(&edge.from), index_of.get(&edge.to)) else {
+                continue;
+            };
+            match edge.kind {
+                QueryGraphEdgeKind::Input => writeln!(dot, "    n{from} -> n{to};"),
+                QueryGraphEdgeKind::Output => writeln!(dot, "    n{from} -> n{to} [style=dashed];"),
+            }
+            .unwrap();
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the graph as JSON.
+    ///
+    /// The result is an object with a `nodes` and an `edges` array. Nodes have an `id`,
+    /// a `label`, a `kind` and, when known, a `durability`, `changed_at` and `verified_at`.
+    /// Edges refer to nodes by their `id`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"nodes\":[");
+        for (index, node) in self.nodes.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            write!(
+                json,
+                "{{\"id\":\"{}\",\"label\":\"{}\",\"kind\":\"{:?}\"",
+                NodeId(node.key),
+                Escaped(&node.label),
+                node.kind
+            )
+            .unwrap();
+            if let Some(durability) = node.durability {
+                write!(json, ",\"durability\":{}", durability.index()).unwrap();
+            }
+            if let Some(changed_at) = node.changed_at {
+                write!(json, ",\"changed_at\":{}", changed_at.as_usize()).unwrap();
+            }
+            if let Some(verified_at) = node.verified_at {
+                write!(json, ",\"verified_at\":{}", verified_at.as_usize()).unwrap();
+            }
+            json.push('}');
+        }
+
+        json.push_str("],\"edges\":[");
+        for (index, edge) in self.edges.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            write!(
+                json,
+                "{{\"from\":\"{}\",\"to\":\"{}\",\"kind\":\"{:?}\"}}",
+                NodeId(edge.from),
+                NodeId(edge.to),
+                edge.kind
+            )
+            .unwrap();
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+impl QueryGraphNode {
+    /// Returns the key of the node.
+    pub fn key(&self) -> DatabaseKeyIndex {
+        self.key
+    }
+
+    /// Returns a human-readable description of the node, such as `length(Id(0))`.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the kind of the node.
+    pub fn kind(&self) -> QueryGraphNodeKind {
+        self.kind
+    }
+
+    /// Returns the durability of the node, if known.
+    pub fn durability(&self) -> Option<Durability> {
+        self.durability
+    }
+
+    /// Returns the last revision in which the node changed, if known.
+    pub fn changed_at(&self) -> Option<Revision> {
+        self.changed_at
+    }
+
+    /// Returns the last revision in which the memoized value of a query was verified.
+    pub fn verified_at(&self) -> Option<Revision> {
+        self.verified_at
+    }
+}
+
+impl QueryGraphEdge {
+    /// Returns the query the edge starts from.
+    pub fn from(&self) -> DatabaseKeyIndex {
+        self.from
+    }
+
+    /// Returns the key the query read or created.
+    pub fn to(&self) -> DatabaseKeyIndex {
+        self.to
+    }
+
+    /// Returns the kind of the edge.
+    pub fn kind(&self) -> QueryGraphEdgeKind {
+        self.kind
+    }
+}
+
+/// Formats a key with the debug name of its ingredient, without requiring an attached database.
+pub(crate) struct KeyLabel<'a> {
+    pub(crate) zalsa: &'a Zalsa,
+    pub(crate) key: DatabaseKeyIndex,
+}
+
+impl fmt::Display for KeyLabel<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.zalsa
+            .lookup_ingredient(self.key.ingredient_index())
+            .fmt_index(self.key.key_index(), f)
+    }
+}
+
+/// Formats a key as a unique node identifier.
+struct NodeId(DatabaseKeyIndex);
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            self.0.ingredient_index().as_u32(),
+            self.0.key_index().as_bits()
+        )
+    }
+}
+
+/// Escapes a string for a quoted DOT or JSON string.
+pub(crate) struct Escaped<'a>(pub(crate) &'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
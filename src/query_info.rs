@@ -0,0 +1,79 @@
+use crate::{DatabaseKeyIndex, Durability, Revision};
+
+/// How the memoized value of a query was created.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum QueryOriginKind {
+    /// The value was assigned as the output of another query, which is
+    /// available with [`QueryInfo::assigned_by`].
+    Assigned,
+
+    /// The value was derived by executing the query, and all of its inputs were tracked.
+    Derived,
+
+    /// The value was derived by executing the query, which also read untracked state.
+    DerivedUntracked,
+}
+
+/// Information about the memoized value of a query, as returned by
+/// [`DatabaseExt::query_info`](`crate::DatabaseExt::query_info`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryInfo {
+    pub(crate) origin: QueryOriginKind,
+    pub(crate) assigned_by: Option<DatabaseKeyIndex>,
+    pub(crate) inputs: Vec<DatabaseKeyIndex>,
+    pub(crate) outputs: Vec<DatabaseKeyIndex>,
+    pub(crate) verified_at: Revision,
+    pub(crate) changed_at: Revision,
+    pub(crate) durability: Durability,
+    pub(crate) cycle_heads: Vec<DatabaseKeyIndex>,
+    pub(crate) has_value: bool,
+}
+
+impl QueryInfo {
+    /// Returns how the memoized value was created.
+    pub fn origin(&self) -> QueryOriginKind {
+        self.origin
+    }
+
+    /// Returns the query that assigned the value, if its origin is [`QueryOriginKind::Assigned`].
+    pub fn assigned_by(&self) -> Option<DatabaseKeyIndex> {
+        self.assigned_by
+    }
+
+    /// Returns the inputs read by the query, in the order they were read.
+    pub fn inputs(&self) -> &[DatabaseKeyIndex] {
+        &self.inputs
+    }
+
+    /// Returns the outputs created by the query, such as tracked structs and
+    /// values assigned to other queries.
+    pub fn outputs(&self) -> &[DatabaseKeyIndex] {
+        &self.outputs
+    }
+
+    /// Returns the last revision in which the memoized value was verified.
+    pub fn verified_at(&self) -> Revision {
+        self.verified_at
+    }
+
+    /// Returns the last revision in which the memoized value changed.
+    pub fn changed_at(&self) -> Revision {
+        self.changed_at
+    }
+
+    /// Returns the minimum durability of the inputs of the query.
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    /// Returns the heads of the cycles the memoized value is a provisional result of,
+    /// empty if the value is final.
+    pub fn cycle_heads(&self) -> &[DatabaseKeyIndex] {
+        &self.cycle_heads
+    }
+
+    /// Returns `true` if the memo still holds the value, `false` if it has been evicted.
+    pub fn has_value(&self) -> bool {
+        self.has_value
+    }
+}
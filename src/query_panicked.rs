@@ -0,0 +1,99 @@
+use std::any::Any;
+use std::fmt;
+use std::panic;
+
+use crate::sync::Arc;
+use crate::zalsa::ZalsaDatabase;
+use crate::{Backtrace, Cancelled, Update};
+
+/// The error memoized for a tracked function marked `#[salsa::tracked(catch_panics)]`
+/// when it, or a query it called, panicked.
+///
+/// Like any other value, the error is only recomputed once an input that was read
+/// before the panic changes.
+#[derive(Clone)]
+pub struct QueryPanicked {
+    message: Arc<str>,
+    backtrace: Option<Arc<Backtrace>>,
+}
+
+impl QueryPanicked {
+    fn new(payload: &(dyn Any + Send), backtrace: Option<Backtrace>) -> Self {
+        Self {
+            message: panic_message(payload),
+            backtrace: backtrace.map(Arc::new),
+        }
+    }
+
+    /// Returns the message the query panicked with, if the panic payload was a string.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the query stack at the point of the panic, innermost query first.
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_deref()
+    }
+}
+
+/// Returns the message of a panic payload, if it is a string.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> Arc<str> {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        Arc::from(*message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        Arc::from(message.as_str())
+    } else {
+        Arc::from("Box<dyn Any>")
+    }
+}
+
+/// Errors are equal if they have the same message, so that a query that panics the same
+/// way again is backdated.
+impl PartialEq for QueryPanicked {
+    fn eq(&self, other: &Self) -> bool {
+        self.message == other.message
+    }
+}
+
+impl Eq for QueryPanicked {}
+
+// SAFETY: `QueryPanicked` has no lifetimes, so replacing it when not equal is sound.
+unsafe impl Update for QueryPanicked {
+    unsafe fn maybe_update(old_pointer: *mut Self, new_value: Self) -> bool {
+        // SAFETY: Guaranteed by caller.
+        unsafe { crate::update::update_fallback(old_pointer, new_value) }
+    }
+}
+
+impl fmt::Debug for QueryPanicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryPanicked")
+            .field("message", &self.message)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for QueryPanicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "query panicked: {}", self.message)
+    }
+}
+
+impl std::error::Error for QueryPanicked {}
+
+/// Runs the body `f` of a `catch_panics` tracked function, memoizing a panic as an error.
+///
+/// Cancellation is not an error of the query and keeps unwinding.
+pub fn catch_query_panic<Db, T>(db: &Db, f: impl FnOnce() -> T) -> Result<T, QueryPanicked>
+where
+    Db: ?Sized + ZalsaDatabase,
+{
+    // The reads of the queries that unwound are added to the active query,
+    // so the memoized error is invalidated like any other value.
+    db.zalsa_local().catch_panics(f).map_err(|payload| {
+        if payload.is::<Cancelled>() {
+            panic::resume_unwind(payload);
+        }
+        QueryPanicked::new(&*payload, db.zalsa_local().take_panic_backtrace())
+    })
+}
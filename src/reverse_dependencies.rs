@@ -0,0 +1,80 @@
+use rustc_hash::FxHashMap;
+
+use crate::hash::FxIndexSet;
+use crate::zalsa::Zalsa;
+use crate::{DatabaseKeyIndex, QueryOriginKind, Revision};
+
+/// An index from each input, tracked struct field or query to the memoized queries that
+/// read it, as returned by [`DatabaseExt::reverse_dependencies`](`crate::DatabaseExt::reverse_dependencies`).
+///
+/// The index is a snapshot of the memos at the time it was built: it only contains the
+/// memoized queries verified in the current revision, and isn't updated when queries
+/// execute afterwards.
+#[derive(Clone, Debug)]
+pub struct ReverseDependencies {
+    revision: Revision,
+    dependents: FxHashMap<DatabaseKeyIndex, Vec<DatabaseKeyIndex>>,
+}
+
+impl ReverseDependencies {
+    /// Builds the index over the memoized queries verified in the current revision.
+    pub(crate) fn new(zalsa: &Zalsa) -> Self {
+        Self::verified_since(zalsa, zalsa.current_revision())
+    }
+
+    /// Builds the index over the memoized queries last verified in `revision` or later.
+    pub(crate) fn verified_since(zalsa: &Zalsa, revision: Revision) -> Self {
+        let mut dependents = FxHashMap::<_, Vec<_>>::default();
+
+        for ingredient in zalsa.ingredients() {
+            for key in ingredient.memoized_keys(zalsa) {
+                let Some(info) = ingredient.query_info(zalsa, key) else {
+                    continue;
+                };
+                if info.verified_at() < revision {
+                    continue;
+                }
+                let dependent = DatabaseKeyIndex::new(ingredient.ingredient_index(), key);
+
+                match info.origin() {
+                    QueryOriginKind::Derived | QueryOriginKind::DerivedUntracked => {
+                        for &input in info.inputs() {
+                            dependents.entry(input).or_default().push(dependent);
+                        }
+                    }
+                    QueryOriginKind::Assigned => {}
+                }
+            }
+        }
+
+        Self {
+            revision: zalsa.current_revision(),
+            dependents,
+        }
+    }
+
+    /// Returns the revision in which the index was built.
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /// Returns the memoized queries that read `key` directly.
+    pub fn dependents_of(&self, key: DatabaseKeyIndex) -> &[DatabaseKeyIndex] {
+        self.dependents.get(&key).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the memoized queries that read `key` directly or indirectly,
+    /// ordered by their distance from `key`.
+    pub fn transitive_dependents_of(&self, key: DatabaseKeyIndex) -> Vec<DatabaseKeyIndex> {
+        let mut visited = FxIndexSet::default();
+        visited.insert(key);
+
+        let mut next = 0;
+        while let Some(&current) = visited.get_index(next) {
+            visited.extend(self.dependents_of(current));
+            next += 1;
+        }
+
+        visited.into_iter().skip(1).collect()
+    }
+}
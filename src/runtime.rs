@@ -1,5 +1,12 @@
 use self::dependency_graph::DependencyGraph;
+pub(crate) use self::durability_revisions::DurabilityRevisions;
+pub(crate) use self::journal::{Journal, JournalPosition};
+pub(crate) use self::transaction::{RestoreMode, Transaction, UndoEntry};
+
+use std::time::{Duration, Instant};
+
 use crate::durability::Durability;
+use crate::event::{EventFilter, EventListeners};
 use crate::function::{SyncGuard, SyncOwner};
 use crate::key::DatabaseKeyIndex;
 use crate::sync::Mutex;
@@ -7,9 +14,12 @@ use crate::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use crate::sync::thread::{self, ThreadId};
 use crate::table::Table;
 use crate::zalsa::Zalsa;
-use crate::{Cancelled, Event, EventKind, Revision};
+use crate::{Cancelled, Event, EventKind, EventKindTag, QueryPanicInfo, Revision, WaitGraph};
 
 mod dependency_graph;
+mod durability_revisions;
+mod journal;
+mod transaction;
 
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct Runtime {
@@ -19,21 +29,27 @@ pub struct Runtime {
     #[cfg_attr(feature = "persistence", serde(skip))]
     revision_cancelled: AtomicBool,
 
+    /// The highest durability of the inputs being changed while `revision_cancelled` is set.
+    /// Only queries that may depend on inputs of this durability or lower are cancelled.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    cancelled_durability: AtomicU8,
+
+    /// If `true`, a write only cancels the queries that may depend on the inputs it changes.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    selective_cancellation: bool,
+
+    /// If set, threads blocked on another thread for longer than this report a
+    /// [`EventKind::DidExceedBlockThreshold`] event.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    block_watchdog: Option<Duration>,
+
     /// Distinguishes provisional cycle results created before and after cancelling other handles
     /// within the same revision. Reset when the revision advances.
     #[cfg_attr(feature = "persistence", serde(skip))]
     cancellation_count: AtomicU8,
 
-    /// Stores the "last change" revision for values of each duration.
-    /// This vector is always of length at least 1 (for Durability 0)
-    /// but its total length depends on the number of durations. The
-    /// element at index 0 is special as it represents the "current
-    /// revision".  In general, we have the invariant that revisions
-    /// in here are *declining* -- that is, `revisions[i] >=
-    /// revisions[i + 1]`, for all `i`. This is because when you
-    /// modify a value with durability D, that implies that values
-    /// with durability less than D may have changed too.
-    revisions: [Revision; Durability::LEN],
+    /// Stores the "last change" revision for values of each durability level.
+    revisions: DurabilityRevisions,
 
     /// The dependency graph tracks which runtimes are blocked on one
     /// another, waiting for queries to terminate.
@@ -43,12 +59,23 @@ pub struct Runtime {
     /// Data for instances
     #[cfg_attr(feature = "persistence", serde(skip))]
     table: Table,
+
+    /// The currently open input transaction, if any.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    transaction: Option<Box<Transaction>>,
+
+    /// The journal of input writes, once a checkpoint has been taken.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    journal: Option<Box<Journal>>,
+
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    event_listeners: EventListeners,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub(super) enum WaitResult {
     Completed,
-    Panicked,
+    Panicked(Option<QueryPanicInfo>),
     Cancelled,
 }
 
@@ -143,7 +170,7 @@ impl Running<'_> {
             thread_id,
         } = *self.0;
 
-        zalsa.event(&|| {
+        zalsa.event(EventKindTag::WillBlockOn, &|| {
             Event::new(EventKind::WillBlockOn {
                 other_thread_id: other_id,
                 database_key,
@@ -154,15 +181,44 @@ impl Running<'_> {
             "block_on: thread {thread_id:?} is blocking on {database_key:?} in thread {other_id:?}",
         );
 
-        let result =
-            DependencyGraph::block_on(dg, thread_id, database_key, other_id, query_mutex_guard);
+        let chrome_trace = zalsa.chrome_trace().if_enabled();
+        if let Some(chrome_trace) = chrome_trace {
+            chrome_trace.begin_block(database_key, other_id);
+        }
+        let blocked_at = zalsa
+            .runtime()
+            .is_listening(EventKindTag::DidUnblock)
+            .then(Instant::now);
+
+        let result = DependencyGraph::block_on(
+            zalsa.runtime(),
+            dg,
+            thread_id,
+            database_key,
+            other_id,
+            query_mutex_guard,
+        );
+
+        if let Some(chrome_trace) = chrome_trace {
+            chrome_trace.end_block(database_key);
+        }
+        if let Some(blocked_at) = blocked_at {
+            let waited = blocked_at.elapsed();
+            zalsa.event(EventKindTag::DidUnblock, &|| {
+                Event::new(EventKind::DidUnblock {
+                    other_thread_id: other_id,
+                    database_key,
+                    waited,
+                })
+            });
+        }
 
         match result {
-            WaitResult::Panicked => {
+            WaitResult::Panicked(info) => {
                 // If the other thread panicked, then we consider this thread
                 // cancelled. The assumption is that the panic will be detected
                 // by the other thread and responded to appropriately.
-                Cancelled::PropagatedPanic.throw()
+                Cancelled::PropagatedPanic(info).throw()
             }
             WaitResult::Cancelled => false,
             WaitResult::Completed => true,
@@ -195,14 +251,61 @@ pub fn stamp(revision: Revision, durability: Durability) -> Stamp {
 
 impl Default for Runtime {
     fn default() -> Self {
+        Self::new(None, Durability::DEFAULT_LEVELS)
+    }
+}
+
+impl Runtime {
+    /// Creates a runtime tracking changes for `levels` durability levels, reporting
+    /// all events to `event_callback`.
+    pub(crate) fn new(
+        event_callback: Option<Box<dyn Fn(Event) + Send + Sync>>,
+        levels: usize,
+    ) -> Self {
+        let event_listeners = EventListeners::default();
+        if let Some(event_callback) = event_callback {
+            event_listeners.add(EventFilter::ALL, event_callback);
+        }
+
         Runtime {
-            revisions: [Revision::start(); Durability::LEN],
+            revisions: DurabilityRevisions::new(levels),
             revision_cancelled: Default::default(),
+            cancelled_durability: Default::default(),
+            selective_cancellation: false,
+            block_watchdog: None,
             cancellation_count: Default::default(),
             dependency_graph: Default::default(),
             table: Default::default(),
+            transaction: None,
+            journal: None,
+            event_listeners,
+        }
+    }
+
+    /// Reports the event created by `event` to the listeners interested in events of kind `tag`.
+    #[inline(always)]
+    pub(crate) fn event(&self, tag: EventKindTag, event: &dyn Fn() -> Event) {
+        if self.event_listeners.is_listening(tag) {
+            self.event_cold(tag, event);
         }
     }
+
+    // Avoid inlining, as events are typically only enabled for debugging purposes.
+    #[cold]
+    #[inline(never)]
+    fn event_cold(&self, tag: EventKindTag, event: &dyn Fn() -> Event) {
+        self.event_listeners.dispatch(tag, event);
+    }
+
+    /// Returns `true` if any listener is interested in events of kind `tag`.
+    #[inline]
+    pub(crate) fn is_listening(&self, tag: EventKindTag) -> bool {
+        self.event_listeners.is_listening(tag)
+    }
+
+    pub(crate) fn event_listeners(&self) -> &EventListeners {
+        &self.event_listeners
+    }
 }
 
 impl std::fmt::Debug for Runtime {
@@ -211,6 +314,8 @@ impl std::fmt::Debug for Runtime {
             .field("revisions", &self.revisions)
             .field("revision_cancelled", &self.revision_cancelled)
             .field("dependency_graph", &self.dependency_graph)
+            .field("transaction", &self.transaction)
+            .field("journal", &self.journal)
             .finish()
     }
 }
@@ -218,15 +323,14 @@ impl std::fmt::Debug for Runtime {
 impl Runtime {
     #[inline]
     pub(crate) fn current_revision(&self) -> Revision {
-        self.revisions[0]
+        self.revisions.current()
     }
 
     /// Reports that an input with durability `durability` changed.
     /// This will update the 'last changed at' values for every durability
     /// less than or equal to `durability` to the current revision.
     pub(crate) fn report_tracked_write(&mut self, durability: Durability) {
-        let new_revision = self.current_revision();
-        self.revisions[1..=durability.index()].fill(new_revision);
+        self.revisions.report_write(durability);
     }
 
     /// The revision in which values with durability `d` may have last
@@ -238,7 +342,12 @@ impl Runtime {
     /// dependencies.
     #[inline]
     pub(crate) fn last_changed_revision(&self, d: Durability) -> Revision {
-        self.revisions[d.index()]
+        self.revisions.last_changed(d)
+    }
+
+    /// Returns the "last changed" revisions of all durabilities.
+    pub(crate) fn durability_revisions(&self) -> DurabilityRevisions {
+        self.revisions
     }
 
     pub(crate) fn load_cancellation_flag(&self) -> bool {
@@ -249,11 +358,39 @@ impl Runtime {
         self.cancellation_count.load(Ordering::Acquire)
     }
 
-    pub(crate) fn set_cancellation_flag(&self) {
-        crate::tracing::trace!("set_cancellation_flag");
+    /// Returns the highest durability of the inputs being changed by the pending write.
+    ///
+    /// Only meaningful while the cancellation flag is set.
+    pub(crate) fn cancelled_durability(&self) -> Durability {
+        Durability::new(self.cancelled_durability.load(Ordering::Acquire))
+    }
+
+    /// Sets the cancellation flag for a write changing inputs of durability `durability`
+    /// or lower. Unless selective cancellation is enabled, all queries are cancelled.
+    pub(crate) fn set_cancellation_flag(&self, durability: Durability) {
+        crate::tracing::trace!("set_cancellation_flag({durability:?})");
+        let durability = if self.selective_cancellation {
+            durability
+        } else {
+            Durability::MAX
+        };
+        self.cancelled_durability
+            .store(durability.index() as u8, Ordering::Release);
         self.revision_cancelled.store(true, Ordering::Release);
     }
 
+    pub(crate) fn set_selective_cancellation(&mut self, selective: bool) {
+        self.selective_cancellation = selective;
+    }
+
+    pub(crate) fn set_block_watchdog(&mut self, threshold: Option<Duration>) {
+        self.block_watchdog = threshold;
+    }
+
+    pub(crate) fn block_watchdog(&self) -> Option<Duration> {
+        self.block_watchdog
+    }
+
     pub(crate) fn reset_cancellation_flag(&mut self) {
         *self.revision_cancelled.get_mut() = false;
     }
@@ -277,6 +414,136 @@ impl Runtime {
         &mut self.table
     }
 
+    /// Returns `true` if an input transaction is currently open.
+    #[inline]
+    pub(crate) fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    /// Opens a new input transaction.
+    ///
+    /// # Panics
+    ///
+    /// If a transaction is already open.
+    pub(crate) fn begin_transaction(&mut self, undoable: bool) {
+        assert!(
+            self.transaction.is_none(),
+            "cannot open a transaction while another transaction is open"
+        );
+        self.transaction = Some(Box::new(Transaction::new(self.revisions, undoable)));
+    }
+
+    /// Returns `true` if input writes must record undo entries because a transaction
+    /// that can be rolled back is open.
+    #[inline]
+    pub(crate) fn records_undo(&self) -> bool {
+        self.transaction
+            .as_ref()
+            .is_some_and(|transaction| transaction.is_undoable())
+    }
+
+    /// Closes the currently open input transaction and returns it.
+    pub(crate) fn take_transaction(&mut self) -> Option<Box<Transaction>> {
+        self.transaction.take()
+    }
+
+    /// Records that a query may observe the current revision.
+    ///
+    /// Inside a transaction, this forces the next input write to open a new revision.
+    #[inline]
+    pub(crate) fn observe_revision(&self) {
+        if let Some(transaction) = &self.transaction {
+            transaction.observe();
+        }
+        if let Some(journal) = &self.journal {
+            journal.observe(self.current_revision());
+        }
+    }
+
+    /// Records `undo` in the undo log of the open transaction.
+    ///
+    /// # Panics
+    ///
+    /// If no transaction is open.
+    pub(crate) fn record_undo(&mut self, undo: UndoEntry) {
+        self.transaction
+            .as_mut()
+            .expect("no transaction is open")
+            .record(undo);
+    }
+
+    /// Returns `true` if input writes are recorded in the journal.
+    ///
+    /// Discards the journal if all checkpoints have been released.
+    #[inline]
+    pub(crate) fn is_journaling(&mut self) -> bool {
+        match &self.journal {
+            Some(journal) if journal.is_released() => {
+                self.journal = None;
+                false
+            }
+            journal => journal.is_some(),
+        }
+    }
+
+    /// Takes a checkpoint at the end of the journal, starting to record it if necessary.
+    pub(crate) fn checkpoint(&mut self) -> JournalPosition {
+        let current_revision = self.current_revision();
+        self.is_journaling();
+        self.journal
+            .get_or_insert_with(|| Box::new(Journal::new(current_revision)))
+            .checkpoint(current_revision)
+    }
+
+    /// Returns `true` if a checkpoint taken with [`Runtime::checkpoint`] is still held.
+    pub(crate) fn is_checkpoint_held(&self) -> bool {
+        self.journal
+            .as_ref()
+            .is_some_and(|journal| !journal.is_released())
+    }
+
+    /// Releases a checkpoint taken with [`Runtime::checkpoint`].
+    pub(crate) fn release_checkpoint(&self) {
+        if let Some(journal) = &self.journal {
+            journal.release();
+        }
+    }
+
+    /// Records `undo` in the journal.
+    ///
+    /// # Panics
+    ///
+    /// If the journal is not being recorded.
+    pub(crate) fn record_journal(&mut self, undo: UndoEntry) {
+        self.journal
+            .as_mut()
+            .expect("the journal is not being recorded")
+            .record(undo);
+    }
+
+    /// Returns the journal, if it is being recorded.
+    pub(crate) fn journal_mut(&mut self) -> Option<&mut Journal> {
+        self.journal.as_deref_mut()
+    }
+
+    /// Restores the "last changed" revisions of all durabilities except for the
+    /// current revision.
+    pub(crate) fn restore_durability_revisions(&mut self, revisions: DurabilityRevisions) {
+        self.revisions.restore(&revisions);
+    }
+
+    /// Returns `true` if a write must advance to a new revision.
+    ///
+    /// Outside of transactions, every write opens a new revision. Inside a transaction,
+    /// only the first write (and any write after a query observed the current revision)
+    /// does.
+    pub(crate) fn write_needs_new_revision(&mut self) -> bool {
+        match &mut self.transaction {
+            Some(transaction) => transaction.needs_new_revision(),
+            None => true,
+        }
+    }
+
     /// Increments the "current revision" counter and clears
     /// the cancellation flag.
     ///
@@ -284,7 +551,7 @@ impl Runtime {
     pub(crate) fn new_revision(&mut self) -> Revision {
         let r_old = self.current_revision();
         let r_new = r_old.next();
-        self.revisions[0] = r_new;
+        self.revisions.set_current(r_new);
         *self.cancellation_count.get_mut() = 0;
         crate::tracing::info!("new_revision: {r_old:?} -> {r_new:?}");
         r_new
@@ -363,6 +630,11 @@ impl Runtime {
         }
     }
 
+    /// Returns a snapshot of the threads blocked on other threads and of the transferred locks.
+    pub(crate) fn wait_graph(&self) -> WaitGraph {
+        self.dependency_graph.lock().snapshot()
+    }
+
     /// Invoked when this runtime completed computing `database_key` with
     /// the given result `wait_result`.
     /// This function unblocks any dependent queries and allows them
@@ -418,6 +690,7 @@ impl Runtime {
     ) -> bool {
         let dg = self.dependency_graph.lock();
         DependencyGraph::transfer_lock(
+            self,
             dg,
             query,
             thread::current().id(),
@@ -430,7 +703,7 @@ impl Runtime {
     #[cfg(feature = "persistence")]
     pub(crate) fn deserialize_from(&mut self, other: &mut Runtime) {
         // The only field that is serialized is `revisions`.
-        self.revisions = other.revisions;
+        self.revisions.deserialize_from(&other.revisions);
     }
 }
 
@@ -1,15 +1,17 @@
 use std::pin::Pin;
+use std::time::Instant;
 
 use rustc_hash::FxHashMap;
 use smallvec::SmallVec;
 
 use crate::function::{SyncGuard, SyncOwner};
 use crate::key::DatabaseKeyIndex;
-use crate::runtime::WaitResult;
 use crate::runtime::dependency_graph::edge::EdgeCondvar;
-use crate::sync::MutexGuard;
+use crate::runtime::{Runtime, WaitResult};
 use crate::sync::thread::ThreadId;
-use crate::tracing;
+use crate::sync::{Arc, MutexGuard};
+use crate::wait_graph::{BlockedThread, TransferredLock, WaitGraph};
+use crate::{Backtrace, Event, EventKind, EventKindTag, tracing};
 
 type QueryDependents = FxHashMap<DatabaseKeyIndex, SmallVec<[ThreadId; 4]>>;
 type TransferredDependents = FxHashMap<DatabaseKeyIndex, SmallSet<DatabaseKeyIndex, 4>>;
@@ -63,33 +65,108 @@ impl DependencyGraph {
     /// * No path from `to_id` to `from_id`
     ///   (i.e., `me.depends_on(to_id, from_id)` is false)
     /// * `held_mutex` is a read lock (or stronger) on `database_key`
-    pub(super) fn block_on<QueryMutexGuard>(
-        mut me: MutexGuard<'_, Self>,
+    /// * `me` is the lock on the dependency graph of `runtime`
+    pub(super) fn block_on<'a, QueryMutexGuard>(
+        runtime: &'a Runtime,
+        mut me: MutexGuard<'a, Self>,
         from_id: ThreadId,
         database_key: DatabaseKeyIndex,
         to_id: ThreadId,
         query_mutex_guard: QueryMutexGuard,
     ) -> WaitResult {
+        let watchdog = runtime.block_watchdog();
+        // Capturing the query stack isn't free, only do it if someone might look at it.
+        let backtrace = watchdog.and_then(|_| Backtrace::capture()).map(Arc::new);
+
         let cvar = std::pin::pin!(EdgeCondvar::default());
         let cvar = cvar.as_ref();
         // SAFETY: We are blocking until the result is removed from `DependencyGraph::wait_results`
         // at which point the `edge` won't signal the condvar anymore.
         // As such we are keeping the cond var alive until the reference in the edge drops.
-        unsafe { me.add_edge(from_id, database_key, to_id, cvar) };
+        unsafe { me.add_edge(from_id, database_key, to_id, backtrace, cvar) };
 
         // Release the mutex that prevents `database_key`
         // from completing, now that the edge has been added.
         drop(query_mutex_guard);
 
+        let mut deadline = watchdog.map(|threshold| Instant::now() + threshold);
         loop {
             if let Some(result) = me.wait_results.remove(&from_id) {
                 debug_assert!(!me.edges.contains_key(&from_id));
                 return result;
             }
-            me = cvar.wait(me);
+            match deadline {
+                None => me = cvar.wait(me),
+                Some(at) => {
+                    let timed_out;
+                    (me, timed_out) =
+                        cvar.wait_timeout(me, at.saturating_duration_since(Instant::now()));
+                    if timed_out && !me.wait_results.contains_key(&from_id) {
+                        // Only report each wait once.
+                        deadline = None;
+                        me = Self::report_long_wait(runtime, me, from_id, database_key);
+                    }
+                }
+            }
         }
     }
 
+    /// Reports that `from_id` has been blocked on `database_key` for longer than the
+    /// threshold of the block watchdog.
+    ///
+    /// The lock on the graph is released while the event listeners run, so that they
+    /// can inspect the graph themselves.
+    #[cold]
+    fn report_long_wait<'a>(
+        runtime: &'a Runtime,
+        me: MutexGuard<'a, Self>,
+        from_id: ThreadId,
+        database_key: DatabaseKeyIndex,
+    ) -> MutexGuard<'a, Self> {
+        let wait_chain = me.snapshot().wait_chain(from_id);
+        drop(me);
+
+        tracing::warn!(
+            "thread {from_id:?} is blocked on {database_key:?} for longer than {:?}: {wait_chain:?}",
+            runtime.block_watchdog()
+        );
+        runtime.event(EventKindTag::DidExceedBlockThreshold, &|| {
+            Event::new(EventKind::DidExceedBlockThreshold {
+                database_key,
+                wait_chain: wait_chain.clone(),
+            })
+        });
+
+        runtime.dependency_graph.lock()
+    }
+
+    /// Returns a snapshot of the blocked threads and of the transferred locks.
+    pub(super) fn snapshot(&self) -> WaitGraph {
+        let now = Instant::now();
+        let blocked_threads = self
+            .edges
+            .0
+            .iter()
+            .map(|(&thread_id, edge)| {
+                BlockedThread::new(
+                    thread_id,
+                    edge.database_key,
+                    edge.blocked_on_id,
+                    now.saturating_duration_since(edge.blocked_at),
+                    edge.backtrace.clone(),
+                )
+            })
+            .collect();
+        let transferred_locks = self
+            .transferred
+            .iter()
+            .map(|(&query, &(owner_thread_id, owner))| {
+                TransferredLock::new(query, owner, owner_thread_id)
+            })
+            .collect();
+        WaitGraph::new(blocked_threads, transferred_locks)
+    }
+
     /// Helper for `block_on`: performs actual graph modification
     /// to add a dependency edge from `from_id` to `to_id`, which is
     /// computing `database_key`.
@@ -103,13 +180,14 @@ impl DependencyGraph {
         from_id: ThreadId,
         database_key: DatabaseKeyIndex,
         to_id: ThreadId,
+        backtrace: Option<Arc<Backtrace>>,
         cvar: Pin<&EdgeCondvar>,
     ) {
         assert_ne!(from_id, to_id);
         debug_assert!(!self.edges.contains_key(&from_id));
         debug_assert!(!self.depends_on(to_id, from_id));
         // SAFETY: The caller is responsible for ensuring that the `EdgeGuard` outlives the `Edge`.
-        let edge = unsafe { edge::Edge::new(to_id, cvar) };
+        let edge = unsafe { edge::Edge::new(to_id, database_key, backtrace, cvar) };
         self.edges.insert(from_id, edge);
         self.query_dependents
             .entry(database_key)
@@ -130,7 +208,7 @@ impl DependencyGraph {
             .unwrap_or_default();
 
         for from_id in dependents {
-            self.unblock_runtime(from_id, wait_result);
+            self.unblock_runtime(from_id, wait_result.clone());
         }
     }
 
@@ -161,8 +239,8 @@ impl DependencyGraph {
             me.transferred.remove(&query);
 
             for query in me.transferred_dependents.remove(&query).unwrap_or_default() {
-                me.unblock_runtimes_blocked_on(query, wait_result);
-                unblock_recursive(me, query, wait_result);
+                me.unblock_runtimes_blocked_on(query, wait_result.clone());
+                unblock_recursive(me, query, wait_result.clone());
             }
         }
 
@@ -228,8 +306,9 @@ impl DependencyGraph {
     /// on current thread after transferring the query ownership.
     ///
     /// Returns `true` if the transfer blocked on `new_owner` (in which case it might be necessary to refetch any previously computed memos).
-    pub(super) fn transfer_lock(
-        mut me: MutexGuard<Self>,
+    pub(super) fn transfer_lock<'a>(
+        runtime: &'a Runtime,
+        mut me: MutexGuard<'a, Self>,
         query: DatabaseKeyIndex,
         current_thread: ThreadId,
         new_owner: DatabaseKeyIndex,
@@ -350,7 +429,14 @@ impl DependencyGraph {
                 crate::tracing::info!(
                     "block_on: thread {current_thread:?} is blocking on {new_owner:?} in thread {new_owner_thread:?}",
                 );
-                Self::block_on(me, current_thread, new_owner, new_owner_thread, guard);
+                Self::block_on(
+                    runtime,
+                    me,
+                    current_thread,
+                    new_owner,
+                    new_owner_thread,
+                    guard,
+                );
                 return true;
             }
         }
@@ -549,10 +635,13 @@ where
 }
 
 mod edge {
+    use crate::Backtrace;
+    use crate::key::DatabaseKeyIndex;
     use crate::sync::thread::ThreadId;
-    use crate::sync::{Condvar, MutexGuard};
+    use crate::sync::{Arc, Condvar, MutexGuard};
 
     use std::pin::Pin;
+    use std::time::{Duration, Instant};
 
     #[derive(Default, Debug)]
     pub(super) struct EdgeCondvar {
@@ -565,12 +654,30 @@ mod edge {
         pub(super) fn wait<'a, T>(&self, mutex_guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
             self.condvar.wait(mutex_guard)
         }
+
+        #[inline]
+        pub(super) fn wait_timeout<'a, T>(
+            &self,
+            mutex_guard: MutexGuard<'a, T>,
+            timeout: Duration,
+        ) -> (MutexGuard<'a, T>, bool) {
+            self.condvar.wait_timeout(mutex_guard, timeout)
+        }
     }
 
     #[derive(Debug)]
     pub(super) struct Edge {
         pub(super) blocked_on_id: ThreadId,
 
+        /// The query the blocked thread is waiting for.
+        pub(super) database_key: DatabaseKeyIndex,
+
+        /// When the thread started waiting.
+        pub(super) blocked_at: Instant,
+
+        /// The query stack of the blocked thread, if captured.
+        pub(super) backtrace: Option<Arc<Backtrace>>,
+
         /// Signalled whenever a query with dependents completes.
         /// Allows those dependents to check if they are ready to unblock.
         /// `condvar: unsafe<'stack_frame> Pin<&'stack_frame Condvar>`
@@ -581,9 +688,17 @@ mod edge {
         /// # SAFETY
         ///
         /// The caller must ensure that the [`EdgeCondvar`] is kept alive until the [`Edge`] is dropped.
-        pub(super) unsafe fn new(blocked_on_id: ThreadId, condvar: Pin<&EdgeCondvar>) -> Self {
+        pub(super) unsafe fn new(
+            blocked_on_id: ThreadId,
+            database_key: DatabaseKeyIndex,
+            backtrace: Option<Arc<Backtrace>>,
+            condvar: Pin<&EdgeCondvar>,
+        ) -> Self {
             Self {
                 blocked_on_id,
+                database_key,
+                blocked_at: Instant::now(),
+                backtrace,
                 // SAFETY: The caller is responsible for ensuring that the `EdgeCondvar` outlives the `Edge`.
                 condvar: unsafe {
                     std::mem::transmute::<Pin<&EdgeCondvar>, Pin<&'static EdgeCondvar>>(condvar)
@@ -0,0 +1,112 @@
+use crate::Revision;
+use crate::durability::Durability;
+
+/// Stores the "last change" revision for values of each configured durability level.
+///
+/// The element at index 0 is special as it represents the "current revision".
+/// In general, we have the invariant that revisions in here are *declining* --
+/// that is, `revisions[i] >= revisions[i + 1]`, for all `i`. This is because
+/// when you modify a value with durability D, that implies that values with
+/// durability less than D may have changed too.
+///
+/// Durabilities above the highest configured level share the revision of that level.
+#[derive(Copy, Clone)]
+pub(crate) struct DurabilityRevisions {
+    /// The number of configured durability levels, at least 1.
+    levels: usize,
+    revisions: [Revision; Durability::LEN],
+}
+
+impl DurabilityRevisions {
+    pub(crate) fn new(levels: usize) -> Self {
+        debug_assert!((1..=Durability::LEN).contains(&levels));
+
+        Self {
+            levels,
+            revisions: [Revision::start(); Durability::LEN],
+        }
+    }
+
+    #[inline]
+    fn level(&self, durability: Durability) -> usize {
+        durability.index().min(self.levels - 1)
+    }
+
+    #[inline]
+    pub(crate) fn current(&self) -> Revision {
+        self.revisions[0]
+    }
+
+    pub(crate) fn set_current(&mut self, revision: Revision) {
+        self.revisions[0] = revision;
+    }
+
+    /// Updates the revision of every level up to `durability` to the current revision.
+    pub(crate) fn report_write(&mut self, durability: Durability) {
+        let level = self.level(durability);
+        let current = self.current();
+        self.revisions[1..=level].fill(current);
+    }
+
+    #[inline]
+    pub(crate) fn last_changed(&self, durability: Durability) -> Revision {
+        self.revisions[self.level(durability)]
+    }
+
+    /// Restores the revisions of all levels except for the current revision.
+    pub(crate) fn restore(&mut self, other: &DurabilityRevisions) {
+        debug_assert_eq!(self.levels, other.levels);
+        self.revisions[1..].copy_from_slice(&other.revisions[1..]);
+    }
+
+    fn as_slice(&self) -> &[Revision] {
+        &self.revisions[..self.levels]
+    }
+
+    /// Copies the revisions deserialized from `other`, which may have been persisted
+    /// with a different number of levels.
+    #[cfg(feature = "persistence")]
+    pub(crate) fn deserialize_from(&mut self, other: &DurabilityRevisions) {
+        // The highest persisted level covers all levels above it, and the persisted
+        // levels above the highest configured level are covered by the latter.
+        for (level, revision) in self.revisions[..self.levels].iter_mut().enumerate() {
+            *revision = other.revisions[level.min(other.levels - 1)];
+        }
+    }
+}
+
+impl std::fmt::Debug for DurabilityRevisions {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_slice().fmt(fmt)
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl serde::Serialize for DurabilityRevisions {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde::Serialize::serialize(self.as_slice(), serializer)
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl<'de> serde::Deserialize<'de> for DurabilityRevisions {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let revisions: Vec<Revision> = serde::Deserialize::deserialize(deserializer)?;
+        if !(1..=Durability::LEN).contains(&revisions.len()) {
+            return Err(serde::de::Error::invalid_length(
+                revisions.len(),
+                &"between 1 and 8 durability levels",
+            ));
+        }
+
+        let mut this = Self::new(revisions.len());
+        this.revisions[..revisions.len()].copy_from_slice(&revisions);
+        Ok(this)
+    }
+}
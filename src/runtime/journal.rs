@@ -0,0 +1,97 @@
+use crate::Revision;
+use crate::revision::AtomicRevision;
+use crate::runtime::{RestoreMode, UndoEntry};
+use crate::sync::atomic::{AtomicUsize, Ordering};
+
+/// The position in the input journal at which a [`Checkpoint`](`crate::Checkpoint`) was taken.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct JournalPosition {
+    /// The number of journal entries at the time the checkpoint was taken.
+    position: usize,
+
+    /// The current revision at the time the checkpoint was taken.
+    revision: Revision,
+}
+
+/// Journal of input writes that can be undone up to a [`Checkpoint`](`crate::Checkpoint`).
+///
+/// Recording starts with the first checkpoint. Every write made through a checkpoint
+/// records an [`UndoEntry`] restoring the previous value, revision and durability of
+/// the field. Once all checkpoints have been released, the journal is discarded.
+pub(crate) struct Journal {
+    /// Undo entries for every write, in write order.
+    entries: Vec<UndoEntry>,
+
+    /// The number of checkpoints that have not been released yet.
+    ///
+    /// Checkpoints are released through a shared reference to the database, so the
+    /// journal is only discarded by the next checkpoint or input write.
+    checkpoints: AtomicUsize,
+
+    /// The last revision in which a query ran while recording.
+    last_observed: AtomicRevision,
+}
+
+impl Journal {
+    pub(super) fn new(current_revision: Revision) -> Self {
+        Self {
+            entries: Vec::new(),
+            checkpoints: AtomicUsize::new(0),
+            last_observed: AtomicRevision::new(current_revision),
+        }
+    }
+
+    pub(super) fn checkpoint(&mut self, current_revision: Revision) -> JournalPosition {
+        *self.checkpoints.get_mut() += 1;
+        JournalPosition {
+            position: self.entries.len(),
+            revision: current_revision,
+        }
+    }
+
+    pub(super) fn release(&self) {
+        self.checkpoints.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if all checkpoints have been released.
+    pub(super) fn is_released(&self) -> bool {
+        self.checkpoints.load(Ordering::Relaxed) == 0
+    }
+
+    #[inline]
+    pub(super) fn observe(&self, current_revision: Revision) {
+        if self.last_observed.load() != current_revision {
+            self.last_observed.store(current_revision);
+        }
+    }
+
+    pub(super) fn record(&mut self, undo: UndoEntry) {
+        self.entries.push(undo);
+    }
+
+    /// Removes and returns the undo entries recorded after `checkpoint` (in reverse write order),
+    /// along with how the fields must be restored.
+    pub(crate) fn undo_to(&mut self, checkpoint: JournalPosition) -> (RestoreMode, Vec<UndoEntry>) {
+        // Memos verified after the checkpoint may depend on the values being undone, in which
+        // case the restored values must be recorded as a new change.
+        let mode = if self.last_observed.load() > checkpoint.revision {
+            RestoreMode::AsWrite
+        } else {
+            RestoreMode::Exact
+        };
+
+        let mut undo_log: Vec<_> = self.entries.drain(checkpoint.position..).collect();
+        undo_log.reverse();
+        (mode, undo_log)
+    }
+}
+
+impl std::fmt::Debug for Journal {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_struct("Journal")
+            .field("writes", &self.entries.len())
+            .field("checkpoints", &self.checkpoints.load(Ordering::Relaxed))
+            .field("last_observed", &self.last_observed.load())
+            .finish()
+    }
+}
@@ -0,0 +1,111 @@
+use crate::runtime::DurabilityRevisions;
+use crate::sync::atomic::{AtomicBool, Ordering};
+use crate::zalsa::Zalsa;
+
+/// An entry in the undo log of a [`Transaction`].
+///
+/// Each entry restores a single input field to the value it had before the write
+/// that recorded the entry.
+pub(crate) type UndoEntry = Box<dyn FnOnce(&mut Zalsa, RestoreMode) + Send + Sync>;
+
+/// How a field is restored when a transaction is rolled back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum RestoreMode {
+    /// Nothing observed the revision opened by the transaction, so the field can be
+    /// restored to its previous revision and durability as if the write never happened.
+    Exact,
+
+    /// Some query may have observed the written value, so the restore is recorded
+    /// as a new write in the current revision.
+    AsWrite,
+}
+
+/// State of an open input transaction.
+///
+/// While a transaction is open, all input writes share a single new revision. The
+/// revision is opened lazily by the first write and is only advanced again if a query
+/// observed it in the meantime (otherwise that query's memo could be verified against
+/// an input that changes later in the same revision).
+pub(crate) struct Transaction {
+    /// The "last changed" revisions of each durability before the transaction started.
+    revisions_before: DurabilityRevisions,
+
+    /// Whether a write of this transaction already opened a new revision.
+    opened_revision: bool,
+
+    /// Set when a query runs in the revision opened by this transaction.
+    observed: AtomicBool,
+
+    /// Whether any query observed a revision opened by this transaction.
+    ever_observed: bool,
+
+    /// Whether the transaction can be rolled back. Writes to inputs of a batch
+    /// (see [`Zalsa::begin_batch`]) share a revision but don't record undo entries.
+    undoable: bool,
+
+    /// Undo entries for every write performed in this transaction, in write order.
+    undo_log: Vec<UndoEntry>,
+}
+
+impl Transaction {
+    pub(super) fn new(revisions_before: DurabilityRevisions, undoable: bool) -> Self {
+        Self {
+            revisions_before,
+            opened_revision: false,
+            observed: AtomicBool::new(false),
+            ever_observed: false,
+            undoable,
+            undo_log: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if a write requires advancing to a new revision.
+    ///
+    /// Resets the observation flag, assuming the caller will open the revision.
+    pub(super) fn needs_new_revision(&mut self) -> bool {
+        let observed = std::mem::replace(self.observed.get_mut(), false);
+        self.ever_observed |= observed;
+        let needs_new_revision = !self.opened_revision || observed;
+        self.opened_revision = true;
+        needs_new_revision
+    }
+
+    #[inline]
+    pub(super) fn observe(&self) {
+        if self.opened_revision && !self.observed.load(Ordering::Relaxed) {
+            self.observed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    #[inline]
+    pub(super) fn is_undoable(&self) -> bool {
+        self.undoable
+    }
+
+    pub(super) fn record(&mut self, undo: UndoEntry) {
+        self.undo_log.push(undo);
+    }
+
+    /// Returns the durability revisions to restore along with the undo entries to
+    /// apply (in reverse write order) when rolling back this transaction.
+    pub(crate) fn into_rollback(mut self) -> (RestoreMode, DurabilityRevisions, Vec<UndoEntry>) {
+        let mode = if self.ever_observed || *self.observed.get_mut() {
+            RestoreMode::AsWrite
+        } else {
+            RestoreMode::Exact
+        };
+        self.undo_log.reverse();
+        (mode, self.revisions_before, self.undo_log)
+    }
+}
+
+impl std::fmt::Debug for Transaction {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_struct("Transaction")
+            .field("opened_revision", &self.opened_revision)
+            .field("observed", &self.observed)
+            .field("undoable", &self.undoable)
+            .field("writes", &self.undo_log.len())
+            .finish()
+    }
+}
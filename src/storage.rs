@@ -1,11 +1,12 @@
 //! Public API facades for the implementation details of [`Zalsa`] and [`ZalsaLocal`].
 use std::marker::PhantomData;
 use std::panic::RefUnwindSafe;
+use std::time::Duration;
 
 use crate::sync::{Arc, Condvar, Mutex};
 use crate::zalsa::{ErasedJar, HasJar, Zalsa, ZalsaDatabase};
 use crate::zalsa_local::{self, ZalsaLocal};
-use crate::{Database, Event, EventKind};
+use crate::{Database, Durability, Event, EventKind, EventKindTag, WriteQueue};
 
 /// A handle to non-local database state.
 pub struct StorageHandle<Db> {
@@ -18,6 +19,9 @@ pub struct StorageHandle<Db> {
     /// This could be stored in Zalsa but it makes things marginally cleaner to keep it separate.
     coordinate: CoordinateDrop,
 
+    /// Input writes enqueued by any handle, applied by the owner of the database.
+    write_queue: WriteQueue<Db>,
+
     /// We store references to `Db`
     phantom: PhantomData<fn() -> Db>,
 }
@@ -29,6 +33,7 @@ impl<Db> Clone for StorageHandle<Db> {
         Self {
             zalsa_impl: self.zalsa_impl.clone(),
             coordinate: CoordinateDrop(Arc::clone(&self.coordinate)),
+            write_queue: self.write_queue.clone(),
             phantom: PhantomData,
         }
     }
@@ -42,15 +47,18 @@ impl<Db: Database> Default for StorageHandle<Db> {
 
 impl<Db: Database> StorageHandle<Db> {
     pub fn new(event_callback: Option<Box<dyn Fn(crate::Event) + Send + Sync + 'static>>) -> Self {
-        Self::with_jars(event_callback, Vec::new())
+        Self::with_jars(event_callback, Vec::new(), Durability::DEFAULT_LEVELS)
     }
 
     fn with_jars(
         event_callback: Option<Box<dyn Fn(crate::Event) + Send + Sync + 'static>>,
         jars: Vec<ErasedJar>,
+        durability_levels: usize,
     ) -> Self {
+        let zalsa_impl = Arc::new(Zalsa::new::<Db>(event_callback, jars, durability_levels));
         Self {
-            zalsa_impl: Arc::new(Zalsa::new::<Db>(event_callback, jars)),
+            write_queue: WriteQueue::new(&zalsa_impl),
+            zalsa_impl,
             coordinate: CoordinateDrop(Arc::new(Coordinate {
                 clones: Mutex::new(1),
                 cvar: Default::default(),
@@ -59,6 +67,12 @@ impl<Db: Database> StorageHandle<Db> {
         }
     }
 
+    /// Returns the queue of input writes shared by all handles of this storage,
+    /// see [`WriteQueue`].
+    pub fn write_queue(&self) -> WriteQueue<Db> {
+        self.write_queue.clone()
+    }
+
     pub fn into_storage(self) -> Storage<Db> {
         Storage {
             handle: self,
@@ -77,6 +91,11 @@ impl<Db: Database> StorageHandle<Db> {
 pub unsafe trait HasStorage: Database + Sized {
     fn storage(&self) -> &Storage<Self>;
     fn storage_mut(&mut self) -> &mut Storage<Self>;
+
+    /// Returns a function cloning the database, if it implements `Clone`.
+    fn clone_fn() -> Option<fn(&Self) -> Self> {
+        None
+    }
 }
 
 /// Concrete implementation of the [`Database`] trait with local state that can be used to drive computations.
@@ -127,6 +146,12 @@ impl<Db: Database> Storage<Db> {
         StorageBuilder::default()
     }
 
+    /// Returns the queue of input writes shared by all handles of this storage,
+    /// see [`WriteQueue`].
+    pub fn write_queue(&self) -> WriteQueue<Db> {
+        self.handle.write_queue()
+    }
+
     /// Convert this instance of [`Storage`] into a [`StorageHandle`].
     ///
     /// This will discard the local state of this [`Storage`], thereby returning a value that
@@ -153,22 +178,40 @@ impl<Db: Database> Storage<Db> {
     /// Sets cancellation flag and blocks until all other workers with access
     /// to this storage have completed.
     ///
+    /// With selective cancellation, only the workers that may depend on inputs
+    /// of durability `durability` or lower are cancelled. The others keep running
+    /// on the current revision until they drop their handle.
+    ///
     /// This could deadlock if there is a single worker with two handles to the
     /// same database!
     ///
     /// Needs to be paired with a call to `reset_cancellation_flag`.
-    fn cancel_others(&mut self) -> &mut Zalsa {
+    fn cancel_others(&mut self, durability: Durability) -> &mut Zalsa {
         debug_assert!(
             self.zalsa_local
                 .try_with_query_stack(|stack| stack.is_empty())
                 == Some(true),
             "attempted to cancel within query computation, this is a deadlock"
         );
-        self.handle.zalsa_impl.runtime().set_cancellation_flag();
+
+        // Inside a transaction, other workers were already cancelled when it was opened.
+        // Only cancel again if new handles have been created since then.
+        if self.handle.zalsa_impl.runtime().in_transaction()
+            && *self.handle.coordinate.clones.lock() == 1
+        {
+            return Arc::get_mut(&mut self.handle.zalsa_impl).unwrap();
+        }
 
         self.handle
             .zalsa_impl
-            .event(&|| Event::new(EventKind::DidSetCancellationFlag));
+            .runtime()
+            .set_cancellation_flag(durability);
+
+        self.handle
+            .zalsa_impl
+            .event(EventKindTag::DidSetCancellationFlag, &|| {
+                Event::new(EventKind::DidSetCancellationFlag)
+            });
 
         let mut clones = self.handle.coordinate.clones.lock();
         while *clones != 1 {
@@ -182,7 +225,7 @@ impl<Db: Database> Storage<Db> {
         // a worker unwinding from cancellation could insert a provisional memo with the new epoch.
         let overflow = zalsa.runtime_mut().bump_cancellation_count();
         if overflow {
-            zalsa.new_revision();
+            zalsa.advance_revision();
         }
         zalsa
     }
@@ -195,6 +238,9 @@ impl<Db: Database> Storage<Db> {
 pub struct StorageBuilder<Db> {
     jars: Vec<ErasedJar>,
     event_callback: Option<Box<dyn Fn(crate::Event) + Send + Sync + 'static>>,
+    durability_levels: usize,
+    selective_cancellation: bool,
+    block_watchdog: Option<Duration>,
     _db: PhantomData<Db>,
 }
 
@@ -203,6 +249,9 @@ impl<Db> Default for StorageBuilder<Db> {
         Self {
             jars: Vec::new(),
             event_callback: None,
+            durability_levels: Durability::DEFAULT_LEVELS,
+            selective_cancellation: false,
+            block_watchdog: None,
             _db: PhantomData,
         }
     }
@@ -228,10 +277,70 @@ impl<Db: Database> StorageBuilder<Db> {
         self
     }
 
+    /// Set the number of durability levels the database tracks changes for, at most 8.
+    ///
+    /// By default, there are three levels: [`Durability::LOW`], [`Durability::MEDIUM`] and
+    /// [`Durability::HIGH`]. More levels allow skipping the verification of queries whose
+    /// inputs are more durable than the inputs that changed. Durabilities above the
+    /// highest configured level are treated like that level, see [`Durability::new`].
+    ///
+    /// # Panics
+    ///
+    /// If `levels` is zero or greater than 8.
+    pub fn durability_levels(mut self, levels: usize) -> Self {
+        assert!(
+            (1..=Durability::LEN).contains(&levels),
+            "the number of durability levels must be between 1 and {}",
+            Durability::LEN
+        );
+        self.durability_levels = levels;
+        self
+    }
+
+    /// Only cancel the queries that may depend on the inputs being changed when
+    /// setting an input, instead of all queries running on other database handles.
+    ///
+    /// A query may depend on an input being changed if it, or one of the queries it
+    /// was called by, has read an input of the same or lower durability so far.
+    /// The queries that are not cancelled keep running on the current revision,
+    /// and the write waits for their database handles to be dropped as usual.
+    ///
+    /// This allows long-running work that only reads inputs of high durability, such
+    /// as indexing libraries, to complete instead of being restarted after inputs of
+    /// low durability are changed. It trades cancellation for writer latency: the write
+    /// can only be applied once no other handle remains, so it blocks until the work
+    /// that wasn't cancelled completes. Drop long-lived handles of unaffected workers
+    /// regularly if writes must remain responsive.
+    ///
+    /// The inputs are only filtered by durability, not by ingredient: a query records
+    /// the tracked functions it called but not the inputs they read, whereas the lowest
+    /// durability of the inputs read is propagated to every query depending on them.
+    pub fn selective_cancellation(mut self, selective: bool) -> Self {
+        self.selective_cancellation = selective;
+        self
+    }
+
+    /// Report a [`DidExceedBlockThreshold`](`crate::EventKind::DidExceedBlockThreshold`)
+    /// event when a thread is blocked on a query running on another thread for longer
+    /// than `threshold`.
+    ///
+    /// The event contains the chain of threads the blocked thread is waiting for and
+    /// their query stacks, which helps to diagnose deadlocks and slow queries. Capturing the
+    /// query stacks makes blocking slightly more expensive.
+    pub fn block_watchdog(mut self, threshold: Duration) -> Self {
+        self.block_watchdog = Some(threshold);
+        self
+    }
+
     /// Construct the [`Storage`] using the provided builder options.
     pub fn build(self) -> Storage<Db> {
+        let mut handle =
+            StorageHandle::with_jars(self.event_callback, self.jars, self.durability_levels);
+        let runtime = Arc::get_mut(&mut handle.zalsa_impl).unwrap().runtime_mut();
+        runtime.set_selective_cancellation(self.selective_cancellation);
+        runtime.set_block_watchdog(self.block_watchdog);
         Storage {
-            handle: StorageHandle::with_jars(self.event_callback, self.jars),
+            handle,
             zalsa_local: ZalsaLocal::new(),
         }
     }
@@ -245,13 +354,23 @@ unsafe impl<T: HasStorage> ZalsaDatabase for T {
     }
 
     fn zalsa_mut(&mut self) -> &mut Zalsa {
-        self.storage_mut().cancel_others()
+        WriteQueue::apply_pending(self);
+        self.storage_mut().cancel_others(Durability::MAX)
+    }
+
+    fn zalsa_mut_for_write(&mut self, durability: Durability) -> &mut Zalsa {
+        WriteQueue::apply_pending(self);
+        self.storage_mut().cancel_others(durability)
     }
 
     #[inline(always)]
     fn zalsa_local(&self) -> &ZalsaLocal {
         &self.storage().zalsa_local
     }
+
+    fn fork_db(&self) -> Option<Box<dyn Database>> {
+        T::clone_fn().map(|clone| Box::new(clone(self)) as Box<dyn Database>)
+    }
 }
 
 impl<Db: Database> Clone for Storage<Db> {
@@ -33,6 +33,16 @@ pub mod shim {
             self.0.wait(guard).unwrap()
         }
 
+        /// Waits for at most `timeout`, returning `true` if the wait timed out.
+        pub fn wait_timeout<'a, T>(
+            &self,
+            guard: MutexGuard<'a, T>,
+            timeout: std::time::Duration,
+        ) -> (MutexGuard<'a, T>, bool) {
+            let (guard, result) = self.0.wait_timeout(guard, timeout).unwrap();
+            (guard, result.timed_out())
+        }
+
         pub fn notify_one(&self) {
             self.0.notify_one();
         }
@@ -141,6 +151,16 @@ pub mod shim {
             guard
         }
 
+        /// Waits for at most `timeout`, returning `true` if the wait timed out.
+        pub fn wait_timeout<'a, T>(
+            &self,
+            mut guard: MutexGuard<'a, T>,
+            timeout: std::time::Duration,
+        ) -> (MutexGuard<'a, T>, bool) {
+            let timed_out = self.0.wait_for(&mut guard, timeout).timed_out();
+            (guard, timed_out)
+        }
+
         pub fn notify_one(&self) {
             self.0.notify_one();
         }
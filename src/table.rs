@@ -355,6 +355,20 @@ impl Table {
             })
     }
 
+    /// Returns the ids of all slots of type `T`, without accessing the slots.
+    ///
+    /// The ids have generation 0.
+    pub(crate) fn ids_of<T: Slot>(&self) -> impl Iterator<Item = Id> + '_ {
+        self.pages
+            .iter()
+            .filter_map(|(page_index, page)| Some((page_index, page.cast_type::<T>()?)))
+            .flat_map(move |(page_index, view)| {
+                (0..view.page_data().len()).map(move |slot_index| {
+                    make_id(PageIndex::new(page_index), SlotIndex::new(slot_index))
+                })
+            })
+    }
+
     #[cold]
     #[inline(never)]
     pub(crate) fn fetch_or_push_page<T: Slot>(
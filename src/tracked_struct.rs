@@ -25,7 +25,7 @@ use crate::table::memo::{MemoTable, MemoTableTypes, MemoTableWithTypesMut};
 use crate::table::{Slot, Table};
 use crate::zalsa::{IngredientIndex, JarKind, Zalsa};
 use crate::zalsa_local::QueryEdge;
-use crate::{Durability, Event, EventKind, Id, Revision};
+use crate::{Durability, Event, EventKind, EventKindTag, Id, Revision};
 
 pub mod tracked_field;
 
@@ -224,6 +224,14 @@ pub(crate) struct Identity {
 }
 
 impl Identity {
+    pub(crate) fn new(key: IdentityHash, disambiguator: Disambiguator) -> Self {
+        Self {
+            ingredient_index: key.ingredient_index,
+            hash: key.hash,
+            disambiguator,
+        }
+    }
+
     pub(crate) fn ingredient_index(&self) -> IngredientIndex {
         self.ingredient_index
     }
@@ -244,6 +252,17 @@ pub struct IdentityHash {
     hash: u64,
 }
 
+impl IdentityHash {
+    /// Mixes `salt` into the hash, giving tracked structs with the same id fields
+    /// distinct identities.
+    pub(crate) fn salted(self, salt: u64) -> Self {
+        Self {
+            ingredient_index: self.ingredient_index,
+            hash: crate::hash::hash(&(self.hash, salt)),
+        }
+    }
+}
+
 /// A map from tracked struct [`Identity`] to their final [`Id`].
 #[derive(Default, Debug)]
 pub(crate) struct IdentityMap {
@@ -359,6 +378,27 @@ impl IdentityMap {
     pub(crate) fn clear(&mut self) {
         self.table.clear()
     }
+
+    /// Returns the tracked structs created by a previous execution of the query
+    /// that the current execution didn't create (yet).
+    #[cfg(feature = "rayon")]
+    pub(crate) fn inactive(&self) -> Vec<(Identity, Id)> {
+        self.table
+            .iter()
+            .filter(|entry| !entry.active)
+            .map(|entry| (entry.identity, entry.id))
+            .collect()
+    }
+
+    /// Marks the tracked structs created by `other` as created by the current query.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn merge_active(&mut self, other: IdentityMap) {
+        for entry in other.table {
+            if entry.active {
+                self.insert_entry(entry.identity, entry.id, true);
+            }
+        }
+    }
 }
 
 /// A tracked struct entry stored in an [`IdentityMap`].
@@ -460,6 +500,23 @@ impl DisambiguatorMap {
         result
     }
 
+    /// Continues numbering after the disambiguators handed out by `other`.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn merge(&mut self, other: DisambiguatorMap) {
+        for (key, disambiguator) in other.map {
+            let entry = self.map.raw_entry_mut().from_hash(key.hash, |k| *k == key);
+            match entry {
+                hashbrown::hash_map::RawEntryMut::Occupied(mut occupied) => {
+                    let current = occupied.get_mut();
+                    current.0 = current.0.max(disambiguator.0);
+                }
+                hashbrown::hash_map::RawEntryMut::Vacant(vacant) => {
+                    vacant.insert_with_hasher(key.hash, key, disambiguator, |k| k.hash);
+                }
+            }
+        }
+    }
+
     pub(crate) fn seed<'a>(&mut self, identities: impl Iterator<Item = &'a Identity>) {
         for identity in identities {
             self.disambiguate(IdentityHash {
@@ -509,13 +566,7 @@ where
             hash: crate::hash::hash(&C::untracked_fields(&fields)),
         };
 
-        let (current_deps, disambiguator) = zalsa_local.disambiguate(identity_hash);
-
-        let identity = Identity {
-            hash: identity_hash.hash,
-            ingredient_index: identity_hash.ingredient_index,
-            disambiguator,
-        };
+        let (current_deps, identity) = zalsa_local.disambiguate(identity_hash);
 
         if let Some(id) = zalsa_local.tracked_struct_id(&identity) {
             // The struct already exists in the intern map.
@@ -780,7 +831,7 @@ where
     /// unspecified results (but not UB). See [`InternedIngredient::delete_index`] for more
     /// discussion and important considerations.
     pub(crate) fn delete_entity(&self, zalsa: &Zalsa, id: Id) {
-        zalsa.event(&|| {
+        zalsa.event(crate::EventKindTag::DidDiscard, &|| {
             Event::new(crate::EventKind::DidDiscard {
                 key: self.database_key_index(id),
             })
@@ -842,7 +893,9 @@ where
 
                 let executor = DatabaseKeyIndex::new(ingredient_index, id);
 
-                zalsa.event(&|| Event::new(EventKind::DidDiscard { key: executor }));
+                zalsa.event(EventKindTag::DidDiscard, &|| {
+                    Event::new(EventKind::DidDiscard { key: executor })
+                });
 
                 memo.remove_outputs(zalsa, executor);
             })
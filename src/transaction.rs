@@ -0,0 +1,57 @@
+use std::ops::Deref;
+
+use crate::input::setter::{FieldWriter, did_set_field};
+use crate::zalsa::Zalsa;
+use crate::{Database, DatabaseKeyIndex, Durability};
+
+/// An open input transaction, see [`DatabaseExt::transaction`](`crate::DatabaseExt::transaction`).
+///
+/// Input fields are set through the transaction instead of the database, which requires
+/// their type to implement `Clone`: rolling back the transaction restores a copy of the
+/// previous value of every field it sets. The transaction dereferences to the database,
+/// e.g. to call tracked functions with `&**tx`.
+pub struct Transaction<'db, Db: ?Sized> {
+    db: &'db mut Db,
+}
+
+impl<'db, Db: ?Sized + Database> Transaction<'db, Db> {
+    pub(crate) fn new(db: &'db mut Db) -> Self {
+        Self { db }
+    }
+
+    /// Returns the database the transaction writes to.
+    pub fn db(&self) -> &Db {
+        self.db
+    }
+}
+
+impl<Db: ?Sized> Deref for Transaction<'_, Db> {
+    type Target = Db;
+
+    fn deref(&self) -> &Db {
+        self.db
+    }
+}
+
+impl<Db, F> FieldWriter<F> for Transaction<'_, Db>
+where
+    Db: ?Sized + Database,
+    F: Clone,
+{
+    fn field_zalsa(&self) -> &Zalsa {
+        self.db.zalsa()
+    }
+
+    fn field_zalsa_mut(&mut self, durability: Durability) -> &mut Zalsa {
+        self.db.zalsa_mut_for_write(durability)
+    }
+
+    fn field_written(&mut self, database_key: DatabaseKeyIndex, durability: Durability) {
+        // The watches are revalidated once the transaction is committed.
+        did_set_field(self.db, database_key, durability);
+    }
+
+    fn clone_fn() -> Option<fn(&F) -> F> {
+        Some(F::clone)
+    }
+}
@@ -97,6 +97,7 @@ impl Views {
         let source_type_id = TypeId::of::<Db>();
         let view_casters = boxcar::Vec::new();
         view_casters.push(ViewCaster::new::<dyn Database>(|db| db.ptr.cast::<Db>()));
+        view_casters.push(ViewCaster::new::<Db>(|db| db.ptr.cast::<Db>()));
         Self {
             source_type_id,
             view_casters,
@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use crate::sync::Arc;
+use crate::sync::thread::ThreadId;
+use crate::{Backtrace, DatabaseKeyIndex};
+
+/// A snapshot of which threads are blocked on queries running on other threads, as
+/// returned by [`DatabaseExt::wait_graph`](`crate::DatabaseExt::wait_graph`).
+///
+/// Threads and transferred locks are listed in no particular order.
+#[derive(Clone, Debug, Default)]
+pub struct WaitGraph {
+    blocked_threads: Vec<BlockedThread>,
+    transferred_locks: Vec<TransferredLock>,
+}
+
+/// A thread waiting for a query that runs on another thread.
+#[derive(Clone, Debug)]
+pub struct BlockedThread {
+    thread_id: ThreadId,
+    database_key: DatabaseKeyIndex,
+    blocked_on: ThreadId,
+    waited: Duration,
+    backtrace: Option<Arc<Backtrace>>,
+}
+
+/// A query whose lock is owned by another query, e.g. because both are part of the same cycle.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TransferredLock {
+    database_key: DatabaseKeyIndex,
+    owner: DatabaseKeyIndex,
+    owner_thread_id: ThreadId,
+}
+
+impl WaitGraph {
+    pub(crate) fn new(
+        blocked_threads: Vec<BlockedThread>,
+        transferred_locks: Vec<TransferredLock>,
+    ) -> Self {
+        Self {
+            blocked_threads,
+            transferred_locks,
+        }
+    }
+
+    /// Returns the threads that are blocked on another thread.
+    pub fn blocked_threads(&self) -> &[BlockedThread] {
+        &self.blocked_threads
+    }
+
+    /// Returns the queries whose lock has been transferred to another query.
+    pub fn transferred_locks(&self) -> &[TransferredLock] {
+        &self.transferred_locks
+    }
+
+    /// Returns what the thread `thread_id` is blocked on, if it is blocked.
+    pub fn blocked_thread(&self, thread_id: ThreadId) -> Option<&BlockedThread> {
+        self.blocked_threads
+            .iter()
+            .find(|blocked| blocked.thread_id == thread_id)
+    }
+
+    /// Returns the chain of threads `thread_id` is waiting for: the thread itself, the
+    /// thread it is blocked on, the thread that one is blocked on and so on.
+    ///
+    /// The last thread of the chain is blocked on a thread that is running, which
+    /// isn't part of the chain and whose query stack isn't known.
+    /// The chain is empty if `thread_id` isn't blocked.
+    pub fn wait_chain(&self, thread_id: ThreadId) -> Vec<BlockedThread> {
+        let mut chain: Vec<BlockedThread> = Vec::new();
+        let mut next = self.blocked_thread(thread_id);
+        while let Some(blocked) = next {
+            // The graph of blocked threads is acyclic, but don't rely on it for diagnostics.
+            if chain.iter().any(|b| b.thread_id == blocked.thread_id) {
+                break;
+            }
+            chain.push(blocked.clone());
+            next = self.blocked_thread(blocked.blocked_on);
+        }
+        chain
+    }
+}
+
+impl BlockedThread {
+    pub(crate) fn new(
+        thread_id: ThreadId,
+        database_key: DatabaseKeyIndex,
+        blocked_on: ThreadId,
+        waited: Duration,
+        backtrace: Option<Arc<Backtrace>>,
+    ) -> Self {
+        Self {
+            thread_id,
+            database_key,
+            blocked_on,
+            waited,
+            backtrace,
+        }
+    }
+
+    /// Returns the id of the blocked thread.
+    pub fn thread_id(&self) -> ThreadId {
+        self.thread_id
+    }
+
+    /// Returns the query the thread is waiting for.
+    pub fn database_key(&self) -> DatabaseKeyIndex {
+        self.database_key
+    }
+
+    /// Returns the thread that owns the lock of the query the thread is waiting for.
+    pub fn blocked_on(&self) -> ThreadId {
+        self.blocked_on
+    }
+
+    /// Returns how long the thread had been waiting when the snapshot was taken.
+    pub fn waited(&self) -> Duration {
+        self.waited
+    }
+
+    /// Returns the query stack of the thread when it started waiting, innermost query first.
+    ///
+    /// Only captured while a block watchdog is configured, see
+    /// [`Storage::builder`](`crate::Storage::builder`).
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_deref()
+    }
+}
+
+impl TransferredLock {
+    pub(crate) fn new(
+        database_key: DatabaseKeyIndex,
+        owner: DatabaseKeyIndex,
+        owner_thread_id: ThreadId,
+    ) -> Self {
+        Self {
+            database_key,
+            owner,
+            owner_thread_id,
+        }
+    }
+
+    /// Returns the query whose lock has been transferred.
+    pub fn database_key(&self) -> DatabaseKeyIndex {
+        self.database_key
+    }
+
+    /// Returns the query that now owns the lock.
+    pub fn owner(&self) -> DatabaseKeyIndex {
+        self.owner
+    }
+
+    /// Returns the thread running the owning query.
+    pub fn owner_thread_id(&self) -> ThreadId {
+        self.owner_thread_id
+    }
+}
@@ -0,0 +1,226 @@
+use std::marker::PhantomData;
+
+use crate::database::RawDatabase;
+use crate::sync::{Arc, Mutex};
+use crate::zalsa::Zalsa;
+use crate::zalsa_local::ZalsaLocal;
+use crate::{Database, DatabaseExt, DatabaseKeyIndex, Id, Revision};
+
+/// Identifies a watch registered with [`DatabaseExt::watch`](`crate::DatabaseExt::watch`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WatchId(Id);
+
+/// A type-erased watched query together with its callback.
+trait ErasedWatch: Send {
+    /// Executes the watched query, retaining its result.
+    ///
+    /// # Safety
+    ///
+    /// `db` must be the database the watch was registered with.
+    unsafe fn execute(&mut self, db: RawDatabase<'_>);
+
+    /// Passes the retained result to the callback if `notify` is `true`, otherwise drops it.
+    fn finish(&mut self, notify: bool);
+}
+
+struct TypedWatch<Db, K, V, Q, F> {
+    query: Q,
+    key: K,
+    callback: F,
+    value: Option<V>,
+    phantom: PhantomData<fn(&Db)>,
+}
+
+impl<Db, K, V, Q, F> ErasedWatch for TypedWatch<Db, K, V, Q, F>
+where
+    Db: Database,
+    K: Clone + Send,
+    V: Send,
+    Q: Fn(&Db, K) -> V + Send,
+    F: FnMut(V) + Send,
+{
+    unsafe fn execute(&mut self, db: RawDatabase<'_>) {
+        // SAFETY: The caller guarantees that `db` is the database the watch was registered
+        // with, which is of type `Db`.
+        let db = unsafe { db.ptr.cast::<Db>().as_ref() };
+        self.value = Some((self.query)(db, self.key.clone()));
+    }
+
+    fn finish(&mut self, notify: bool) {
+        if let Some(value) = self.value.take() {
+            if notify {
+                (self.callback)(value);
+            }
+        }
+    }
+}
+
+struct Watch {
+    /// The query and its callback, `None` while they are being run by `poll`.
+    query: Option<Box<dyn ErasedWatch>>,
+
+    /// The last revision in which the result of the query was verified,
+    /// `None` if the query hasn't been executed yet.
+    verified_at: Option<Revision>,
+
+    /// The last revision in which the result of the query changed.
+    changed_at: Revision,
+
+    /// The inputs read by the query, `None` if it also read untracked state.
+    inputs: Option<Box<[DatabaseKeyIndex]>>,
+}
+
+/// The watches registered with a database.
+///
+/// A watched query is executed outside of any query, recording the tracked functions
+/// and inputs it reads so that they can be verified with the same machinery that
+/// verifies memoized values.
+#[derive(Default)]
+pub(crate) struct Watches {
+    /// The watches indexed by id, `None` once removed.
+    watches: boxcar::Vec<Mutex<Option<Watch>>>,
+}
+
+impl Watches {
+    pub(crate) fn watch<Db, K, V>(
+        &self,
+        query: impl Fn(&Db, K) -> V + Send + 'static,
+        key: K,
+        callback: impl FnMut(V) + Send + 'static,
+    ) -> WatchId
+    where
+        Db: Database,
+        K: Clone + Send + 'static,
+        V: Send + 'static,
+    {
+        let index = self.watches.push(Mutex::new(Some(Watch {
+            query: Some(Box::new(TypedWatch {
+                query,
+                key,
+                callback,
+                value: None,
+                phantom: PhantomData::<fn(&Db)>,
+            })),
+            verified_at: None,
+            changed_at: Revision::start(),
+            inputs: None,
+        })));
+
+        // SAFETY: The index is smaller than `Id::MAX_U32` as long as the number of
+        // registered watches is.
+        WatchId(unsafe { Id::from_index(index as u32) })
+    }
+
+    pub(crate) fn unwatch(&self, id: WatchId) {
+        *self.watches[id.0.index() as usize].lock() = None;
+    }
+
+    /// Revalidates all watched queries that haven't been verified in the current revision
+    /// and invokes the callbacks of those whose result changed.
+    ///
+    /// Watches that are being polled by another thread are skipped.
+    ///
+    /// # Safety
+    ///
+    /// `db` must be the database the watches were registered with.
+    pub(crate) unsafe fn poll(&self, zalsa: &Zalsa, zalsa_local: &ZalsaLocal, db: RawDatabase<'_>) {
+        let current_revision = zalsa.current_revision();
+
+        for (index, slot) in self.watches.iter() {
+            let mut guard = slot.lock();
+            let Some(watch) = &mut *guard else {
+                continue;
+            };
+
+            if let Some(verified_at) = watch.verified_at {
+                if verified_at == current_revision {
+                    continue;
+                }
+
+                let unchanged = watch.inputs.as_deref().is_some_and(|inputs| {
+                    inputs.iter().all(|input| {
+                        input
+                            .maybe_changed_after(db, zalsa, verified_at)
+                            .is_unchanged()
+                    })
+                });
+                if unchanged {
+                    watch.verified_at = Some(current_revision);
+                    continue;
+                }
+            }
+
+            let Some(mut query) = watch.query.take() else {
+                continue;
+            };
+            let first_execution = watch.verified_at.is_none();
+            let previous_changed_at = watch.changed_at;
+
+            // Run the query and the callback without holding the lock, so that
+            // the callback can remove the watch.
+            drop(guard);
+            let ((), reads) = zalsa_local.record_reads(|| {
+                // SAFETY: The caller guarantees that `db` is the database the watch was
+                // registered with.
+                unsafe { query.execute(db) }
+            });
+
+            let changed = first_execution || reads.changed_at > previous_changed_at;
+            crate::tracing::debug!("watch {index} changed: {changed}");
+            query.finish(changed);
+
+            let mut guard = slot.lock();
+            // The watch may have been removed in the meantime.
+            if let Some(watch) = &mut *guard {
+                watch.query = Some(query);
+                watch.inputs = reads.inputs.map(|inputs| inputs.into_iter().collect());
+                watch.changed_at = reads.changed_at;
+                watch.verified_at = Some(current_revision);
+            }
+        }
+    }
+}
+
+/// A callback invoked after input writes, which returns `false` once it should be removed.
+type WriteHook = Arc<dyn Fn(RawDatabase<'_>) -> bool + Send + Sync>;
+
+/// The callbacks invoked with the database after input writes, e.g. by the
+/// [`EagerScheduler`](`crate::EagerScheduler`).
+#[derive(Default)]
+pub(crate) struct WriteHooks {
+    hooks: Mutex<Vec<WriteHook>>,
+}
+
+impl WriteHooks {
+    /// Registers `hook`, which must expect the type of database it is registered with.
+    pub(crate) fn add(&self, hook: impl Fn(RawDatabase<'_>) -> bool + Send + Sync + 'static) {
+        self.hooks.lock().push(Arc::new(hook));
+    }
+
+    /// Invokes the hooks with `db`, removing those that returned `false`.
+    ///
+    /// # Safety
+    ///
+    /// `db` must be the database the hooks were registered with.
+    unsafe fn run(&self, db: RawDatabase<'_>) {
+        // The hooks are invoked without holding the lock so that they can register new ones.
+        let hooks = self.hooks.lock().clone();
+        for hook in hooks {
+            if !hook(db) {
+                self.hooks.lock().retain(|other| !Arc::ptr_eq(other, &hook));
+            }
+        }
+    }
+}
+
+/// Revalidates the watches of `db` and invokes its write hooks after an input write, unless
+/// the write is part of an open transaction or batch, which does so once it is committed.
+pub fn after_input_write<Db: ?Sized + Database>(db: &Db) {
+    if db.zalsa().runtime().in_transaction() {
+        return;
+    }
+    db.poll_watches();
+
+    // SAFETY: The hooks of a database are registered with the database itself.
+    unsafe { db.zalsa().write_hooks().run(RawDatabase::from(db)) };
+}
@@ -0,0 +1,306 @@
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe, RefUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use crate::Database;
+use crate::storage::HasStorage;
+use crate::sync::{Arc, Condvar, Mutex};
+use crate::zalsa::Zalsa;
+
+type WriteOp<Db> = Box<dyn FnOnce(&mut Db) + Send>;
+type WriteCallback = Box<dyn FnOnce(Result<(), WriteError>) + Send>;
+
+/// A queue of input writes shared by all handles of a database storage.
+///
+/// Any thread can enqueue writes with [`WriteQueue::push`], without needing `&mut`
+/// access to the database and without waiting for other handles to be dropped.
+/// The owner of the database applies all queued writes in a single revision by
+/// calling [`WriteQueue::apply`] at a point where blocking on the other handles is fine.
+/// Queued writes are also applied before the next write to the database that needs
+/// `&mut` access, e.g. setting an input, unless a transaction is open or a checkpoint
+/// is held.
+///
+/// Each write returns a [`WriteTicket`] that resolves once the write is visible to
+/// queries, or once it has been discarded.
+pub struct WriteQueue<Db> {
+    inner: Arc<QueueInner<Db>>,
+}
+
+struct QueueInner<Db> {
+    /// The address of the [`Zalsa`] of the storage owning the queue.
+    zalsa: usize,
+    pending: Mutex<Vec<PendingWrite<Db>>>,
+}
+
+// No user code runs while `pending` is locked, so a panic can't leave it in a broken state.
+impl<Db> RefUnwindSafe for QueueInner<Db> {}
+
+struct PendingWrite<Db> {
+    op: WriteOp<Db>,
+    completion: Completion,
+}
+
+impl<Db> Clone for WriteQueue<Db> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<Db> WriteQueue<Db> {
+    pub(crate) fn new(zalsa: &Zalsa) -> Self {
+        Self {
+            inner: Arc::new(QueueInner {
+                zalsa: std::ptr::from_ref(zalsa) as usize,
+                pending: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Enqueues `op` to be applied by the next call to [`WriteQueue::apply`], or before
+    /// the next write to the database.
+    ///
+    /// `op` typically calls input setters on the database it receives.
+    pub fn push(&self, op: impl FnOnce(&mut Db) + Send + 'static) -> WriteTicket {
+        let state = Arc::new(TicketState::default());
+        self.inner.pending.lock().push(PendingWrite {
+            op: Box::new(op),
+            completion: Completion(state.clone()),
+        });
+        WriteTicket(state)
+    }
+
+    /// Returns the number of writes waiting to be applied.
+    pub fn len(&self) -> usize {
+        self.inner.pending.lock().len()
+    }
+
+    /// Returns `true` if no writes are waiting to be applied.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Applies all queued writes to `db`, in the order they were enqueued, and returns
+    /// how many writes were applied.
+    ///
+    /// All writes share a single new revision. Their tickets resolve once the last write
+    /// has been applied. If a write panics, the input writes it made before panicking
+    /// are kept and the remaining writes are still applied. The ticket of the panicking
+    /// write resolves with [`WriteError::Panicked`] and the panic is resumed afterwards.
+    ///
+    /// **WARNING:** Just like an ordinary write, this method triggers
+    /// cancellation. If you invoke it while a snapshot exists, it
+    /// will block until that snapshot is dropped -- if that snapshot
+    /// is owned by the current thread, this could trigger deadlock.
+    ///
+    /// # Panics
+    ///
+    /// If `db` doesn't use the storage the queue belongs to, or if a transaction is open.
+    pub fn apply(&self, db: &mut Db) -> usize
+    where
+        Db: Database,
+    {
+        let (count, panic) = self.apply_writes(db);
+        if let Some(payload) = panic {
+            panic::resume_unwind(payload);
+        }
+        count
+    }
+
+    /// Applies the queued writes before the write of a caller with `&mut` access to `db`.
+    ///
+    /// Panics of the writes are only reported through their tickets, the caller is unrelated.
+    pub(crate) fn apply_pending(db: &mut Db)
+    where
+        Db: HasStorage,
+    {
+        let queue = db.storage().write_queue();
+        let runtime = db.zalsa().runtime();
+        if queue.is_empty() || runtime.in_transaction() || runtime.is_checkpoint_held() {
+            return;
+        }
+        let _ = queue.apply_writes(db);
+    }
+
+    /// Applies the queued writes, returning how many were applied and the first panic.
+    fn apply_writes(&self, db: &mut Db) -> (usize, Option<Box<dyn Any + Send>>)
+    where
+        Db: Database,
+    {
+        assert_eq!(
+            std::ptr::from_ref(db.zalsa()) as usize,
+            self.inner.zalsa,
+            "applying the write queue of another database"
+        );
+
+        let writes = std::mem::take(&mut *self.inner.pending.lock());
+        if writes.is_empty() {
+            return (0, None);
+        }
+
+        db.zalsa_mut().begin_batch();
+
+        let mut applied = Vec::with_capacity(writes.len());
+        let mut panic = None;
+        for PendingWrite { op, completion } in writes {
+            match panic::catch_unwind(AssertUnwindSafe(|| op(db))) {
+                Ok(()) => applied.push(completion),
+                Err(payload) => {
+                    completion.complete(Err(WriteError::Panicked));
+                    panic.get_or_insert(payload);
+                }
+            }
+        }
+
+        db.zalsa_mut().commit_transaction();
+        crate::watch::after_input_write(db);
+
+        let count = applied.len();
+        for completion in applied {
+            completion.complete(Ok(()));
+        }
+        (count, panic)
+    }
+}
+
+impl<Db> fmt::Debug for WriteQueue<Db> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteQueue")
+            .field("pending", &self.len())
+            .finish()
+    }
+}
+
+/// The error a [`WriteTicket`] resolves with if its write wasn't applied completely.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WriteError {
+    /// The write was dropped without being applied because the queue was dropped.
+    Discarded,
+
+    /// The write panicked. The input writes it made before panicking were applied.
+    Panicked,
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Discarded => f.write_str("the write was discarded without being applied"),
+            WriteError::Panicked => f.write_str("the write panicked and was applied partially"),
+        }
+    }
+}
+
+impl std::error::Error for WriteError {}
+
+/// Tracks a write enqueued with [`WriteQueue::push`].
+///
+/// The ticket resolves once the write is visible to queries. Wait for it with
+/// [`WriteTicket::wait`], `.await` it or register a callback with [`WriteTicket::on_complete`].
+#[must_use]
+pub struct WriteTicket(Arc<TicketState>);
+
+#[derive(Default)]
+struct TicketState {
+    inner: Mutex<TicketInner>,
+    cvar: Condvar,
+}
+
+#[derive(Default)]
+struct TicketInner {
+    result: Option<Result<(), WriteError>>,
+    waker: Option<Waker>,
+    callback: Option<WriteCallback>,
+}
+
+impl WriteTicket {
+    /// Returns the result of the write if it has been applied or discarded already.
+    pub fn try_result(&self) -> Option<Result<(), WriteError>> {
+        self.0.inner.lock().result
+    }
+
+    /// Blocks until the write has been applied or discarded.
+    pub fn wait(self) -> Result<(), WriteError> {
+        let mut inner = self.0.inner.lock();
+        loop {
+            if let Some(result) = inner.result {
+                return result;
+            }
+            inner = self.0.cvar.wait(inner);
+        }
+    }
+
+    /// Calls `callback` once the write has been applied or discarded.
+    ///
+    /// The callback runs on the thread resolving the ticket, or immediately if the
+    /// ticket is resolved already.
+    pub fn on_complete(self, callback: impl FnOnce(Result<(), WriteError>) + Send + 'static) {
+        let mut inner = self.0.inner.lock();
+        match inner.result {
+            Some(result) => {
+                drop(inner);
+                callback(result);
+            }
+            None => inner.callback = Some(Box::new(callback)),
+        }
+    }
+}
+
+impl Future for WriteTicket {
+    type Output = Result<(), WriteError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.0.inner.lock();
+        match inner.result {
+            Some(result) => Poll::Ready(result),
+            None => {
+                inner.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl fmt::Debug for WriteTicket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WriteTicket")
+            .field(&self.try_result())
+            .finish()
+    }
+}
+
+/// Resolves a ticket, discarding its write if dropped before it completes.
+struct Completion(Arc<TicketState>);
+
+impl Completion {
+    fn complete(self, result: Result<(), WriteError>) {
+        self.resolve(result);
+    }
+
+    fn resolve(&self, result: Result<(), WriteError>) {
+        let (waker, callback) = {
+            let mut inner = self.0.inner.lock();
+            if inner.result.is_some() {
+                return;
+            }
+            inner.result = Some(result);
+            (inner.waker.take(), inner.callback.take())
+        };
+        self.0.cvar.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        if let Some(callback) = callback {
+            callback(result);
+        }
+    }
+}
+
+impl Drop for Completion {
+    fn drop(&mut self) {
+        self.resolve(Err(WriteError::Discarded));
+    }
+}
@@ -5,13 +5,19 @@ use std::panic::RefUnwindSafe;
 use hashbrown::HashMap;
 use rustc_hash::FxHashMap;
 
-use crate::hash::TypeIdHasher;
+use crate::chrome_trace::ChromeTrace;
+use crate::execution_reason::ExecutionReasons;
+use crate::external::{ExternalIngredient, ExternalJar};
+use crate::hash::{FxHashSet, TypeIdHasher};
 use crate::ingredient::{Ingredient, Jar};
 use crate::plumbing::SalsaStructInDb;
-use crate::runtime::Runtime;
+use crate::runtime::{JournalPosition, RestoreMode, Runtime, UndoEntry};
+#[cfg(feature = "salsa_unstable")]
+use crate::sync::atomic::{AtomicBool, Ordering};
 use crate::table::Table;
 use crate::table::memo::MemoTableWithTypes;
 use crate::views::Views;
+use crate::watch::{Watches, WriteHooks};
 use crate::zalsa_local::ZalsaLocal;
 use crate::{Database, Durability, Id, Revision};
 
@@ -46,9 +52,25 @@ pub unsafe trait ZalsaDatabase: Any {
     #[doc(hidden)]
     fn zalsa_mut(&mut self) -> &mut Zalsa;
 
+    /// Plumbing method: Access the internal salsa methods for mutating the database
+    /// in order to change inputs of durability `durability` or lower.
+    ///
+    /// With selective cancellation enabled, only the queries of other database handles
+    /// that may depend on such inputs are cancelled, see [`Storage::builder`].
+    ///
+    /// [`Storage::builder`]: crate::Storage::builder
+    #[doc(hidden)]
+    fn zalsa_mut_for_write(&mut self, durability: Durability) -> &mut Zalsa;
+
     /// Access the thread-local state associated with this database
     #[doc(hidden)]
     fn zalsa_local(&self) -> &ZalsaLocal;
+
+    /// Plumbing method: Create a new handle to this database with its own thread-local state.
+    ///
+    /// Returns `None` if the database does not implement `Clone`.
+    #[doc(hidden)]
+    fn fork_db(&self) -> Option<Box<dyn Database>>;
 }
 
 pub fn views<Db: ?Sized + Database>(db: &Db) -> &Views {
@@ -165,7 +187,17 @@ pub struct Zalsa {
     /// Each handle gets its own runtime, but the runtimes have shared state between them.
     runtime: Runtime,
 
-    event_callback: Option<Box<dyn Fn(crate::Event) + Send + Sync>>,
+    execution_reasons: ExecutionReasons,
+
+    watches: Watches,
+
+    write_hooks: WriteHooks,
+
+    chrome_trace: ChromeTrace,
+
+    /// Whether tracked functions record execution and cache statistics.
+    #[cfg(feature = "salsa_unstable")]
+    profiling: AtomicBool,
 }
 
 /// All fields on Zalsa are locked behind [`Mutex`]es and [`RwLock`]s and cannot enter
@@ -179,6 +211,7 @@ impl Zalsa {
     pub(crate) fn new<Db: Database>(
         event_callback: Option<Box<dyn Fn(crate::Event) + Send + Sync + 'static>>,
         jars: Vec<ErasedJar>,
+        durability_levels: usize,
     ) -> Self {
         let mut zalsa = Self {
             views_of: Views::new::<Db>(),
@@ -186,9 +219,14 @@ impl Zalsa {
             ingredient_to_id_struct_type_id_map: Default::default(),
             ingredients_vec: Vec::new(),
             ingredients_requiring_reset: Vec::new(),
-            runtime: Runtime::default(),
+            runtime: Runtime::new(event_callback, durability_levels),
             memo_ingredient_indices: Default::default(),
-            event_callback,
+            execution_reasons: ExecutionReasons::default(),
+            watches: Watches::default(),
+            write_hooks: WriteHooks::default(),
+            chrome_trace: ChromeTrace::default(),
+            #[cfg(feature = "salsa_unstable")]
+            profiling: AtomicBool::new(false),
             #[cfg(not(feature = "inventory"))]
             nonce: NONCE.nonce(),
         };
@@ -213,9 +251,52 @@ impl Zalsa {
             zalsa.insert_jar(jar);
         }
 
+        // Built-in ingredients are inserted last so that they don't shift
+        // the indices of the user's ingredients.
+        zalsa.insert_jar(ErasedJar::erase::<ExternalJar>());
+
         zalsa
     }
 
+    /// Returns the ingredient tracking external reads.
+    pub(crate) fn external_reads(&self) -> &ExternalIngredient {
+        let index = self.lookup_jar_by_type::<ExternalJar>();
+        self.lookup_ingredient(index)
+            .assert_type::<ExternalIngredient>()
+    }
+
+    /// Returns the registered watches.
+    pub(crate) fn watches(&self) -> &Watches {
+        &self.watches
+    }
+
+    /// Returns the callbacks invoked after input writes.
+    pub(crate) fn write_hooks(&self) -> &WriteHooks {
+        &self.write_hooks
+    }
+
+    /// Returns the reasons why queries were executed.
+    pub(crate) fn execution_reasons(&self) -> &ExecutionReasons {
+        &self.execution_reasons
+    }
+
+    /// Returns the Chrome trace recording query executions.
+    pub(crate) fn chrome_trace(&self) -> &ChromeTrace {
+        &self.chrome_trace
+    }
+
+    #[cfg(feature = "salsa_unstable")]
+    pub(crate) fn set_profiling(&self, enabled: bool) {
+        self.profiling.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if tracked functions should record execution and cache statistics.
+    #[cfg(feature = "salsa_unstable")]
+    #[inline]
+    pub(crate) fn is_profiling(&self) -> bool {
+        self.profiling.load(Ordering::Relaxed)
+    }
+
     #[cfg(not(feature = "inventory"))]
     pub(crate) fn nonce(&self) -> crate::nonce::Nonce<StorageNonce> {
         self.nonce
@@ -278,7 +359,6 @@ impl Zalsa {
             [memo_ingredient_index.as_usize()]
     }
 
-    #[allow(unused)]
     pub(crate) fn ingredients(&self) -> impl Iterator<Item = &dyn Ingredient> {
         self.ingredients_vec
             .iter()
@@ -295,12 +375,18 @@ impl Zalsa {
     /// invocation.
     #[inline]
     pub(crate) fn unwind_if_revision_cancelled(&self, zalsa_local: &ZalsaLocal) {
-        self.event(&|| crate::Event::new(crate::EventKind::WillCheckCancellation));
+        self.event(crate::EventKindTag::WillCheckCancellation, &|| {
+            crate::Event::new(crate::EventKind::WillCheckCancellation)
+        });
+        self.runtime().observe_revision();
         if zalsa_local.should_trigger_local_cancellation() {
             zalsa_local.unwind_cancelled();
         }
         if self.runtime().load_cancellation_flag() {
-            zalsa_local.unwind_pending_write();
+            let durability = self.runtime().cancelled_durability();
+            if durability == Durability::MAX || zalsa_local.may_depend_on(durability) {
+                zalsa_local.unwind_pending_write();
+            }
         }
     }
 
@@ -433,26 +519,148 @@ impl Zalsa {
 
     /// **NOT SEMVER STABLE**
     /// Triggers a new revision.
+    ///
+    /// Inside a transaction, writes share a single revision and this only advances
+    /// the revision if required.
     #[doc(hidden)]
     pub fn new_revision(&mut self) -> Revision {
+        if !self.runtime.write_needs_new_revision() {
+            return self.current_revision();
+        }
+
+        self.advance_revision()
+    }
+
+    /// Unconditionally advances to a new revision, even inside a transaction.
+    pub(crate) fn advance_revision(&mut self) -> Revision {
         let new_revision = self.runtime.new_revision();
         let _span = crate::tracing::debug_span!("new_revision", ?new_revision).entered();
 
+        self.runtime
+            .event(crate::EventKindTag::DidStartRevision, &|| {
+                crate::Event::new(crate::EventKind::DidStartRevision {
+                    revision: new_revision,
+                })
+            });
+
         for ingredient in &self.ingredients_requiring_reset {
             self.ingredients_vec[ingredient.as_u32() as usize]
-                .reset_for_new_revision(self.runtime.table_mut());
+                .reset_for_new_revision(&mut self.runtime);
         }
 
+        self.collect_external_reads();
+
         new_revision
     }
 
+    /// Frees the external dependencies no memo depends on anymore, once enough
+    /// of them were reported since the last collection.
+    fn collect_external_reads(&mut self) {
+        let index = self.lookup_jar_by_type::<ExternalJar>();
+        let external =
+            self.ingredients_vec[index.as_u32() as usize].assert_type_mut::<ExternalIngredient>();
+        if !external.should_collect() {
+            return;
+        }
+
+        let mut referenced = FxHashSet::default();
+        for ingredient in self.ingredients() {
+            for key in ingredient.memoized_keys(self) {
+                let Some(info) = ingredient.query_info(self, key) else {
+                    continue;
+                };
+                referenced.extend(
+                    info.inputs()
+                        .iter()
+                        .filter(|input| input.ingredient_index() == index)
+                        .map(|input| input.key_index()),
+                );
+            }
+        }
+
+        self.ingredients_vec[index.as_u32() as usize]
+            .assert_type_mut::<ExternalIngredient>()
+            .collect_unreferenced(&referenced);
+    }
+
+    /// Opens an input transaction, see [`DatabaseExt::transaction`](`crate::DatabaseExt::transaction`).
+    pub(crate) fn begin_transaction(&mut self) {
+        self.runtime.begin_transaction(true);
+    }
+
+    /// Opens a batch of input writes that share a single new revision. Unlike a
+    /// transaction, a batch can't be rolled back. Close it with [`Zalsa::commit_transaction`].
+    pub(crate) fn begin_batch(&mut self) {
+        self.runtime.begin_transaction(false);
+    }
+
+    /// Closes the open input transaction, keeping all of its writes.
+    pub(crate) fn commit_transaction(&mut self) {
+        let transaction = self.runtime.take_transaction();
+        debug_assert!(transaction.is_some(), "no transaction is open");
+    }
+
+    /// Closes the open input transaction, reverting all of its writes.
+    pub(crate) fn rollback_transaction(&mut self) {
+        let Some(transaction) = self.runtime.take_transaction() else {
+            return;
+        };
+
+        let (mode, revisions_before, undo_log) = transaction.into_rollback();
+        if undo_log.is_empty() {
+            return;
+        }
+
+        let _span = crate::tracing::debug_span!("rollback_transaction", ?mode).entered();
+        self.apply_undo_log(mode, undo_log);
+
+        if mode == RestoreMode::Exact {
+            self.runtime.restore_durability_revisions(revisions_before);
+        }
+    }
+
+    /// Takes a checkpoint of the input journal, see [`DatabaseExt::checkpoint`](`crate::DatabaseExt::checkpoint`).
+    pub(crate) fn checkpoint(&mut self) -> JournalPosition {
+        self.runtime.checkpoint()
+    }
+
+    /// Undoes all input writes recorded after `checkpoint`, see [`Checkpoint::undo`].
+    ///
+    /// [`Checkpoint::undo`]: `crate::Checkpoint::undo`
+    pub(crate) fn undo_to(&mut self, checkpoint: JournalPosition) {
+        let journal = self
+            .runtime
+            .journal_mut()
+            .expect("the journal is not being recorded");
+
+        let (mode, undo_log) = journal.undo_to(checkpoint);
+        if undo_log.is_empty() {
+            return;
+        }
+
+        let _span = crate::tracing::debug_span!("undo_to", ?mode).entered();
+        self.apply_undo_log(mode, undo_log);
+    }
+
+    fn apply_undo_log(&mut self, mode: RestoreMode, undo_log: Vec<UndoEntry>) {
+        if mode == RestoreMode::AsWrite {
+            // Queries may have observed the written values, so the restored values have to
+            // be visible as a new change.
+            self.advance_revision();
+        }
+
+        for undo in undo_log {
+            undo(self, mode);
+        }
+    }
+
     /// **NOT SEMVER STABLE**
     #[doc(hidden)]
     pub fn evict_lru(&mut self) {
         let _span = crate::tracing::debug_span!("evict_lru").entered();
         for ingredient in &self.ingredients_requiring_reset {
             self.ingredients_vec[ingredient.as_u32() as usize]
-                .reset_for_new_revision(self.runtime.table_mut());
+                .reset_for_new_revision(&mut self.runtime);
         }
     }
 
@@ -462,18 +670,8 @@ impl Zalsa {
     }
 
     #[inline(always)]
-    pub fn event(&self, event: &dyn Fn() -> crate::Event) {
-        if self.event_callback.is_some() {
-            self.event_cold(event);
-        }
-    }
-
-    // Avoid inlining, as events are typically only enabled for debugging purposes.
-    #[cold]
-    #[inline(never)]
-    pub fn event_cold(&self, event: &dyn Fn() -> crate::Event) {
-        let event_callback = self.event_callback.as_ref().unwrap();
-        event_callback(event());
+    pub fn event(&self, tag: crate::EventKindTag, event: &dyn Fn() -> crate::Event) {
+        self.runtime.event(tag, event);
     }
 }
 
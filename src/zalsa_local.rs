@@ -1,13 +1,12 @@
 use std::alloc::{Layout, alloc, dealloc, handle_alloc_error};
-use std::cell::{RefCell, UnsafeCell};
+use std::any::Any;
+use std::cell::{Cell, RefCell, UnsafeCell};
 use std::fmt;
 use std::fmt::Formatter;
 use std::marker::PhantomData;
 use std::mem::ManuallyDrop;
 use std::panic::UnwindSafe;
 use std::ptr::{self, NonNull};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicU8, Ordering};
 
 use rustc_hash::FxHashMap;
 use thin_vec::ThinVec;
@@ -17,16 +16,24 @@ use crate::accumulator::{
     Accumulator,
     accumulated_map::{AccumulatedMap, AtomicInputAccumulatedValues},
 };
-use crate::active_query::{CompletedQuery, DetachedInputOutputs, QueryCompletion, QueryStack};
+use crate::active_query::{
+    Backtrace, CompletedQuery, DetachedInputOutputs, QueryCompletion, QueryStack,
+};
+#[cfg(feature = "rayon")]
+use crate::active_query::{ForkedQuery, QueryBranch};
+use crate::cancellation_token::CancellationToken;
+use crate::chrome_trace::ChromeTrace;
 use crate::cycle::{AtomicIterationStamp, CycleHeads, IterationStamp, empty_cycle_heads};
 use crate::durability::Durability;
+use crate::hash::FxIndexSet;
 use crate::key::DatabaseKeyIndex;
+use crate::query_panicked::panic_message;
 use crate::runtime::Stamp;
 use crate::sync::atomic::AtomicBool;
 use crate::table::{PageIndex, Slot, Table};
-use crate::tracked_struct::{Disambiguator, Identity, IdentityHash};
+use crate::tracked_struct::{Identity, IdentityHash};
 use crate::zalsa::{IngredientIndex, Zalsa};
-use crate::{Cancelled, Id, Revision};
+use crate::{Cancelled, Id, QueryPanicInfo, Revision};
 
 /// State that is specific to a single execution thread.
 ///
@@ -46,43 +53,40 @@ pub struct ZalsaLocal {
     most_recent_pages: UnsafeCell<FxHashMap<IngredientIndex, PageIndex>>,
 
     cancelled: CancellationToken,
-}
 
-/// A cancellation token that can be used to cancel a query computation for a specific local `Database`.
-#[derive(Default, Clone, Debug)]
-pub struct CancellationToken(Arc<AtomicU8>);
+    /// The panic this thread is unwinding with, as last seen leaving a query function.
+    unwinding_panic: RefCell<Option<UnwindingPanic>>,
 
-impl CancellationToken {
-    const CANCELLED_MASK: u8 = 0b01;
-    const DISABLED_MASK: u8 = 0b10;
+    /// Whether the panic last seen leaving a query function is a cancellation.
+    unwinding_cancelled: Cell<bool>,
 
-    /// Inform the database to cancel the current query computation.
-    pub fn cancel(&self) {
-        self.0.fetch_or(Self::CANCELLED_MASK, Ordering::Relaxed);
-    }
+    /// The number of active queries that memoize panics, see [`ZalsaLocal::catch_panics`].
+    catching_panics: Cell<usize>,
 
-    /// Check if the query computation has been requested to be cancelled.
-    pub fn is_cancelled(&self) -> bool {
-        self.0.load(Ordering::Relaxed) & Self::CANCELLED_MASK != 0
-    }
+    /// The reads performed outside of any query while recording, see [`ZalsaLocal::record_reads`].
+    recorded_reads: RefCell<Option<RecordedReads>>,
 
-    #[inline]
-    fn set_cancellation_disabled(&self, disabled: bool) -> bool {
-        let previous_disabled_bit = if disabled {
-            self.0.fetch_or(Self::DISABLED_MASK, Ordering::Relaxed)
-        } else {
-            self.0.fetch_and(!Self::DISABLED_MASK, Ordering::Relaxed)
-        };
-        previous_disabled_bit & Self::DISABLED_MASK != 0
-    }
+    /// The queries claimed by the threads waiting for the branch this handle runs,
+    /// see [`ZalsaLocal::assert_not_claimed_by_fork`].
+    #[cfg(feature = "rayon")]
+    fork_claims: RefCell<std::sync::Arc<[DatabaseKeyIndex]>>,
+}
 
-    fn should_trigger_local_cancellation(&self) -> bool {
-        self.0.load(Ordering::Relaxed) == Self::CANCELLED_MASK
-    }
+/// The reads performed outside of any query, e.g. by a watched query.
+pub(crate) struct RecordedReads {
+    /// The inputs read, `None` if an untracked read was reported.
+    pub(crate) inputs: Option<FxIndexSet<DatabaseKeyIndex>>,
 
-    fn reset(&self) {
-        self.0.store(0, Ordering::Relaxed);
-    }
+    /// The last revision in which one of the inputs changed.
+    pub(crate) changed_at: Revision,
+}
+
+struct UnwindingPanic {
+    /// The query whose function the panic last unwound out of.
+    unwound_from: DatabaseKeyIndex,
+    /// The length of the query stack while `unwound_from` was active.
+    depth: usize,
+    info: QueryPanicInfo,
 }
 
 impl ZalsaLocal {
@@ -91,6 +95,62 @@ impl ZalsaLocal {
             query_stack: RefCell::new(QueryStack::default()),
             most_recent_pages: UnsafeCell::new(FxHashMap::default()),
             cancelled: CancellationToken::default(),
+            unwinding_panic: RefCell::new(None),
+            unwinding_cancelled: Cell::new(false),
+            catching_panics: Cell::new(0),
+            recorded_reads: RefCell::new(None),
+            #[cfg(feature = "rayon")]
+            fork_claims: RefCell::new(std::sync::Arc::new([])),
+        }
+    }
+
+    /// Executes `op` outside of any query and returns the tracked reads it performed.
+    ///
+    /// # Panics
+    ///
+    /// If a query is active or reads are already being recorded.
+    pub(crate) fn record_reads<R>(&self, op: impl FnOnce() -> R) -> (R, RecordedReads) {
+        assert!(
+            self.query_stack.borrow().is_empty(),
+            "cannot record reads inside of a query"
+        );
+
+        struct Recording<'me>(&'me RefCell<Option<RecordedReads>>);
+
+        impl Drop for Recording<'_> {
+            fn drop(&mut self) {
+                self.0.borrow_mut().take();
+            }
+        }
+
+        let previous = self.recorded_reads.borrow_mut().replace(RecordedReads {
+            inputs: Some(FxIndexSet::default()),
+            changed_at: Revision::start(),
+        });
+        assert!(previous.is_none(), "reads are already being recorded");
+
+        let recording = Recording(&self.recorded_reads);
+        let result = op();
+        let reads = recording
+            .0
+            .borrow_mut()
+            .take()
+            .expect("reads are being recorded");
+        (result, reads)
+    }
+
+    /// Records a read performed outside of any query, if reads are being recorded.
+    fn record_read(&self, input: Option<DatabaseKeyIndex>, changed_at: Revision) {
+        if let Some(reads) = &mut *self.recorded_reads.borrow_mut() {
+            match input {
+                Some(input) => {
+                    if let Some(inputs) = &mut reads.inputs {
+                        inputs.insert(input);
+                    }
+                }
+                None => reads.inputs = None,
+            }
+            reads.changed_at = reads.changed_at.max(changed_at);
         }
     }
 
@@ -179,7 +239,16 @@ impl ZalsaLocal {
     }
 
     #[inline]
-    pub(crate) fn push_query(&self, database_key_index: DatabaseKeyIndex) -> ActiveQueryGuard<'_> {
+    pub(crate) fn push_query<'me>(
+        &'me self,
+        zalsa: &'me Zalsa,
+        database_key_index: DatabaseKeyIndex,
+    ) -> ActiveQueryGuard<'me> {
+        let chrome_trace = zalsa.chrome_trace().if_enabled();
+        if let Some(chrome_trace) = chrome_trace {
+            chrome_trace.begin_query(database_key_index);
+        }
+
         // SAFETY: We do not access the query stack reentrantly.
         unsafe {
             self.with_query_stack_unchecked_mut(|stack| {
@@ -187,6 +256,7 @@ impl ZalsaLocal {
 
                 ActiveQueryGuard {
                     local_state: self,
+                    chrome_trace,
                     database_key_index,
                     #[cfg(debug_assertions)]
                     push_len: stack.len(),
@@ -252,6 +322,105 @@ impl ZalsaLocal {
         }
     }
 
+    /// Records that the panic `payload` unwound out of the function of the active query
+    /// `database_key_index`.
+    ///
+    /// The first query a panic unwinds out of is the one that panicked. A propagated panic
+    /// keeps the query that panicked on the other thread.
+    #[cold]
+    fn record_query_panic(&self, database_key_index: DatabaseKeyIndex, payload: &(dyn Any + Send)) {
+        self.unwinding_cancelled.set(payload.is::<Cancelled>());
+        // SAFETY: We do not access the query stack reentrantly.
+        let depth = unsafe { self.with_query_stack_unchecked(|stack| stack.len()) };
+        let mut unwinding_panic = self.unwinding_panic.borrow_mut();
+        let info = match payload.downcast_ref::<Cancelled>() {
+            Some(Cancelled::PropagatedPanic(Some(info))) => info.clone(),
+            Some(_) => {
+                *unwinding_panic = None;
+                return;
+            }
+            None => {
+                let message = panic_message(payload);
+                match unwinding_panic.take() {
+                    // The panic unwound out of a query called by this one, unless the query
+                    // caught that panic and then panicked differently.
+                    Some(panic)
+                        if panic.depth == depth + 1 && *panic.info.message() == *message =>
+                    {
+                        panic.info
+                    }
+                    _ => {
+                        // SAFETY: We do not access the query stack reentrantly.
+                        let backtrace = unsafe {
+                            self.with_query_stack_unchecked(|stack| Backtrace::from_queries(stack))
+                        };
+                        QueryPanicInfo::new(message, database_key_index, backtrace)
+                    }
+                }
+            }
+        };
+        *unwinding_panic = Some(UnwindingPanic {
+            unwound_from: database_key_index,
+            depth,
+            info,
+        });
+    }
+
+    /// Returns the panic that unwound out of the function of `database_key_index`, if any.
+    ///
+    /// The panic is forgotten once it unwound out of the outermost query.
+    pub(crate) fn query_panic(
+        &self,
+        database_key_index: DatabaseKeyIndex,
+    ) -> Option<QueryPanicInfo> {
+        let mut unwinding_panic = self.unwinding_panic.borrow_mut();
+        let info = unwinding_panic
+            .as_ref()
+            .filter(|panic| panic.unwound_from == database_key_index)
+            .map(|panic| panic.info.clone());
+        // SAFETY: We do not access the query stack reentrantly.
+        if unsafe { self.with_query_stack_unchecked(|stack| stack.is_empty()) } {
+            *unwinding_panic = None;
+        }
+        info
+    }
+
+    /// Runs `op` of the active query, which memoizes panics, catching the panics of `op`.
+    ///
+    /// The queries unwinding while a query catches panics hand their inputs and the
+    /// query backtrace at the panic to their caller.
+    pub(crate) fn catch_panics<T>(&self, op: impl FnOnce() -> T) -> std::thread::Result<T> {
+        self.catching_panics.set(self.catching_panics.get() + 1);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(op));
+        self.catching_panics.set(self.catching_panics.get() - 1);
+        if result.is_err() {
+            // The panic stops here, so it can't be confused with a later one.
+            *self.unwinding_panic.borrow_mut() = None;
+        }
+        result
+    }
+
+    /// Returns `true` if a query unwinding with the current panic has to hand its inputs to
+    /// its caller, because a query memoizes the panic.
+    ///
+    /// Cancellation is never memoized.
+    fn hands_over_panic(&self) -> bool {
+        self.catching_panics.get() > 0 && !self.unwinding_cancelled.get()
+    }
+
+    /// Returns the query backtrace at the panic the active query is catching: the one
+    /// handed over by the query that panicked, or the current one if the active query
+    /// panicked itself.
+    pub(crate) fn take_panic_backtrace(&self) -> Option<Backtrace> {
+        // SAFETY: We do not access the query stack reentrantly.
+        unsafe {
+            self.with_query_stack_unchecked_mut(|stack| {
+                let backtrace = stack.last_mut()?.take_panic_backtrace();
+                Some(backtrace.unwrap_or_else(|| Backtrace::from_queries(stack)))
+            })
+        }
+    }
+
     /// Add an output to the current query's list of dependencies
     ///
     /// Returns `Err` if not in a query.
@@ -317,21 +486,26 @@ impl ZalsaLocal {
         );
 
         // SAFETY: We do not access the query stack reentrantly.
-        unsafe {
+        let in_query = unsafe {
             self.with_query_stack_unchecked_mut(|stack| {
-                if let Some(top_query) = stack.last_mut() {
-                    top_query.add_read(
-                        input,
-                        durability,
-                        changed_at,
-                        cycle_heads,
-                        #[cfg(feature = "accumulator")]
-                        has_accumulated,
-                        #[cfg(feature = "accumulator")]
-                        accumulated_inputs,
-                    );
-                }
+                let Some(top_query) = stack.last_mut() else {
+                    return false;
+                };
+                top_query.add_read(
+                    input,
+                    durability,
+                    changed_at,
+                    cycle_heads,
+                    #[cfg(feature = "accumulator")]
+                    has_accumulated,
+                    #[cfg(feature = "accumulator")]
+                    accumulated_inputs,
+                );
+                true
             })
+        };
+        if !in_query {
+            self.record_read(Some(input), changed_at);
         }
     }
 
@@ -351,12 +525,17 @@ impl ZalsaLocal {
         );
 
         // SAFETY: We do not access the query stack reentrantly.
-        unsafe {
+        let in_query = unsafe {
             self.with_query_stack_unchecked_mut(|stack| {
-                if let Some(top_query) = stack.last_mut() {
-                    top_query.add_read_simple(input, durability, changed_at);
-                }
+                let Some(top_query) = stack.last_mut() else {
+                    return false;
+                };
+                top_query.add_read_simple(input, durability, changed_at);
+                true
             })
+        };
+        if !in_query {
+            self.record_read(Some(input), changed_at);
         }
     }
 
@@ -368,12 +547,17 @@ impl ZalsaLocal {
     #[inline(always)]
     pub(crate) fn report_untracked_read(&self, current_revision: Revision) {
         // SAFETY: We do not access the query stack reentrantly.
-        unsafe {
+        let in_query = unsafe {
             self.with_query_stack_unchecked_mut(|stack| {
-                if let Some(top_query) = stack.last_mut() {
-                    top_query.add_untracked_read(current_revision);
-                }
+                let Some(top_query) = stack.last_mut() else {
+                    return false;
+                };
+                top_query.add_untracked_read(current_revision);
+                true
             })
+        };
+        if !in_query {
+            self.record_read(None, current_revision);
         }
     }
 
@@ -384,19 +568,86 @@ impl ZalsaLocal {
     /// * Identify a unique disambiguator for the hash within the current query,
     ///   adding the hash to the current query's disambiguator table.
     /// * Returns a tuple of:
-    ///   * the id of the current query
     ///   * the current dependencies (durability, changed_at) of current query
-    ///   * the disambiguator index
+    ///   * the identity of the tracked struct, including the disambiguator
     #[track_caller]
-    pub(crate) fn disambiguate(&self, key: IdentityHash) -> (Stamp, Disambiguator) {
+    pub(crate) fn disambiguate(&self, key: IdentityHash) -> (Stamp, Identity) {
         // SAFETY: We do not access the query stack reentrantly.
         unsafe {
             self.with_query_stack_unchecked_mut(|stack| {
                 let top_query = stack.last_mut().expect(
                     "cannot create a tracked struct disambiguator outside of a tracked function",
                 );
-                let disambiguator = top_query.disambiguate(key);
-                (top_query.stamp(), disambiguator)
+                let identity = top_query.disambiguate(key);
+                (top_query.stamp(), identity)
+            })
+        }
+    }
+
+    /// Forks the active query, if any, into branches running on other database handles.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn fork_active_query(&self) -> Option<ForkedQuery> {
+        let inherited = self.fork_claims.borrow().clone();
+        // SAFETY: We do not access the query stack reentrantly.
+        unsafe {
+            self.with_query_stack_unchecked_mut(|stack| {
+                let claimed = inherited
+                    .iter()
+                    .copied()
+                    .chain(stack.iter().flat_map(|query| query.claimed_queries()))
+                    .collect();
+                Some(stack.last_mut()?.fork(claimed))
+            })
+        }
+    }
+
+    /// Pushes the frame for branch `index` of the forked query `fork`.
+    ///
+    /// The branch runs as part of the forked query: the dependencies it collects are
+    /// merged into the forked query with [`ZalsaLocal::merge_branch`].
+    #[cfg(feature = "rayon")]
+    pub(crate) fn push_branch<'me>(
+        &'me self,
+        zalsa: &'me Zalsa,
+        fork: &ForkedQuery,
+        index: usize,
+    ) -> ActiveQueryGuard<'me> {
+        *self.fork_claims.borrow_mut() = fork.claimed.clone();
+        let guard = self.push_query(zalsa, fork.database_key_index);
+        // SAFETY: We do not access the query stack reentrantly.
+        unsafe {
+            self.with_query_stack_unchecked_mut(|stack| {
+                stack.last_mut().unwrap().seed_branch(fork, index);
+            })
+        }
+        guard
+    }
+
+    /// Panics if `database_key_index` is claimed by a thread waiting for the branch
+    /// this handle runs.
+    ///
+    /// The thread forking a query waits for its branches in rayon, where the dependency
+    /// graph can't see it: blocking on one of its claims would deadlock.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn assert_not_claimed_by_fork(&self, database_key_index: DatabaseKeyIndex) {
+        if self.fork_claims.borrow().contains(&database_key_index) {
+            panic!(
+                "a branch of `par_map` or `join` depends on {database_key_index:?}, \
+                which is being computed by the thread waiting for the branch"
+            );
+        }
+    }
+
+    /// Adds the dependencies collected by a branch of a fork to the active query.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn merge_branch(&self, branch: QueryBranch) {
+        // SAFETY: We do not access the query stack reentrantly.
+        unsafe {
+            self.with_query_stack_unchecked_mut(move |stack| {
+                stack
+                    .last_mut()
+                    .expect("merging a branch outside of a tracked function")
+                    .merge_branch(branch);
             })
         }
     }
@@ -442,6 +693,19 @@ impl ZalsaLocal {
         self.cancelled.should_trigger_local_cancellation()
     }
 
+    /// Returns `true` if the active queries may depend on an input of durability `durability`
+    /// or lower, based on the inputs they read so far.
+    pub(crate) fn may_depend_on(&self, durability: Durability) -> bool {
+        // SAFETY: We do not access the query stack reentrantly.
+        unsafe {
+            self.with_query_stack_unchecked(|stack| {
+                stack
+                    .iter()
+                    .any(|query| query.stamp().durability <= durability)
+            })
+        }
+    }
+
     #[cold]
     pub(crate) fn unwind_pending_write(&self) {
         Cancelled::PendingWrite.throw();
@@ -449,7 +713,7 @@ impl ZalsaLocal {
 
     #[cold]
     pub(crate) fn unwind_cancelled(&self) {
-        Cancelled::Local.throw();
+        self.cancelled.to_cancelled().throw();
     }
 
     #[inline]
@@ -1822,6 +2086,8 @@ pub(crate) fn output_edges(
 /// destructor will also remove the query.
 pub(crate) struct ActiveQueryGuard<'me> {
     local_state: &'me ZalsaLocal,
+    /// The trace the start of the query was recorded in, if any.
+    chrome_trace: Option<&'me ChromeTrace>,
     #[cfg(debug_assertions)]
     push_len: usize,
     pub(crate) database_key_index: DatabaseKeyIndex,
@@ -1861,6 +2127,30 @@ impl<'me> ActiveQueryGuard<'me> {
         }
     }
 
+    /// Records that the panic `payload` unwound out of the query's function.
+    pub(crate) fn record_panic(&self, payload: &(dyn Any + Send)) {
+        self.local_state
+            .record_query_panic(self.database_key_index, payload);
+    }
+
+    /// Pops the frame of a branch of a forked query, returning the dependencies it collected.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn pop_branch(self) -> QueryBranch {
+        // SAFETY: We do not access the query stack reentrantly.
+        let branch = unsafe {
+            self.local_state.with_query_stack_unchecked_mut(|stack| {
+                stack.pop_branch(
+                    self.database_key_index,
+                    #[cfg(debug_assertions)]
+                    self.push_len,
+                )
+            })
+        };
+        self.record_end();
+        std::mem::forget(self);
+        branch
+    }
+
     pub(crate) fn take_cycle_heads(&mut self) -> CycleHeads {
         // SAFETY: We do not access the query stack reentrantly.
         unsafe {
@@ -1888,6 +2178,12 @@ impl<'me> ActiveQueryGuard<'me> {
         }
     }
 
+    fn record_end(&self) {
+        if let Some(chrome_trace) = self.chrome_trace {
+            chrome_trace.end_query(self.database_key_index);
+        }
+    }
+
     /// Invoked when the query has successfully completed execution.
     fn complete(self, iteration: IterationStamp) -> CompletedQuery {
         // SAFETY: We do not access the query stack reentrantly.
@@ -1901,6 +2197,7 @@ impl<'me> ActiveQueryGuard<'me> {
                 )
             })
         };
+        self.record_end();
         std::mem::forget(self);
         query
     }
@@ -1933,6 +2230,7 @@ impl<'me> ActiveQueryGuard<'me> {
                     )
                 })
         };
+        self.record_end();
         std::mem::forget(self);
         completion
     }
@@ -1963,16 +2261,26 @@ impl DetachedQuery<'_> {
 
 impl Drop for ActiveQueryGuard<'_> {
     fn drop(&mut self) {
+        let unwinding = std::thread::panicking() && self.local_state.hands_over_panic();
         // SAFETY: We do not access the query stack reentrantly.
         unsafe {
             self.local_state.with_query_stack_unchecked_mut(|stack| {
-                stack.pop(
-                    self.database_key_index,
-                    #[cfg(debug_assertions)]
-                    self.push_len,
-                );
+                if unwinding {
+                    stack.pop_unwinding(
+                        self.database_key_index,
+                        #[cfg(debug_assertions)]
+                        self.push_len,
+                    );
+                } else {
+                    stack.pop(
+                        self.database_key_index,
+                        #[cfg(debug_assertions)]
+                        self.push_len,
+                    );
+                }
             })
         };
+        self.record_end();
     }
 }
 
@@ -3,10 +3,11 @@
 
 mod common;
 
+use std::time::{Duration, Instant};
 use std::{sync::Barrier, thread};
 
 use expect_test::expect;
-use salsa::{Cancelled, Database};
+use salsa::{CancellationToken, Cancelled, Database};
 
 use crate::common::LogDatabase;
 
@@ -65,3 +66,101 @@ fn cancellation_token() {
             "WillExecute { database_key: b(Id(0)) }",
         ]"#]]);
 }
+
+#[test]
+fn cancellation_reason() {
+    let db = common::LoggerDatabase::default();
+    let input = MyInput::new(&db, 22);
+
+    db.cancellation_token().cancel_with_reason("shutting down");
+    let res = Cancelled::catch(|| b(&db, input));
+    assert!(
+        matches!(&res, Err(Cancelled::Reason(reason)) if reason == "shutting down"),
+        "{res:?}"
+    );
+
+    // The cancellation only applies to the cancelled computation.
+    assert_eq!(b(&db, input), 22);
+}
+
+#[test]
+fn cancellation_deadline() {
+    let db = common::LoggerDatabase::default();
+    let input = MyInput::new(&db, 22);
+    let token = db.cancellation_token();
+
+    token.cancel_after(Duration::from_secs(3600));
+    assert_eq!(b(&db, input), 22);
+    assert!(token.deadline().is_some());
+
+    token.set_deadline(Instant::now());
+    let res = Cancelled::catch(|| b(&db, input));
+    assert!(matches!(res, Err(Cancelled::DeadlineExceeded)), "{res:?}");
+
+    // The deadline is consumed once it cancelled the computation.
+    assert_eq!(token.deadline(), None);
+    assert_eq!(b(&db, input), 22);
+}
+
+#[test]
+fn cancellation_hierarchy() {
+    let db1 = common::LoggerDatabase::default();
+    let db2 = db1.clone();
+    let input = MyInput::new(&db1, 22);
+
+    let request = CancellationToken::new();
+    db1.cancellation_token().set_parent(&request);
+    db2.cancellation_token().set_parent(&request);
+    let subrequest = request.child_token();
+
+    assert_eq!(b(&db1, input), 22);
+
+    request.cancel_with_reason("request aborted");
+    assert!(subrequest.is_cancelled());
+    for db in [&db1, &db2] {
+        let res = Cancelled::catch(|| b(db, input));
+        assert!(
+            matches!(&res, Err(Cancelled::Reason(reason)) if reason == "request aborted"),
+            "{res:?}"
+        );
+    }
+
+    // The handles stay cancelled as long as the request is.
+    let res = Cancelled::catch(|| b(&db1, input));
+    assert!(matches!(res, Err(Cancelled::Reason(_))), "{res:?}");
+}
+
+#[test]
+fn cancellation_hierarchy_deadline() {
+    let db = common::LoggerDatabase::default();
+    let input = MyInput::new(&db, 22);
+
+    let request = CancellationToken::new();
+    let subrequest = request.child_token();
+    db.cancellation_token().set_parent(&subrequest);
+
+    request.set_deadline(Instant::now());
+    let res = Cancelled::catch(|| b(&db, input));
+    assert!(matches!(res, Err(Cancelled::DeadlineExceeded)), "{res:?}");
+    assert!(request.is_cancelled());
+    assert!(subrequest.is_cancelled());
+}
+
+#[test]
+fn cancellation_hierarchy_cleared_deadline() {
+    let request = CancellationToken::new();
+    let subrequest = request.child_token();
+
+    request.set_deadline(Instant::now());
+    request.clear_deadline();
+    assert!(!subrequest.is_cancelled());
+    assert!(!request.is_cancelled());
+}
+
+#[test]
+#[should_panic(expected = "cancellation token can't be its own ancestor")]
+fn cancellation_hierarchy_cycle() {
+    let request = CancellationToken::new();
+    let subrequest = request.child_token();
+    request.set_parent(&subrequest);
+}
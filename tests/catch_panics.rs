@@ -0,0 +1,111 @@
+#![cfg(feature = "inventory")]
+
+//! Test that panics of `catch_panics` tracked functions are memoized as errors.
+
+mod common;
+use common::LogDatabase;
+
+use expect_test::expect;
+use salsa::{QueryPanicked, Setter};
+
+#[salsa::input]
+struct Input {
+    divisor: u32,
+    unrelated: u32,
+}
+
+#[salsa::tracked]
+fn divide(db: &dyn LogDatabase, input: Input) -> u32 {
+    db.push_log("divide".to_string());
+    100 / input.divisor(db)
+}
+
+#[salsa::tracked(catch_panics)]
+fn checked(db: &dyn LogDatabase, input: Input) -> u32 {
+    db.push_log("checked".to_string());
+    if input.divisor(db) == 1 {
+        panic!("divisor is one");
+    }
+    divide(db, input)
+}
+
+#[salsa::tracked]
+fn caller(db: &dyn LogDatabase, input: Input) -> Result<u32, QueryPanicked> {
+    db.push_log("caller".to_string());
+    checked(db, input)
+}
+
+#[salsa::tracked]
+impl Input {
+    #[salsa::tracked(catch_panics)]
+    fn doubled(self, db: &dyn LogDatabase) -> u32 {
+        2 * divide(db, self)
+    }
+}
+
+#[test]
+fn panic_in_query() {
+    let mut db = common::LoggerDatabase::default();
+    let input = Input::new(&db, 1, 0);
+
+    let error = caller(&db, input).unwrap_err();
+    assert_eq!(error.message(), "divisor is one");
+    db.assert_logs(expect![[r#"
+        [
+            "caller",
+            "checked",
+        ]"#]]);
+
+    // The error is memoized.
+    assert_eq!(caller(&db, input), Err(error));
+    db.assert_logs(expect!["[]"]);
+
+    input.set_unrelated(&mut db).to(1);
+    assert!(caller(&db, input).is_err());
+    db.assert_logs(expect!["[]"]);
+
+    input.set_divisor(&mut db).to(5);
+    assert_eq!(caller(&db, input), Ok(20));
+    db.assert_logs(expect![[r#"
+        [
+            "checked",
+            "divide",
+            "caller",
+        ]"#]]);
+}
+
+#[test]
+fn panic_in_called_query() {
+    let mut db = common::LoggerDatabase::default();
+    let input = Input::new(&db, 0, 0);
+
+    let error = checked(&db, input).unwrap_err();
+    assert_eq!(error.message(), "attempt to divide by zero");
+    let backtrace = salsa::attach(&db, || error.backtrace().unwrap().to_string());
+    assert!(backtrace.contains("divide(Id(0))"), "{backtrace}");
+    assert!(backtrace.contains("checked(Id(0))"), "{backtrace}");
+    db.assert_logs(expect![[r#"
+        [
+            "checked",
+            "divide",
+        ]"#]]);
+
+    // The inputs read by the query that panicked are dependencies of the error.
+    input.set_divisor(&mut db).to(4);
+    assert_eq!(checked(&db, input), Ok(25));
+    db.assert_logs(expect![[r#"
+        [
+            "checked",
+            "divide",
+        ]"#]]);
+}
+
+#[test]
+fn tracked_method() {
+    let mut db = common::LoggerDatabase::default();
+    let input = Input::new(&db, 0, 0);
+
+    assert!(input.doubled(&db).is_err());
+    input.set_divisor(&mut db).to(10);
+    assert_eq!(input.doubled(&db), Ok(20));
+}
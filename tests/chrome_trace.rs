@@ -0,0 +1,78 @@
+#![cfg(feature = "inventory")]
+
+//! Test that query executions can be exported as a Chrome trace.
+
+use salsa::{Database, DatabaseExt};
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Database, file: File) -> usize {
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn is_long(db: &dyn Database, file: File) -> bool {
+    length(db, file) > 2
+}
+
+fn trace_events(db: &dyn Database) -> Vec<String> {
+    let mut json = Vec::new();
+    db.write_chrome_trace(&mut json).unwrap();
+    let trace: serde_json::Value = serde_json::from_slice(&json).unwrap();
+
+    trace["traceEvents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| {
+            let name = if event["ph"] == "M" {
+                &event["args"]["name"]
+            } else {
+                &event["name"]
+            };
+            format!(
+                "{} {} {} tid={}",
+                event["ph"].as_str().unwrap(),
+                name.as_str().unwrap(),
+                event["cat"].as_str().unwrap_or_default(),
+                event["tid"]
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn chrome_trace() {
+    let db = salsa::DatabaseImpl::new();
+    let file = File::new(&db, "a".to_string());
+
+    db.start_chrome_trace();
+    is_long(&db, file);
+    db.stop_chrome_trace();
+
+    // Not recorded.
+    let other = File::new(&db, "b".to_string());
+    length(&db, other);
+
+    let events = trace_events(&db);
+    assert!(events[0].starts_with("M "), "{events:?}");
+    assert_eq!(
+        events[1..],
+        [
+            "B is_long(Id(0)) is_long tid=0",
+            "B length(Id(0)) length tid=0",
+            "E length(Id(0)) length tid=0",
+            "E is_long(Id(0)) is_long tid=0",
+        ]
+    );
+}
+
+#[test]
+fn empty_trace() {
+    let db = salsa::DatabaseImpl::new();
+    assert_eq!(trace_events(&db), Vec::<String>::new());
+}
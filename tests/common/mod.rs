@@ -4,7 +4,7 @@
 
 use std::sync::{Arc, Mutex};
 
-use salsa::{Database, Storage};
+use salsa::{Database, DatabaseKeyIndex, Storage};
 
 /// Logging userdata: provides [`LogDatabase`][] trait.
 ///
@@ -91,7 +91,11 @@ impl Default for EventLoggerDatabase {
         Self {
             storage: Storage::new(Some(Box::new({
                 let logger = logger.clone();
-                move |event| logger.push_log(format!("{:?}", event.kind))
+                move |event| match event.kind {
+                    // The execution time differs between runs.
+                    salsa::EventKind::DidExecute { .. } => (),
+                    _ => logger.push_log(format!("{:?}", event.kind)),
+                }
             }))),
             logger,
         }
@@ -178,6 +182,41 @@ impl HasLogger for ExecuteValidateLoggerDatabase {
     }
 }
 
+/// Database that records the keys of the queries it executes.
+#[salsa::db]
+#[derive(Clone)]
+pub struct ExecutedKeysDatabase {
+    storage: Storage<Self>,
+    executed: Arc<Mutex<Vec<DatabaseKeyIndex>>>,
+}
+
+impl ExecutedKeysDatabase {
+    /// Returns the keys of the queries executed since the last call.
+    pub fn take_executed(&self) -> Vec<DatabaseKeyIndex> {
+        std::mem::take(&mut *self.executed.lock().unwrap())
+    }
+}
+
+impl Default for ExecutedKeysDatabase {
+    fn default() -> Self {
+        let executed = Arc::new(Mutex::new(Vec::new()));
+        Self {
+            storage: Storage::new(Some(Box::new({
+                let executed = executed.clone();
+                move |event| {
+                    if let salsa::EventKind::WillExecute { database_key } = event.kind {
+                        executed.lock().unwrap().push(database_key);
+                    }
+                }
+            }))),
+            executed,
+        }
+    }
+}
+
+#[salsa::db]
+impl Database for ExecutedKeysDatabase {}
+
 /// Trait implemented by databases that lets them provide a fixed u32 value.
 pub trait HasValue {
     fn get_value(&self) -> u32;
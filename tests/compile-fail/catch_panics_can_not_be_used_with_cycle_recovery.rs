@@ -0,0 +1,18 @@
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(catch_panics, cycle_result = fallback)]
+fn catch_panics_can_not_be_used_with_cycle_recovery(
+    db: &dyn salsa::Database,
+    input: MyInput,
+) -> u32 {
+    input.field(db)
+}
+
+fn fallback(_db: &dyn salsa::Database, _id: salsa::Id, _input: MyInput) -> u32 {
+    0
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use salsa::{DatabaseExt, Setter};
+
+struct NotClone(u32);
+
+#[salsa::input]
+struct Opaque {
+    #[returns(ref)]
+    value: NotClone,
+}
+
+fn main() {
+    let mut db = salsa::DatabaseImpl::new();
+    let opaque = Opaque::new(&db, NotClone(0));
+
+    let mut checkpoint = db.checkpoint();
+    opaque.set_value(&mut checkpoint).to(NotClone(1));
+}
@@ -0,0 +1,19 @@
+use salsa::{DatabaseExt, Setter};
+
+struct NotClone(u32);
+
+#[salsa::input]
+struct Opaque {
+    #[returns(ref)]
+    value: NotClone,
+}
+
+fn main() {
+    let mut db = salsa::DatabaseImpl::new();
+    let opaque = Opaque::new(&db, NotClone(0));
+
+    let _: Result<(), ()> = db.transaction(|tx| {
+        opaque.set_value(tx).to(NotClone(1));
+        Ok(())
+    });
+}
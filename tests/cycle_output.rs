@@ -83,7 +83,8 @@ impl Default for Database {
                     | salsa::EventKind::DidValidateMemoizedValue { .. } => {
                         logger.push_log(format!("salsa_event({:?})", event.kind));
                     }
-                    salsa::EventKind::WillCheckCancellation => {}
+                    salsa::EventKind::WillCheckCancellation
+                    | salsa::EventKind::DidExecute { .. } => {}
                     _ => {
                         logger.push_log(format!("salsa_event({:?})", event.kind));
                     }
@@ -138,6 +139,8 @@ fn revalidate_no_changes() {
     db.assert_logs(expect![[r#"
         [
             "salsa_event(DidSetCancellationFlag)",
+            "salsa_event(DidStartRevision { revision: R2 })",
+            "salsa_event(DidSetInputField { database_key: InputValue.value(Id(1)), durability: Durability(0) })",
             "salsa_event(DidValidateMemoizedValue { database_key: read_value(Id(400)) })",
             "salsa_event(DidValidateInternedValue { key: query_d::interned_arguments(Id(800)), revision: R2 })",
             "salsa_event(DidValidateMemoizedValue { database_key: query_d(Id(800)) })",
@@ -168,6 +171,8 @@ fn revalidate_with_change_after_output_read() {
     db.assert_logs(expect![[r#"
         [
             "salsa_event(DidSetCancellationFlag)",
+            "salsa_event(DidStartRevision { revision: R2 })",
+            "salsa_event(DidSetInputField { database_key: InputValue.value(Id(1)), durability: Durability(0) })",
             "salsa_event(DidValidateMemoizedValue { database_key: read_value(Id(400)) })",
             "salsa_event(DidValidateInternedValue { key: query_d::interned_arguments(Id(800)), revision: R2 })",
             "salsa_event(WillExecute { database_key: query_d(Id(800)) })",
@@ -57,6 +57,8 @@ fn the_test() {
     db.assert_logs(expect![[r#"
         [
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: Input.value(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillCheckCancellation",
             "WillExecute { database_key: query(Id(0)) }",
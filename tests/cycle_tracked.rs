@@ -165,6 +165,8 @@ fn main() {
             "WillCheckCancellation",
             "DidFinalizeCycle { database_key: cost_to_start(Id(403)), iteration: 1 }",
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: GraphInput.simple(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: create_graph(Id(0)) }",
             "WillDiscardStaleOutput { execute_key: create_graph(Id(0)), output_key: Node(Id(403)) }",
@@ -286,6 +288,8 @@ fn test_cycle_with_fixpoint_structs() {
     db.assert_logs(expect![[r#"
         [
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: GraphInput.fixpoint_variant(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: create_tracked_in_cycle(Id(0)) }",
             "WillCheckCancellation",
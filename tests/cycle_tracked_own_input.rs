@@ -102,6 +102,8 @@ fn main() {
     db.assert_logs(expect![[r#"
         [
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: ClassNode.type_params(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: infer_class(Id(0)) }",
             "WillCheckCancellation",
@@ -0,0 +1,114 @@
+#![cfg(feature = "inventory")]
+
+//! Tests that databases can be configured with more durability levels.
+
+mod common;
+
+use common::{HasLogger, LogDatabase, Logger};
+use salsa::{Durability, Setter, Storage};
+
+#[salsa::db]
+#[derive(Clone)]
+struct ValidateLoggerDatabase {
+    storage: Storage<Self>,
+    logger: Logger,
+}
+
+impl ValidateLoggerDatabase {
+    fn with_durability_levels(levels: usize) -> Self {
+        let logger = Logger::default();
+        Self {
+            storage: Storage::builder()
+                .event_callback(Box::new({
+                    let logger = logger.clone();
+                    move |event| {
+                        if let salsa::EventKind::DidValidateMemoizedValue { .. } = event.kind {
+                            logger.push_log(format!("{:?}", event.kind));
+                        }
+                    }
+                }))
+                .durability_levels(levels)
+                .build(),
+            logger,
+        }
+    }
+}
+
+#[salsa::db]
+impl salsa::Database for ValidateLoggerDatabase {}
+
+impl HasLogger for ValidateLoggerDatabase {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn LogDatabase, file: File) -> usize {
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn double_length(db: &dyn LogDatabase, file: File) -> usize {
+    length(db, file) * 2
+}
+
+/// Creates a database with `levels` durability levels in which `double_length` has been
+/// computed for an input of durability level 3, after which an input of level 2 changed.
+fn setup(levels: usize) -> (ValidateLoggerDatabase, File) {
+    let mut db = ValidateLoggerDatabase::with_durability_levels(levels);
+    let config = File::builder("config".to_string())
+        .text_durability(Durability::new(2))
+        .new(&db);
+    let sysroot = File::builder("sysroot".to_string())
+        .text_durability(Durability::new(3))
+        .new(&db);
+
+    assert_eq!(double_length(&db, sysroot), 14);
+
+    config
+        .set_text(&mut db)
+        .with_durability(Durability::new(2))
+        .to("changed".to_string());
+    db.clear_logs();
+
+    (db, sysroot)
+}
+
+#[test]
+fn more_levels_skip_verification() {
+    // Level 3 is distinct from level 2, so `double_length` is validated
+    // without walking its dependencies.
+    let (db, sysroot) = setup(5);
+
+    assert_eq!(double_length(&db, sysroot), 14);
+    db.assert_logs_len(1);
+}
+
+#[test]
+fn levels_above_configured_share_highest() {
+    // Levels 2 and 3 are both treated like the highest of the three default levels,
+    // so `double_length` has to validate `length` as well.
+    let (db, sysroot) = setup(3);
+
+    assert_eq!(double_length(&db, sysroot), 14);
+    db.assert_logs_len(2);
+}
+
+#[test]
+fn durability_debug() {
+    assert_eq!(format!("{:#?}", Durability::MEDIUM), "Durability::MEDIUM");
+    assert_eq!(format!("{:#?}", Durability::new(4)), "Durability::new(4)");
+    assert_eq!(Durability::new(7), Durability::HIGH);
+}
+
+#[test]
+#[should_panic(expected = "the number of durability levels must be between 1 and 8")]
+fn too_many_levels() {
+    ValidateLoggerDatabase::with_durability_levels(9);
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "inventory")]
+
+//! Test that event listeners can be registered and unregistered at runtime.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use expect_test::expect;
+use salsa::{Database, DatabaseExt, EventFilter, EventKind, EventKindTag, Setter};
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Database, file: File) -> usize {
+    file.text(db).len()
+}
+
+fn listener(log: &Arc<Mutex<Vec<String>>>) -> Box<dyn Fn(salsa::Event) + Send + Sync> {
+    let log = log.clone();
+    Box::new(move |event| {
+        let entry = match event.kind {
+            EventKind::WillExecute { database_key } => format!("WillExecute({database_key:?})"),
+            EventKind::DidValidateMemoizedValue { database_key } => {
+                format!("DidValidateMemoizedValue({database_key:?})")
+            }
+            kind => format!("{:?}", kind.tag()),
+        };
+        log.lock().unwrap().push(entry);
+    })
+}
+
+#[test]
+fn filtered_listeners() {
+    let mut db = common::LoggerDatabase::default();
+    let executions = Arc::new(Mutex::new(Vec::new()));
+    let validations = Arc::new(Mutex::new(Vec::new()));
+
+    let executions_id = db.add_event_listener(
+        EventFilter::from(EventKindTag::WillExecute),
+        listener(&executions),
+    );
+    db.add_event_listener(
+        [
+            EventKindTag::DidValidateMemoizedValue,
+            EventKindTag::DidStartRevision,
+        ]
+        .into_iter()
+        .collect(),
+        listener(&validations),
+    );
+
+    let file = File::new(&db, "a".to_string());
+    assert_eq!(length(&db, file), 1);
+    db.synthetic_write(salsa::Durability::LOW);
+    assert_eq!(length(&db, file), 1);
+
+    expect![[r#"
+        [
+            "WillExecute(length(Id(0)))",
+        ]
+    "#]]
+    .assert_debug_eq(&executions.lock().unwrap());
+    expect![[r#"
+        [
+            "DidStartRevision",
+            "DidValidateMemoizedValue(length(Id(0)))",
+        ]
+    "#]]
+    .assert_debug_eq(&validations.lock().unwrap());
+
+    assert!(db.remove_event_listener(executions_id));
+    assert!(!db.remove_event_listener(executions_id));
+
+    file.set_text(&mut db).to("ab".to_string());
+    assert_eq!(length(&db, file), 2);
+    assert_eq!(executions.lock().unwrap().len(), 1);
+}
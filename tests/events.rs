@@ -0,0 +1,119 @@
+#![cfg(feature = "inventory")]
+
+//! Test the events reported for executions, evictions, input writes and new revisions.
+
+mod common;
+
+use common::{HasLogger, LogDatabase, Logger};
+use expect_test::expect;
+use salsa::{Database, Durability, EventKind, Setter, Storage};
+
+#[salsa::db]
+#[derive(Clone)]
+struct EventDatabase {
+    storage: Storage<Self>,
+    logger: Logger,
+}
+
+impl Default for EventDatabase {
+    fn default() -> Self {
+        let logger = Logger::default();
+        Self {
+            storage: Storage::new(Some(Box::new({
+                let logger = logger.clone();
+                move |event| match event.kind {
+                    EventKind::DidExecute {
+                        database_key,
+                        backdated,
+                        ..
+                    } => logger.push_log(format!(
+                        "DidExecute {{ database_key: {database_key:?}, backdated: {backdated} }}"
+                    )),
+                    EventKind::DidEvictMemo { .. }
+                    | EventKind::DidSetInputField { .. }
+                    | EventKind::DidStartRevision { .. } => {
+                        logger.push_log(format!("{:?}", event.kind))
+                    }
+                    _ => (),
+                }
+            }))),
+            logger,
+        }
+    }
+}
+
+#[salsa::db]
+impl Database for EventDatabase {}
+
+impl HasLogger for EventDatabase {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Database, file: File) -> usize {
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn is_long(db: &dyn Database, file: File) -> bool {
+    length(db, file) > 2
+}
+
+#[salsa::tracked(lru = 1)]
+fn shout(db: &dyn Database, file: File) -> String {
+    file.text(db).to_uppercase()
+}
+
+#[test]
+fn execute_and_set() {
+    let mut db = EventDatabase::default();
+    let file = File::new(&db, "a".to_string());
+
+    assert!(!is_long(&db, file));
+    db.assert_logs(expect![[r#"
+        [
+            "DidExecute { database_key: length(Id(0)), backdated: false }",
+            "DidExecute { database_key: is_long(Id(0)), backdated: false }",
+        ]"#]]);
+
+    file.set_text(&mut db)
+        .with_durability(Durability::HIGH)
+        .to("b".to_string());
+    assert!(!is_long(&db, file));
+    db.assert_logs(expect![[r#"
+        [
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: File.text(Id(0)), durability: Durability(7) }",
+            "DidExecute { database_key: length(Id(0)), backdated: true }",
+        ]"#]]);
+}
+
+#[test]
+fn evict() {
+    let mut db = EventDatabase::default();
+    let first = File::new(&db, "a".to_string());
+    let second = File::new(&db, "b".to_string());
+
+    assert_eq!(shout(&db, first), "A");
+    assert_eq!(shout(&db, second), "B");
+    db.assert_logs(expect![[r#"
+        [
+            "DidExecute { database_key: shout(Id(0)), backdated: false }",
+            "DidExecute { database_key: shout(Id(1)), backdated: false }",
+        ]"#]]);
+
+    // Evictions happen at the start of the next revision.
+    db.synthetic_write(Durability::LOW);
+    db.assert_logs(expect![[r#"
+        [
+            "DidStartRevision { revision: R2 }",
+            "DidEvictMemo { database_key: DatabaseKeyIndex(IngredientIndex(4), Id(0)) }",
+        ]"#]]);
+}
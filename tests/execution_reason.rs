@@ -0,0 +1,141 @@
+#![cfg(feature = "inventory")]
+
+//! Test that the reason why a query re-executed can be inspected.
+
+mod common;
+
+use salsa::{Database, DatabaseExt, ExecutionReason, Setter};
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Database, file: File) -> usize {
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn is_long(db: &dyn Database, file: File) -> bool {
+    length(db, file) > 2
+}
+
+#[salsa::tracked]
+fn untracked(db: &dyn Database, file: File) -> usize {
+    db.report_untracked_read();
+    file.text(db).len()
+}
+
+#[salsa::tracked(cycle_initial = count_initial)]
+fn count(db: &dyn Database, file: File) -> usize {
+    let count = count(db, file);
+    if count < file.text(db).len() {
+        count + 1
+    } else {
+        count
+    }
+}
+
+fn count_initial(_db: &dyn Database, _id: salsa::Id, _file: File) -> usize {
+    0
+}
+
+#[test]
+fn execution_reason() {
+    let mut db = common::ExecutedKeysDatabase::default();
+    db.record_execution_reasons(true);
+    let file = File::new(&db, "a".to_string());
+
+    assert!(!is_long(&db, file));
+    let [is_long_key, length_key] = db.take_executed()[..] else {
+        panic!("expected `is_long` and `length` to execute");
+    };
+    assert_eq!(
+        db.last_execution_reason(is_long_key),
+        Some(ExecutionReason::New)
+    );
+    assert_eq!(
+        db.last_execution_reason(length_key),
+        Some(ExecutionReason::New)
+    );
+
+    file.set_text(&mut db).to("abc".to_string());
+    let text_key = file.text_database_key(&db);
+
+    assert!(is_long(&db, file));
+    assert_eq!(db.take_executed(), [length_key, is_long_key]);
+
+    let Some(ExecutionReason::InputChanged(chain)) = db.last_execution_reason(is_long_key) else {
+        panic!("expected `is_long` to re-execute because an input changed");
+    };
+    let chain = chain
+        .iter()
+        .map(|input| (input.key(), input.changed_at()))
+        .collect::<Vec<_>>();
+    let revision = chain[0].1.unwrap();
+    assert_eq!(
+        chain,
+        [(length_key, Some(revision)), (text_key, Some(revision))]
+    );
+
+    let reason = db.last_execution_reason(length_key).unwrap();
+    assert_eq!(reason.root_cause().unwrap().key(), text_key);
+}
+
+#[test]
+fn untracked_reason() {
+    let mut db = common::ExecutedKeysDatabase::default();
+    db.record_execution_reasons(true);
+    let file = File::new(&db, "a".to_string());
+
+    untracked(&db, file);
+    let [untracked_key] = db.take_executed()[..] else {
+        panic!("expected `untracked` to execute");
+    };
+
+    db.synthetic_write(salsa::Durability::LOW);
+    untracked(&db, file);
+    assert_eq!(
+        db.last_execution_reason(untracked_key),
+        Some(ExecutionReason::Untracked)
+    );
+}
+
+#[test]
+fn cycle_reason() {
+    let db = common::ExecutedKeysDatabase::default();
+    db.record_execution_reasons(true);
+    let file = File::new(&db, "abc".to_string());
+
+    assert_eq!(count(&db, file), 3);
+    let [count_key] = db.take_executed()[..] else {
+        panic!("expected `count` to execute");
+    };
+    assert_eq!(
+        db.last_execution_reason(count_key),
+        Some(ExecutionReason::Cycle)
+    );
+}
+
+#[test]
+fn disabled() {
+    let db = common::ExecutedKeysDatabase::default();
+    let file = File::new(&db, "a".to_string());
+
+    is_long(&db, file);
+    for key in db.take_executed() {
+        assert_eq!(db.last_execution_reason(key), None);
+    }
+
+    // Disabling recording discards the recorded reasons.
+    db.record_execution_reasons(true);
+    let other = File::new(&db, "b".to_string());
+    is_long(&db, other);
+    let executed = db.take_executed();
+    assert!(db.last_execution_reason(executed[0]).is_some());
+    db.record_execution_reasons(false);
+    for key in executed {
+        assert_eq!(db.last_execution_reason(key), None);
+    }
+}
@@ -0,0 +1,154 @@
+#![cfg(feature = "inventory")]
+
+//! Test that queries reporting external reads are only re-executed
+//! if the external state changed.
+
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use common::LogDatabase;
+use expect_test::expect;
+use salsa::{Database, Durability, ExternalRead, Setter};
+
+#[salsa::input]
+struct Config {
+    name: String,
+}
+
+/// Stands in for state outside of the database.
+static VERSION: AtomicU32 = AtomicU32::new(0);
+
+#[salsa::tracked]
+fn versioned_name(db: &dyn LogDatabase, config: Config) -> String {
+    db.report_external_read(ExternalRead::new("version", |_| {
+        VERSION.load(Ordering::Relaxed)
+    }));
+    db.push_log(format!("versioned_name({:?})", config.name(db)));
+    config.name(db).clone()
+}
+
+#[salsa::tracked]
+fn untracked_name(db: &dyn LogDatabase, config: Config) -> String {
+    db.report_untracked_read();
+    db.push_log(format!("untracked_name({:?})", config.name(db)));
+    config.name(db).clone()
+}
+
+#[test]
+fn external_read() {
+    let mut db = common::LoggerDatabase::default();
+    let config = Config::new(&db, "salsa".to_string());
+
+    assert_eq!(versioned_name(&db, config), "salsa");
+    assert_eq!(untracked_name(&db, config), "salsa");
+    db.assert_logs(expect![[r#"
+        [
+            "versioned_name(\"salsa\")",
+            "untracked_name(\"salsa\")",
+        ]"#]]);
+
+    // The external state didn't change, so only the untracked read re-executes.
+    db.synthetic_write(Durability::LOW);
+    assert_eq!(versioned_name(&db, config), "salsa");
+    assert_eq!(untracked_name(&db, config), "salsa");
+    db.assert_logs(expect![[r#"
+        [
+            "untracked_name(\"salsa\")",
+        ]"#]]);
+
+    // Changes to the external state are noticed in the next revision.
+    VERSION.store(1, Ordering::Relaxed);
+    assert_eq!(versioned_name(&db, config), "salsa");
+    db.assert_logs(expect!["[]"]);
+
+    db.synthetic_write(Durability::LOW);
+    assert_eq!(versioned_name(&db, config), "salsa");
+    db.assert_logs(expect![[r#"
+        [
+            "versioned_name(\"salsa\")",
+        ]"#]]);
+}
+
+static DURABLE_VERSION: AtomicU32 = AtomicU32::new(0);
+
+#[salsa::tracked]
+fn durable_name(db: &dyn LogDatabase, config: Config) -> String {
+    db.report_external_read(
+        ExternalRead::new("durable version", |_| {
+            DURABLE_VERSION.load(Ordering::Relaxed)
+        })
+        .with_durability(Durability::HIGH),
+    );
+    db.push_log(format!("durable_name({:?})", config.name(db)));
+    config.name(db).clone()
+}
+
+#[test]
+fn external_read_durability() {
+    let mut db = common::LoggerDatabase::default();
+    let config = Config::builder("salsa".to_string())
+        .durability(Durability::HIGH)
+        .new(&db);
+
+    assert_eq!(durable_name(&db, config), "salsa");
+    db.assert_logs(expect![[r#"
+        [
+            "durable_name(\"salsa\")",
+        ]"#]]);
+
+    // Only low durability values changed, so the external state isn't re-validated.
+    DURABLE_VERSION.store(1, Ordering::Relaxed);
+    db.synthetic_write(Durability::LOW);
+    assert_eq!(durable_name(&db, config), "salsa");
+    db.assert_logs(expect!["[]"]);
+
+    db.synthetic_write(Durability::HIGH);
+    assert_eq!(durable_name(&db, config), "salsa");
+    db.assert_logs(expect![[r#"
+        [
+            "durable_name(\"salsa\")",
+        ]"#]]);
+}
+
+#[salsa::input]
+struct Counter {
+    value: u32,
+}
+
+static COUNTER_VERSION: AtomicU32 = AtomicU32::new(0);
+
+#[salsa::tracked]
+fn counter_version(db: &dyn LogDatabase, counter: Counter) -> u32 {
+    let value = counter.value(db);
+    db.report_external_read(ExternalRead::new(("counter", value), |_| {
+        COUNTER_VERSION.load(Ordering::Relaxed)
+    }));
+    db.push_log(format!("counter_version({value})"));
+    value
+}
+
+#[test]
+fn unreferenced_keys_are_freed() {
+    let mut db = common::LoggerDatabase::default();
+    let counter = Counter::new(&db, 0);
+
+    // Every value reports a new key, the keys of earlier values are freed and reused over time.
+    for value in 0..500 {
+        counter.set_value(&mut db).to(value);
+        assert_eq!(counter_version(&db, counter), value);
+    }
+    db.clear_logs();
+
+    db.synthetic_write(Durability::LOW);
+    assert_eq!(counter_version(&db, counter), 499);
+    db.assert_logs(expect!["[]"]);
+
+    COUNTER_VERSION.store(1, Ordering::Relaxed);
+    db.synthetic_write(Durability::LOW);
+    assert_eq!(counter_version(&db, counter), 499);
+    db.assert_logs(expect![[r#"
+        [
+            "counter_version(499)",
+        ]"#]]);
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "inventory")]
+
+//! Test that deleting an input discards the memos keyed on it,
+//! invalidates its dependents and allows its slot to be reused.
+
+mod common;
+
+use common::LogDatabase;
+use expect_test::expect;
+use salsa::{Database, Setter};
+
+#[salsa::input(debug)]
+struct File {
+    text: String,
+}
+
+#[salsa::input(debug)]
+struct Workspace {
+    files: Vec<File>,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Database, file: File) -> usize {
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn total_length(db: &dyn Database, workspace: Workspace) -> usize {
+    workspace
+        .files(db)
+        .into_iter()
+        .map(|file| length(db, file))
+        .sum()
+}
+
+#[salsa::tracked]
+fn first_text_length(db: &dyn Database, workspace: Workspace) -> usize {
+    workspace.files(db)[0].text(db).len()
+}
+
+#[test]
+fn delete_discards_memos() {
+    let mut db = common::DiscardLoggerDatabase::default();
+    let a = File::new(&db, "a".to_string());
+    let b = File::new(&db, "bb".to_string());
+    let workspace = Workspace::new(&db, vec![a, b]);
+
+    assert_eq!(total_length(&db, workspace), 3);
+
+    workspace.set_files(&mut db).to(vec![b]);
+    a.delete(&mut db);
+
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(DidDiscard { key: DatabaseKeyIndex(IngredientIndex(0), Id(0)) })",
+            "salsa_event(DidDiscard { key: DatabaseKeyIndex(IngredientIndex(5), Id(0)) })",
+        ]"#]]);
+
+    assert_eq!(total_length(&db, workspace), 2);
+}
+
+#[test]
+fn deleted_slot_is_reused() {
+    let mut db = salsa::DatabaseImpl::new();
+    let a = File::new(&db, "a".to_string());
+    a.delete(&mut db);
+
+    let b = File::new(&db, "b".to_string());
+    assert_eq!(format!("{:?}", salsa::plumbing::AsId::as_id(&b)), "Id(0g1)");
+    assert_eq!(length(&db, b), 1);
+}
+
+#[test]
+#[should_panic(expected = "attempted to access input `File(Id(0))`, which has been deleted")]
+fn read_deleted_input() {
+    let mut db = salsa::DatabaseImpl::new();
+    let a = File::new(&db, "a".to_string());
+    a.delete(&mut db);
+
+    a.text(&db);
+}
+
+#[test]
+#[should_panic(expected = "attempted to access input `File(Id(0))`, which has been deleted")]
+fn read_reused_input() {
+    let mut db = salsa::DatabaseImpl::new();
+    let a = File::new(&db, "a".to_string());
+    a.delete(&mut db);
+    File::new(&db, "b".to_string());
+
+    a.text(&db);
+}
+
+#[test]
+#[should_panic(expected = "attempted to access input `File(Id(0))`, which has been deleted")]
+fn dependents_observe_deletion() {
+    let mut db = salsa::DatabaseImpl::new();
+    let a = File::new(&db, "a".to_string());
+    let workspace = Workspace::new(&db, vec![a]);
+
+    assert_eq!(first_text_length(&db, workspace), 1);
+
+    // The memo must not be reused, as the input it read is gone.
+    a.delete(&mut db);
+    first_text_length(&db, workspace);
+}
+
+#[test]
+fn delete_twice() {
+    let mut db = salsa::DatabaseImpl::new();
+    let a = File::new(&db, "a".to_string());
+    a.delete(&mut db);
+
+    let revision = salsa::plumbing::current_revision(&db);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| a.delete(&mut db)));
+    let payload = result.expect_err("deleting an input twice should panic");
+    assert_eq!(
+        payload.downcast_ref::<String>().map(String::as_str),
+        Some("attempted to access input `File(Id(0))`, which has been deleted")
+    );
+
+    // The failed deletion doesn't start a new revision.
+    assert_eq!(salsa::plumbing::current_revision(&db), revision);
+}
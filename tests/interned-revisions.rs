@@ -61,6 +61,8 @@ fn test_intern_new() {
             "WillExecute { database_key: function(Id(0)) }",
             "DidInternValue { key: Interned(Id(400)), revision: R1 }",
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: Input.field1(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: function(Id(0)) }",
             "DidInternValue { key: Interned(Id(401)), revision: R2 }",
@@ -95,6 +97,8 @@ fn test_reintern() {
     db.assert_logs(expect![[r#"
         [
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: Input.field1(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: function(Id(0)) }",
             "DidValidateInternedValue { key: Interned(Id(400)), revision: R2 }",
@@ -129,6 +133,8 @@ fn test_durability() {
             "WillExecute { database_key: function(Id(0)) }",
             "DidInternValue { key: Interned(Id(400)), revision: R1 }",
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: Input.field1(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "DidValidateMemoizedValue { database_key: function(Id(0)) }",
         ]"#]]);
@@ -200,38 +206,56 @@ fn test_reuse() {
             "WillExecute { database_key: function(Id(0)) }",
             "DidInternValue { key: Interned(Id(400)), revision: R1 }",
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: Input.field1(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: function(Id(0)) }",
             "DidInternValue { key: Interned(Id(401)), revision: R2 }",
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R3 }",
+            "DidSetInputField { database_key: Input.field1(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: function(Id(0)) }",
             "DidInternValue { key: Interned(Id(402)), revision: R3 }",
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R4 }",
+            "DidSetInputField { database_key: Input.field1(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: function(Id(0)) }",
             "DidReuseInternedValue { key: Interned(Id(400g1)), revision: R4 }",
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R5 }",
+            "DidSetInputField { database_key: Input.field1(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: function(Id(0)) }",
             "DidReuseInternedValue { key: Interned(Id(401g1)), revision: R5 }",
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R6 }",
+            "DidSetInputField { database_key: Input.field1(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: function(Id(0)) }",
             "DidReuseInternedValue { key: Interned(Id(402g1)), revision: R6 }",
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R7 }",
+            "DidSetInputField { database_key: Input.field1(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: function(Id(0)) }",
             "DidReuseInternedValue { key: Interned(Id(400g2)), revision: R7 }",
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R8 }",
+            "DidSetInputField { database_key: Input.field1(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: function(Id(0)) }",
             "DidReuseInternedValue { key: Interned(Id(401g2)), revision: R8 }",
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R9 }",
+            "DidSetInputField { database_key: Input.field1(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: function(Id(0)) }",
             "DidReuseInternedValue { key: Interned(Id(402g2)), revision: R9 }",
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R10 }",
+            "DidSetInputField { database_key: Input.field1(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: function(Id(0)) }",
             "DidReuseInternedValue { key: Interned(Id(400g3)), revision: R10 }",
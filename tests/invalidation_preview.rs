@@ -0,0 +1,65 @@
+#![cfg(feature = "inventory")]
+
+//! Test that the queries affected by a prospective input change can be previewed.
+
+mod common;
+
+use salsa::{Database, DatabaseExt, Durability};
+
+#[salsa::input]
+struct Manifest {
+    version: u32,
+}
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn is_stable(db: &dyn Database, manifest: Manifest) -> bool {
+    manifest.version(db) >= 1
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Database, file: File) -> usize {
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn is_long(db: &dyn Database, file: File) -> bool {
+    length(db, file) > 2
+}
+
+#[test]
+fn preview_invalidation() {
+    let db = common::ExecutedKeysDatabase::default();
+    let manifest = Manifest::builder(1)
+        .version_durability(Durability::HIGH)
+        .new(&db);
+    let file = File::new(&db, "a".to_string());
+
+    is_stable(&db, manifest);
+    is_long(&db, file);
+    let [is_stable_key, is_long_key, length_key] = db.take_executed()[..] else {
+        panic!("expected `is_stable`, `is_long` and `length` to execute");
+    };
+
+    // Queries of a higher durability than the change are skipped.
+    let preview = db.preview_invalidation(&[file.text_database_key(&db)]);
+    assert_eq!(preview.affected(), [length_key, is_long_key]);
+    assert_eq!(preview.revalidated(), []);
+    assert_eq!(preview.skipped(), [is_stable_key]);
+
+    // Queries of a lower durability have to be revalidated.
+    let preview = db.preview_invalidation(&[manifest.version_database_key(&db)]);
+    assert_eq!(preview.affected(), [is_stable_key]);
+    assert_eq!(preview.revalidated().len(), 2);
+    assert!(preview.revalidated().contains(&length_key));
+    assert!(preview.revalidated().contains(&is_long_key));
+    assert_eq!(preview.skipped(), []);
+
+    // Nothing changed.
+    assert!(is_stable(&db, manifest));
+    assert_eq!(db.take_executed(), []);
+}
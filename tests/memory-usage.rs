@@ -93,7 +93,7 @@ fn test() {
             IngredientInfo {
                 debug_name: "MyInput",
                 count: 3,
-                size_of_metadata: 96,
+                size_of_metadata: 120,
                 size_of_fields: 72,
                 heap_size_of_fields: Some(
                     450,
@@ -0,0 +1,213 @@
+#![cfg(all(feature = "inventory", feature = "rayon", feature = "accumulator"))]
+
+//! Test that the reads, tracked structs and accumulated values of closures run
+//! with `salsa::par_map` and `salsa::join` are recorded in the calling query.
+
+mod common;
+
+use common::{LogDatabase, LoggerDatabase};
+use salsa::plumbing::{AsId, FromId};
+use salsa::{Accumulator, Id, Setter};
+
+#[salsa::input(debug)]
+struct File {
+    text: String,
+}
+
+#[salsa::input(debug)]
+struct Workspace {
+    files: Vec<File>,
+}
+
+#[salsa::tracked(debug)]
+struct Word<'db> {
+    text: String,
+}
+
+#[salsa::accumulator]
+#[derive(Debug)]
+struct Diagnostic(String);
+
+#[salsa::tracked]
+fn length(db: &dyn LogDatabase, file: File) -> usize {
+    db.push_log(format!("length({})", file.text(db)));
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn lengths(db: &dyn LogDatabase, workspace: Workspace) -> Vec<usize> {
+    db.push_log("lengths".to_string());
+    salsa::par_map(db, workspace.files(db), length)
+}
+
+#[salsa::tracked]
+fn texts_untracked(db: &dyn LogDatabase, workspace: Workspace) -> Vec<String> {
+    db.push_log("texts_untracked".to_string());
+    salsa::par_map(db, workspace.files(db), |db, file| file.text(db))
+}
+
+#[salsa::tracked]
+fn words(db: &dyn LogDatabase, workspace: Workspace) -> Vec<Id> {
+    // Tracked structs can't outlive the forked database handle, return their ids instead.
+    salsa::par_map(db, workspace.files(db), |db, file| {
+        Word::new(db, file.text(db)).as_id()
+    })
+}
+
+fn word_texts(db: &dyn LogDatabase, words: &[Id]) -> Vec<String> {
+    words
+        .iter()
+        .map(|&word| Word::from_id(word).text(db))
+        .collect()
+}
+
+#[salsa::tracked]
+fn check(db: &dyn LogDatabase, workspace: Workspace) {
+    let _: Vec<()> = salsa::par_map(db, workspace.files(db), |db, file| {
+        Diagnostic(format!("checked {}", file.text(db))).accumulate(db);
+    });
+}
+
+#[salsa::tracked]
+fn first_and_last(db: &dyn LogDatabase, workspace: Workspace) -> (String, String) {
+    let files = workspace.files(db);
+    salsa::join(
+        db,
+        |db| files[0].text(db),
+        |db| files[files.len() - 1].text(db),
+    )
+}
+
+#[salsa::tracked]
+fn panics(db: &dyn LogDatabase, workspace: Workspace) -> Vec<usize> {
+    salsa::par_map(db, workspace.files(db), |db, file| {
+        if file.text(db).is_empty() {
+            panic!("empty file");
+        }
+        file.text(db).len()
+    })
+}
+
+#[salsa::tracked]
+fn reenters(db: &dyn LogDatabase, workspace: Workspace) -> Vec<usize> {
+    salsa::par_map(db, workspace.files(db), |db, _| {
+        reenters(db, workspace).len()
+    })
+}
+
+fn workspace(db: &LoggerDatabase, texts: &[&str]) -> (Workspace, Vec<File>) {
+    let files: Vec<_> = texts
+        .iter()
+        .map(|text| File::new(db, text.to_string()))
+        .collect();
+    (Workspace::new(db, files.clone()), files)
+}
+
+#[test]
+fn par_map_records_reads() {
+    let mut db = LoggerDatabase::default();
+    let (workspace, files) = workspace(&db, &["a", "bb", "ccc"]);
+
+    assert_eq!(texts_untracked(&db, workspace), ["a", "bb", "ccc"]);
+    db.assert_logs_len(1);
+
+    // The query read the text of the file in a branch.
+    files[1].set_text(&mut db).to("bbbb".to_string());
+    assert_eq!(texts_untracked(&db, workspace), ["a", "bbbb", "ccc"]);
+    db.assert_logs_len(1);
+}
+
+#[test]
+fn par_map_reuses_memos() {
+    let mut db = LoggerDatabase::default();
+    let (workspace, files) = workspace(&db, &["a", "bb", "ccc"]);
+
+    assert_eq!(lengths(&db, workspace), [1, 2, 3]);
+    db.assert_logs_len(4);
+
+    files[1].set_text(&mut db).to("bbbb".to_string());
+    assert_eq!(lengths(&db, workspace), [1, 4, 3]);
+    db.assert_logs(expect_test::expect![[r#"
+        [
+            "length(bbbb)",
+            "lengths",
+        ]"#]]);
+
+    // The length of the file didn't change, so `lengths` is backdated.
+    files[0].set_text(&mut db).to("x".to_string());
+    assert_eq!(lengths(&db, workspace), [1, 4, 3]);
+    db.assert_logs(expect_test::expect![[r#"
+        [
+            "length(x)",
+        ]"#]]);
+}
+
+#[test]
+fn par_map_tracked_structs() {
+    let mut db = LoggerDatabase::default();
+    let (workspace, files) = workspace(&db, &["a", "b", "a"]);
+
+    let first = words(&db, workspace);
+    assert_eq!(word_texts(&db, &first), ["a", "b", "a"]);
+    // Structs with the same fields created in different branches are distinct.
+    assert_ne!(first[0], first[2]);
+
+    // Re-executing the query re-uses the structs of the previous execution.
+    files[1].set_text(&mut db).to("c".to_string());
+    let second = words(&db, workspace);
+    assert_eq!(word_texts(&db, &second), ["a", "c", "a"]);
+    assert_eq!(first[0], second[0]);
+    assert_eq!(first[2], second[2]);
+}
+
+#[test]
+fn par_map_accumulated() {
+    let db = LoggerDatabase::default();
+    let (workspace, _) = workspace(&db, &["a", "b", "c"]);
+
+    let diagnostics = check::accumulated::<Diagnostic>(&db, workspace);
+    let diagnostics: Vec<_> = diagnostics.iter().map(|d| d.0.as_str()).collect();
+    assert_eq!(diagnostics, ["checked a", "checked b", "checked c"]);
+}
+
+#[test]
+fn join_records_reads() {
+    let mut db = LoggerDatabase::default();
+    let (workspace, files) = workspace(&db, &["a", "b", "c"]);
+
+    assert_eq!(
+        first_and_last(&db, workspace),
+        ("a".to_string(), "c".to_string())
+    );
+
+    files[2].set_text(&mut db).to("d".to_string());
+    assert_eq!(
+        first_and_last(&db, workspace),
+        ("a".to_string(), "d".to_string())
+    );
+}
+
+#[test]
+fn par_map_propagates_panics() {
+    let db = LoggerDatabase::default();
+    let (workspace, _) = workspace(&db, &["a", "", "c"]);
+
+    let panic = std::panic::catch_unwind(|| panics(&db, workspace)).unwrap_err();
+    assert_eq!(panic.downcast_ref::<&str>(), Some(&"empty file"));
+
+    // The database is still usable afterwards.
+    assert_eq!(lengths(&db, workspace), [1, 0, 1]);
+}
+
+#[test]
+fn par_map_panics_instead_of_deadlocking() {
+    let db = LoggerDatabase::default();
+    let (workspace, _) = workspace(&db, &["a", "b"]);
+
+    let panic = std::panic::catch_unwind(|| reenters(&db, workspace)).unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(
+        message.starts_with("a branch of `par_map` or `join` depends on reenters("),
+        "{message}"
+    );
+}
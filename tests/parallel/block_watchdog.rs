@@ -0,0 +1,134 @@
+// The watchdog relies on timeouts, which shuttle doesn't model.
+#![cfg(not(feature = "shuttle"))]
+
+//! Test that the block watchdog reports the chain of threads a thread is waiting for
+//! and that `wait_graph` lists the blocked threads.
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use salsa::{BlockedThread, Database, DatabaseExt, EventKind, Storage};
+
+use crate::signal::Signal;
+
+#[salsa::db]
+#[derive(Clone)]
+struct WatchdogDb {
+    storage: Storage<Self>,
+    signal: Arc<Signal>,
+    chains: Arc<Mutex<Vec<Vec<BlockedThread>>>>,
+}
+
+#[salsa::db]
+impl Database for WatchdogDb {}
+
+#[salsa::db]
+trait SignalDatabase: Database {
+    fn signal(&self) -> &Signal;
+}
+
+#[salsa::db]
+impl SignalDatabase for WatchdogDb {
+    fn signal(&self) -> &Signal {
+        &self.signal
+    }
+}
+
+impl WatchdogDb {
+    fn new(threshold: Duration) -> Self {
+        let signal = <Arc<Signal>>::default();
+        let chains = <Arc<Mutex<Vec<_>>>>::default();
+        Self {
+            storage: Storage::builder()
+                .block_watchdog(threshold)
+                .event_callback(Box::new({
+                    let signal = signal.clone();
+                    let chains = chains.clone();
+                    move |event| {
+                        if let EventKind::DidExceedBlockThreshold { wait_chain, .. } = event.kind {
+                            // Stage 2 once thread 2 is reported, stage 3 for thread 3.
+                            let stage = wait_chain.len() + 1;
+                            chains.lock().unwrap().push(wait_chain);
+                            signal.signal(stage);
+                        }
+                    }
+                }))
+                .build(),
+            signal,
+            chains,
+        }
+    }
+}
+
+#[salsa::tracked]
+fn slow(db: &dyn SignalDatabase) -> u32 {
+    // Signal that thread 1 has started computing `slow`.
+    db.signal().signal(1);
+    // Wait until the watchdog reported thread 3.
+    db.signal().wait_for(3);
+    1
+}
+
+#[salsa::tracked]
+fn outer(db: &dyn SignalDatabase) -> u32 {
+    slow(db) + 1
+}
+
+#[test]
+fn execute() {
+    let db = WatchdogDb::new(Duration::from_millis(10));
+    let (db_t1, db_t2, db_t3) = (db.clone(), db.clone(), db.clone());
+
+    // Thread 1: computes `slow`, until thread 3 has been reported.
+    let t1 = std::thread::spawn(move || slow(&db_t1));
+    db.signal.wait_for(1);
+
+    // Thread 2: computes `outer`, which blocks on `slow` in thread 1.
+    let t2 = std::thread::spawn(move || outer(&db_t2));
+    db.signal.wait_for(2);
+
+    let graph = db.wait_graph();
+    let [blocked] = graph.blocked_threads() else {
+        panic!("expected one blocked thread: {graph:?}");
+    };
+    assert_eq!(blocked.thread_id(), t2.thread().id());
+    assert_eq!(blocked.blocked_on(), t1.thread().id());
+    assert_eq!(
+        db.ingredient_debug_name(blocked.database_key().ingredient_index()),
+        "slow"
+    );
+    assert!(blocked.waited() >= Duration::from_millis(10));
+    assert!(graph.transferred_locks().is_empty());
+
+    // Thread 3: blocks on `outer` in thread 2.
+    let t3 = std::thread::spawn(move || outer(&db_t3));
+
+    assert_eq!(t1.join().unwrap(), 1);
+    assert_eq!(t2.join().unwrap(), 2);
+    assert_eq!(t3.join().unwrap(), 2);
+    assert!(db.wait_graph().blocked_threads().is_empty());
+
+    let chains = db.chains.lock().unwrap();
+    let [chain_t2, chain_t3] = &chains[..] else {
+        panic!("expected two reports: {chains:?}");
+    };
+    assert_eq!(chain_t2.len(), 1);
+    assert_eq!(chain_t2[0].thread_id(), chain_t3[1].thread_id());
+
+    let [t3_on_t2, t2_on_t1] = &chain_t3[..] else {
+        panic!("expected a chain of two threads: {chain_t3:?}");
+    };
+    assert_eq!(t3_on_t2.blocked_on(), t2_on_t1.thread_id());
+    assert_eq!(
+        db.ingredient_debug_name(t3_on_t2.database_key().ingredient_index()),
+        "outer"
+    );
+    assert_eq!(
+        db.ingredient_debug_name(t2_on_t1.database_key().ingredient_index()),
+        "slow"
+    );
+
+    // Thread 2 blocked while executing `outer`.
+    let backtrace = t2_on_t1.backtrace().expect("backtrace of thread 2");
+    let backtrace = db.attach(|_| format!("{backtrace}"));
+    assert!(backtrace.contains("outer"), "{backtrace}");
+}
@@ -0,0 +1,91 @@
+// Shuttle doesn't like panics inside of its runtime.
+#![cfg(not(feature = "shuttle"))]
+
+//! Test that the eager scheduler recomputes root queries in the background
+//! and gets out of the way of writes.
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use salsa::{EagerScheduler, Setter};
+
+use crate::setup::{Knobs, KnobsDatabase};
+
+#[salsa::input]
+struct Input {
+    value: u32,
+}
+
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[salsa::tracked]
+fn double(db: &dyn KnobsDatabase, input: Input) -> u32 {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    input.value(db) * 2
+}
+
+#[salsa::tracked]
+fn spin(db: &dyn KnobsDatabase, _input: Input) -> u32 {
+    db.signal(1);
+    loop {
+        db.unwind_if_revision_cancelled();
+        std::thread::yield_now();
+    }
+}
+
+#[test]
+fn recomputes_in_background() {
+    let mut db = Knobs::default();
+    let input = Input::new(&db, 1);
+
+    let mut scheduler = EagerScheduler::new(&db, 2);
+    scheduler.register(move |db: &Knobs| {
+        double(db, input);
+    });
+
+    scheduler.schedule(&db);
+    scheduler.wait();
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 1);
+
+    // Writes schedule the recomputation automatically, scheduling again in
+    // the same revision doesn't start more workers.
+    input.set_value(&mut db).to(2);
+    scheduler.schedule(&db);
+    scheduler.wait();
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 2);
+
+    // The result has been memoized in the background.
+    assert_eq!(double(&db, input), 4);
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn cancelled_by_write() {
+    let mut db = Knobs::default();
+    let input = Input::new(&db, 1);
+
+    let mut scheduler = EagerScheduler::new(&db, 1);
+    scheduler.register(move |db: &Knobs| {
+        spin(db, input);
+    });
+    scheduler.schedule(&db);
+    db.wait_for(1);
+
+    // Blocks until the background thread has been cancelled.
+    input.set_value(&mut db).to(2);
+    assert_eq!(input.value(&db), 2);
+}
+
+#[test]
+fn cancelled_on_drop() {
+    let db = Knobs::default();
+    let input = Input::new(&db, 1);
+
+    let mut scheduler = EagerScheduler::new(&db, 1);
+    scheduler.register(move |db: &Knobs| {
+        spin(db, input);
+    });
+    scheduler.schedule(&db);
+    db.wait_for(1);
+
+    // Blocks until the background thread has been cancelled.
+    drop(scheduler);
+}
@@ -3,6 +3,7 @@
 mod setup;
 mod signal;
 
+mod block_watchdog;
 mod cancellation_token_cycle_nested;
 mod cancellation_token_multi_blocked;
 mod cancellation_token_recomputes;
@@ -18,7 +19,10 @@ mod cycle_nested_three_threads;
 mod cycle_nested_three_threads_changed;
 mod cycle_panic;
 mod cycle_provisional_depending_on_itself;
+mod eager_scheduler;
 mod lru_eviction_cancels_cycle;
+mod panic_propagation;
+mod selective_cancellation;
 
 #[cfg(not(feature = "shuttle"))]
 pub(crate) mod sync {
@@ -0,0 +1,111 @@
+// Shuttle doesn't like panics inside of its runtime.
+#![cfg(not(feature = "shuttle"))]
+
+//! Test that threads blocked on a query that panicked on another thread
+//! receive the original panic, also when blocked transitively.
+use salsa::Cancelled;
+
+use crate::setup::{Knobs, KnobsDatabase};
+
+#[salsa::tracked]
+fn query_a(db: &dyn KnobsDatabase) -> u32 {
+    query_b(db)
+}
+
+#[salsa::tracked]
+fn query_b(db: &dyn KnobsDatabase) -> u32 {
+    // Signal that t1 has started computing query_b
+    db.signal(1);
+    // Wait for t2 and t3 to block
+    db.wait_for(3);
+    panic!("query_b failed")
+}
+
+#[salsa::tracked]
+fn query_c(db: &dyn KnobsDatabase) -> u32 {
+    query_a(db)
+}
+
+#[salsa::tracked]
+fn query_d(db: &dyn KnobsDatabase) -> u32 {
+    // The panic of query_e is caught, it isn't the one query_d unwinds with.
+    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| query_e(db)));
+    // Signal that t1 has started computing query_d
+    db.signal(1);
+    // Wait for t2 to block
+    db.wait_for(2);
+    panic!("query_d failed")
+}
+
+#[salsa::tracked]
+fn query_e(_db: &dyn KnobsDatabase) -> u32 {
+    panic!("query_e failed")
+}
+
+#[test]
+fn execute() {
+    let db = Knobs::default();
+    let db_t1 = db.clone();
+    let db_t2 = db.clone();
+    let db_t3 = db.clone();
+
+    // Thread 1: Computes query_a -> query_b, which panics
+    let t1 = std::thread::spawn(move || query_a(&db_t1));
+    db.wait_for(1);
+
+    // Thread 2: Computes query_c, which blocks on query_a
+    db.signal_on_will_block(2);
+    let t2 = std::thread::spawn(move || query_c(&db_t2));
+    db.wait_for(2);
+
+    // Thread 3: Blocks on query_c
+    db.signal_on_will_block(3);
+    let t3 = std::thread::spawn(move || query_c(&db_t3));
+
+    let r1 = t1.join().unwrap_err();
+    assert_eq!(r1.downcast_ref::<&str>(), Some(&"query_b failed"));
+
+    for result in [t2.join(), t3.join()] {
+        let cancelled = *result.unwrap_err().downcast::<Cancelled>().unwrap();
+        let Cancelled::PropagatedPanic(Some(info)) = cancelled else {
+            panic!("expected a propagated panic, got: {cancelled:?}");
+        };
+        assert_eq!(info.message(), "query_b failed");
+
+        let (database_key, backtrace) = salsa::attach(&db, || {
+            (
+                format!("{:?}", info.database_key()),
+                info.backtrace().to_string(),
+            )
+        });
+        assert!(database_key.starts_with("query_b("), "{database_key}");
+        assert!(backtrace.contains("query_b("), "{backtrace}");
+        assert!(backtrace.contains("query_a("), "{backtrace}");
+    }
+}
+
+#[test]
+fn caught_panic() {
+    let db = Knobs::default();
+    let db_t1 = db.clone();
+    let db_t2 = db.clone();
+
+    // Thread 1: Computes query_d, which catches the panic of query_e and panics itself
+    let t1 = std::thread::spawn(move || query_d(&db_t1));
+    db.wait_for(1);
+
+    // Thread 2: Blocks on query_d
+    db.signal_on_will_block(2);
+    let t2 = std::thread::spawn(move || query_d(&db_t2));
+
+    let r1 = t1.join().unwrap_err();
+    assert_eq!(r1.downcast_ref::<&str>(), Some(&"query_d failed"));
+
+    let cancelled = *t2.join().unwrap_err().downcast::<Cancelled>().unwrap();
+    let Cancelled::PropagatedPanic(Some(info)) = cancelled else {
+        panic!("expected a propagated panic, got: {cancelled:?}");
+    };
+    assert_eq!(info.message(), "query_d failed");
+    let database_key = salsa::attach(&db, || format!("{:?}", info.database_key()));
+    assert!(database_key.starts_with("query_d("), "{database_key}");
+}
@@ -0,0 +1,82 @@
+// Shuttle doesn't like panics inside of its runtime.
+#![cfg(not(feature = "shuttle"))]
+
+//! Test that with selective cancellation, setting an input only cancels
+//! the queries that may depend on it.
+use salsa::{Cancelled, Durability, Setter};
+
+use crate::setup::{Knobs, KnobsDatabase};
+
+#[salsa::input]
+struct Input {
+    value: u32,
+}
+
+#[salsa::tracked]
+fn slow(db: &dyn KnobsDatabase, input: Input) -> u32 {
+    let value = input.value(db);
+    db.signal(1);
+    db.wait_for(2);
+    value + fast(db, input)
+}
+
+#[salsa::tracked]
+fn fast(db: &dyn KnobsDatabase, input: Input) -> u32 {
+    input.value(db)
+}
+
+#[test]
+fn unaffected_query_completes() {
+    let mut db = Knobs::with_selective_cancellation();
+    let library = Input::builder(1).durability(Durability::HIGH).new(&db);
+    let file = Input::new(&db, 1);
+
+    let db2 = db.clone();
+    let t1 = std::thread::spawn(move || slow(&db2, library));
+
+    db.wait_for(1);
+    db.signal_on_did_cancel(2);
+    file.set_value(&mut db).to(2);
+
+    assert_eq!(t1.join().unwrap(), 2);
+}
+
+#[test]
+fn affected_query_is_cancelled() {
+    let mut db = Knobs::with_selective_cancellation();
+    let library = Input::builder(1).durability(Durability::HIGH).new(&db);
+    let file = Input::new(&db, 1);
+
+    let db2 = db.clone();
+    let t1 = std::thread::spawn(move || slow(&db2, file));
+
+    db.wait_for(1);
+    db.signal_on_did_cancel(2);
+    library.set_value(&mut db).to(2);
+
+    let cancelled = *t1.join().unwrap_err().downcast::<Cancelled>().unwrap();
+    assert!(
+        matches!(cancelled, Cancelled::PendingWrite),
+        "{cancelled:?}"
+    );
+}
+
+#[test]
+fn all_queries_cancelled_by_default() {
+    let mut db = Knobs::default();
+    let library = Input::builder(1).durability(Durability::HIGH).new(&db);
+    let file = Input::new(&db, 1);
+
+    let db2 = db.clone();
+    let t1 = std::thread::spawn(move || slow(&db2, library));
+
+    db.wait_for(1);
+    db.signal_on_did_cancel(2);
+    file.set_value(&mut db).to(2);
+
+    let cancelled = *t1.join().unwrap_err().downcast::<Cancelled>().unwrap();
+    assert!(
+        matches!(cancelled, Cancelled::PendingWrite),
+        "{cancelled:?}"
+    );
+}
@@ -62,25 +62,39 @@ impl Clone for Knobs {
 
 impl Default for Knobs {
     fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl Knobs {
+    /// Creates a database that only cancels the queries affected by a write.
+    pub fn with_selective_cancellation() -> Self {
+        Self::new(true)
+    }
+
+    fn new(selective_cancellation: bool) -> Self {
         let signal = <Arc<Signal>>::default();
         let signal_on_will_block = Arc::new(AtomicUsize::new(0));
         let signal_on_did_cancel = Arc::new(AtomicUsize::new(0));
 
         Self {
-            storage: Storage::new(Some(Box::new({
-                let signal = signal.clone();
-                let signal_on_will_block = signal_on_will_block.clone();
-                let signal_on_did_cancel = signal_on_did_cancel.clone();
-                move |event| match event.kind {
-                    salsa::EventKind::WillBlockOn { .. } => {
-                        signal.signal(signal_on_will_block.load(Ordering::Acquire));
-                    }
-                    salsa::EventKind::DidSetCancellationFlag => {
-                        signal.signal(signal_on_did_cancel.load(Ordering::Acquire));
+            storage: Storage::builder()
+                .selective_cancellation(selective_cancellation)
+                .event_callback(Box::new({
+                    let signal = signal.clone();
+                    let signal_on_will_block = signal_on_will_block.clone();
+                    let signal_on_did_cancel = signal_on_did_cancel.clone();
+                    move |event| match event.kind {
+                        salsa::EventKind::WillBlockOn { .. } => {
+                            signal.signal(signal_on_will_block.load(Ordering::Acquire));
+                        }
+                        salsa::EventKind::DidSetCancellationFlag => {
+                            signal.signal(signal_on_did_cancel.load(Ordering::Acquire));
+                        }
+                        _ => {}
                     }
-                    _ => {}
-                }
-            }))),
+                }))
+                .build(),
             signal,
             signal_on_will_block,
             signal_on_did_cancel,
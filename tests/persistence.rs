@@ -72,7 +72,8 @@ fn everything() {
                 ],
                 "fields": [
                   1
-                ]
+                ],
+                "deleted": false
               },
               "2": {
                 "durabilities": [
@@ -83,7 +84,8 @@ fn everything() {
                 ],
                 "fields": [
                   2
-                ]
+                ],
+                "deleted": false
               }
             }
           }
@@ -122,7 +124,8 @@ fn everything() {
                 ],
                 "fields": [
                   1
-                ]
+                ],
+                "deleted": false
               },
               "2": {
                 "durabilities": [
@@ -133,7 +136,8 @@ fn everything() {
                 ],
                 "fields": [
                   2
-                ]
+                ],
+                "deleted": false
               },
               "3": {
                 "durabilities": [
@@ -144,7 +148,8 @@ fn everything() {
                 ],
                 "fields": [
                   1
-                ]
+                ],
+                "deleted": false
               },
               "4": {
                 "durabilities": [
@@ -155,7 +160,8 @@ fn everything() {
                 ],
                 "fields": [
                   2
-                ]
+                ],
+                "deleted": false
               }
             },
             "2": {
@@ -168,7 +174,8 @@ fn everything() {
                 ],
                 "fields": [
                   1
-                ]
+                ],
+                "deleted": false
               }
             },
             "4": {
@@ -355,7 +362,8 @@ fn partial_query() {
                 ],
                 "fields": [
                   0
-                ]
+                ],
+                "deleted": false
               }
             },
             "13": {
@@ -414,6 +422,8 @@ fn partial_query() {
     db.assert_logs(expect![[r#"
         [
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: MyInput.field(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillExecute { database_key: query(Id(0)) }",
             "WillCheckCancellation",
@@ -466,7 +476,8 @@ fn partial_query_interned() {
                 ],
                 "fields": [
                   0
-                ]
+                ],
+                "deleted": false
               }
             },
             "4": {
@@ -84,6 +84,8 @@ fn test_leaked_inputs_ignored() {
     db.assert_logs(expect![[r#"
         [
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: MyInput.field2(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "DidValidateInternedValue { key: counter_field::interned_arguments(Id(800)), revision: R2 }",
             "WillCheckCancellation",
@@ -80,6 +80,8 @@ fn test_leaked_inputs_ignored() {
     db.assert_logs(expect![[r#"
         [
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: MyInput.field2(Id(0)), durability: Durability(0) }",
             "WillCheckCancellation",
             "WillCheckCancellation",
             "DidValidateMemoizedValue { database_key: counter_field(Id(400)) }",
@@ -0,0 +1,89 @@
+#![cfg(feature = "inventory")]
+
+//! Test that the dependency graph of memoized queries can be exported.
+
+mod common;
+
+use expect_test::expect;
+use salsa::{Database, DatabaseExt, Durability, QueryGraphEdgeKind, QueryGraphNodeKind};
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Database, file: File) -> usize {
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn is_long(db: &dyn Database, file: File) -> bool {
+    length(db, file) > 2
+}
+
+#[test]
+fn query_graph() {
+    let db = common::ExecutedKeysDatabase::default();
+    let file = File::builder("a".to_string())
+        .text_durability(Durability::MEDIUM)
+        .new(&db);
+    let other = File::new(&db, "abc".to_string());
+
+    is_long(&db, file);
+    let [is_long_key, length_key] = db.take_executed()[..] else {
+        panic!("expected `is_long` and `length` to execute");
+    };
+    length(&db, other);
+
+    // The graph of a root only contains its transitive dependencies.
+    let graph = db.query_graph(Some(is_long_key));
+    let kinds = graph
+        .nodes()
+        .iter()
+        .map(|node| node.kind())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            QueryGraphNodeKind::Query,
+            QueryGraphNodeKind::Query,
+            QueryGraphNodeKind::InputField
+        ]
+    );
+    assert_eq!(graph.nodes()[0].key(), is_long_key);
+    assert_eq!(graph.nodes()[1].key(), length_key);
+    assert_eq!(graph.nodes()[2].durability(), Some(Durability::MEDIUM));
+    assert!(
+        graph
+            .edges()
+            .iter()
+            .all(|edge| edge.kind() == QueryGraphEdgeKind::Input)
+    );
+
+    expect![[r#"
+        digraph salsa {
+            n0 [label="is_long(Id(0))\ndurability: Durability::MEDIUM\nchanged_at: R1\nverified_at: R1", shape=box];
+            n1 [label="length(Id(0))\ndurability: Durability::MEDIUM\nchanged_at: R1\nverified_at: R1", shape=box];
+            n2 [label="File.text(Id(0))\ndurability: Durability::MEDIUM\nchanged_at: R1", shape=ellipse];
+            n0 -> n1;
+            n1 -> n2;
+        }
+    "#]]
+    .assert_eq(&graph.to_dot());
+
+    // Without a root, the graph contains all memoized queries.
+    let graph = db.query_graph(None);
+    assert_eq!(graph.nodes().len(), 5);
+    assert_eq!(graph.edges().len(), 3);
+}
+
+#[test]
+fn query_graph_json() {
+    let db = salsa::DatabaseImpl::new();
+    let file = File::new(&db, "a\"b".to_string());
+    length(&db, file);
+
+    expect![[r#"{"nodes":[{"id":"3:1","label":"length(Id(0))","kind":"Query","durability":0,"changed_at":1,"verified_at":1},{"id":"1:1","label":"File.text(Id(0))","kind":"InputField","durability":0,"changed_at":1}],"edges":[{"from":"3:1","to":"1:1","kind":"Input"}]}"#]]
+    .assert_eq(&db.query_graph(None).to_json());
+}
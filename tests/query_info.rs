@@ -0,0 +1,57 @@
+#![cfg(feature = "inventory")]
+
+//! Test that the dependency information of memoized values can be inspected.
+
+mod common;
+
+use salsa::{Database, DatabaseExt, Durability, QueryOriginKind};
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Database, file: File) -> usize {
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn is_long(db: &dyn Database, file: File) -> bool {
+    length(db, file) > 2
+}
+
+#[test]
+fn query_info() {
+    let mut db = common::ExecutedKeysDatabase::default();
+    let file = File::builder("a".to_string())
+        .text_durability(Durability::MEDIUM)
+        .new(&db);
+
+    assert!(!is_long(&db, file));
+    let [is_long_key, length_key] = db.take_executed()[..] else {
+        panic!("expected `is_long` and `length` to execute");
+    };
+
+    let info = db.query_info(is_long_key).unwrap();
+    assert_eq!(info.origin(), QueryOriginKind::Derived);
+    assert_eq!(info.assigned_by(), None);
+    assert_eq!(info.inputs(), [length_key]);
+    assert_eq!(info.outputs(), []);
+    assert_eq!(info.durability(), Durability::MEDIUM);
+    assert_eq!(info.cycle_heads(), []);
+    assert!(info.has_value());
+
+    let length_info = db.query_info(length_key).unwrap();
+    let [text_key] = length_info.inputs()[..] else {
+        panic!("expected `length` to read a single input");
+    };
+    assert_eq!(db.query_info(text_key), None);
+
+    // Verifying the memo in a new revision doesn't change it.
+    db.synthetic_write(Durability::LOW);
+    assert!(!is_long(&db, file));
+    let verified = db.query_info(is_long_key).unwrap();
+    assert!(verified.verified_at() > info.verified_at());
+    assert_eq!(verified.changed_at(), info.changed_at());
+}
@@ -0,0 +1,65 @@
+#![cfg(all(feature = "inventory", feature = "salsa_unstable"))]
+
+//! Test that execution and cache statistics are recorded per tracked function.
+
+use salsa::{Database, QueryProfile, Setter};
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Database, file: File) -> usize {
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn is_long(db: &dyn Database, file: File) -> bool {
+    length(db, file) > 2
+}
+
+fn profile_of(db: &dyn Database, name: &str) -> QueryProfile {
+    let mut profiles = db
+        .query_profile()
+        .into_iter()
+        .filter(|profile| profile.debug_name() == name);
+    let profile = profiles.next().unwrap();
+    assert!(profiles.next().is_none());
+    profile
+}
+
+#[test]
+fn query_profile() {
+    let mut db = salsa::DatabaseImpl::new();
+    let file = File::new(&db, "a".to_string());
+
+    // Nothing is recorded until profiling is enabled.
+    is_long(&db, file);
+    assert_eq!(profile_of(&db, "is_long").hits(), 0);
+
+    <dyn Database>::set_profiling(&db, true);
+    file.set_text(&mut db).to("b".to_string());
+    is_long(&db, file);
+    is_long(&db, file);
+
+    let is_long_profile = profile_of(&db, "is_long");
+    assert_eq!(is_long_profile.executions(), 0);
+    assert_eq!(is_long_profile.deep_verifications(), 1);
+    assert_eq!(is_long_profile.hits(), 2);
+    assert_eq!(is_long_profile.backdates(), 0);
+
+    // `length` re-executed, but its value didn't change.
+    let length_profile = profile_of(&db, "length");
+    assert_eq!(length_profile.executions(), 1);
+    assert_eq!(length_profile.deep_verifications(), 1);
+    assert_eq!(length_profile.backdates(), 1);
+    assert!(length_profile.max_execution_time() <= length_profile.total_execution_time());
+
+    <dyn Database>::reset_query_profile(&db);
+    assert_eq!(profile_of(&db, "length").executions(), 0);
+    assert_ne!(
+        length_profile.ingredient_index(),
+        is_long_profile.ingredient_index()
+    );
+}
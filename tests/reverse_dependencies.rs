@@ -0,0 +1,63 @@
+#![cfg(feature = "inventory")]
+
+//! Test that the queries depending on an input can be looked up.
+
+mod common;
+
+use salsa::{Database, DatabaseExt, Setter};
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Database, file: File) -> usize {
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn is_long(db: &dyn Database, file: File) -> bool {
+    length(db, file) > 2
+}
+
+#[test]
+fn reverse_dependencies() {
+    let mut db = common::ExecutedKeysDatabase::default();
+    let file = File::new(&db, "a".to_string());
+    let other = File::new(&db, "b".to_string());
+
+    is_long(&db, file);
+    let [is_long_key, length_key] = db.take_executed()[..] else {
+        panic!("expected `is_long` and `length` to execute");
+    };
+    length(&db, other);
+    let [other_length_key] = db.take_executed()[..] else {
+        panic!("expected `length` to execute");
+    };
+    let text_key = db.query_info(length_key).unwrap().inputs()[0];
+    let other_text_key = db.query_info(other_length_key).unwrap().inputs()[0];
+
+    let dependencies = db.reverse_dependencies();
+    assert_eq!(dependencies.dependents_of(text_key), [length_key]);
+    assert_eq!(dependencies.dependents_of(length_key), [is_long_key]);
+    assert_eq!(dependencies.dependents_of(is_long_key), []);
+    assert_eq!(dependencies.dependents_of(other_length_key), []);
+    assert_eq!(
+        dependencies.transitive_dependents_of(text_key),
+        [length_key, is_long_key]
+    );
+
+    assert_eq!(
+        dependencies.dependents_of(other_text_key),
+        [other_length_key]
+    );
+
+    // The index only contains the memoized queries verified in the current revision.
+    file.set_text(&mut db).to("abc".to_string());
+    is_long(&db, file);
+    let dependencies_after = db.reverse_dependencies();
+    assert!(dependencies_after.revision() > dependencies.revision());
+    assert_eq!(dependencies_after.dependents_of(text_key), [length_key]);
+    assert_eq!(dependencies_after.dependents_of(other_text_key), []);
+}
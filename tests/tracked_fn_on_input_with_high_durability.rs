@@ -47,6 +47,7 @@ fn execute() {
     db.assert_logs(expect![[r#"
         [
             "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
             "WillCheckCancellation",
             "DidValidateMemoizedValue { database_key: tracked_fn(Id(0)) }",
             "WillCheckCancellation",
@@ -0,0 +1,148 @@
+#![cfg(feature = "inventory")]
+
+//! Test that input transactions apply all writes in a single revision
+//! and roll them back on errors and panics.
+
+mod common;
+
+use common::LogDatabase;
+use expect_test::expect;
+use salsa::{DatabaseExt, Setter};
+
+#[salsa::input(debug)]
+struct File {
+    text: String,
+}
+
+#[salsa::input(debug)]
+struct Config {
+    tab_width: u32,
+}
+
+struct NotClone(u32);
+
+#[salsa::input]
+struct Opaque {
+    #[returns(ref)]
+    value: NotClone,
+}
+
+#[salsa::tracked]
+fn rendered(db: &dyn LogDatabase, file: File, config: Config) -> String {
+    db.push_log(format!("rendered({file:?})"));
+    format!("{}:{}", file.text(db), config.tab_width(db))
+}
+
+#[test]
+fn commit_uses_single_revision() {
+    let mut db = common::LoggerDatabase::default();
+    let file = File::new(&db, "a".to_string());
+    let config = Config::new(&db, 4);
+
+    assert_eq!(rendered(&db, file, config), "a:4");
+    db.clear_logs();
+
+    assert_eq!(
+        format!("{:?}", salsa::plumbing::current_revision(&db)),
+        "R1"
+    );
+    let result: Result<(), ()> = db.transaction(|tx| {
+        file.set_text(tx).to("b".to_string());
+        file.set_text(tx).to("c".to_string());
+        config.set_tab_width(tx).to(8);
+        Ok(())
+    });
+    assert!(result.is_ok());
+
+    assert_eq!(
+        format!("{:?}", salsa::plumbing::current_revision(&db)),
+        "R2"
+    );
+    assert_eq!(rendered(&db, file, config), "c:8");
+    db.assert_logs(expect![[r#"
+        [
+            "rendered(File { [salsa id]: Id(0), text: \"c\" })",
+        ]"#]]);
+}
+
+#[test]
+fn commit_cancels_once() {
+    let mut db = common::EventLoggerDatabase::default();
+    let file = File::new(&db, "a".to_string());
+    let config = Config::new(&db, 4);
+    db.clear_logs();
+
+    let result: Result<(), ()> = db.transaction(|tx| {
+        file.set_text(tx).to("b".to_string());
+        config.set_tab_width(tx).to(8);
+        Ok(())
+    });
+    assert!(result.is_ok());
+
+    // Other handles are only cancelled when opening the transaction.
+    db.assert_logs(expect![[r#"
+        [
+            "DidSetCancellationFlag",
+            "DidStartRevision { revision: R2 }",
+            "DidSetInputField { database_key: File.text(Id(0)), durability: Durability(0) }",
+            "DidSetInputField { database_key: Config.tab_width(Id(400)), durability: Durability(0) }",
+        ]"#]]);
+}
+
+#[test]
+fn rollback_on_error() {
+    let mut db = common::LoggerDatabase::default();
+    let file = File::new(&db, "a".to_string());
+    let config = Config::new(&db, 4);
+
+    assert_eq!(rendered(&db, file, config), "a:4");
+    db.clear_logs();
+
+    let result: Result<(), _> = db.transaction(|tx| {
+        file.set_text(tx).to("b".to_string());
+        config.set_tab_width(tx).to(8);
+        Err("nope")
+    });
+    assert_eq!(result, Err("nope"));
+
+    // The previous values are restored and the memo is still valid.
+    assert_eq!(rendered(&db, file, config), "a:4");
+    db.assert_logs(expect!["[]"]);
+}
+
+#[test]
+fn rollback_on_panic_after_read() {
+    let mut db = common::LoggerDatabase::default();
+    let file = File::new(&db, "a".to_string());
+    let config = Config::new(&db, 4);
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _: Result<(), ()> = db.transaction(|tx| {
+            file.set_text(tx).to("b".to_string());
+            assert_eq!(rendered(tx.db(), file, config), "b:4");
+
+            config.set_tab_width(tx).to(8);
+            assert_eq!(rendered(tx.db(), file, config), "b:8");
+
+            panic!("boom");
+        });
+    }));
+    assert!(result.is_err());
+    db.clear_logs();
+
+    // The results computed inside the transaction are not reused.
+    assert_eq!(rendered(&db, file, config), "a:4");
+    db.assert_logs(expect![[r#"
+        [
+            "rendered(File { [salsa id]: Id(0), text: \"a\" })",
+        ]"#]]);
+}
+
+#[test]
+fn non_clone_field_outside_transaction() {
+    let mut db = salsa::DatabaseImpl::new();
+    let opaque = Opaque::new(&db, NotClone(0));
+
+    opaque.set_value(&mut db).to(NotClone(1));
+    assert_eq!(opaque.value(&db).0, 1);
+}
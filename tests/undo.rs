@@ -0,0 +1,139 @@
+#![cfg(feature = "inventory")]
+
+//! Test that input writes can be undone to a checkpoint and that
+//! memoized results are reused afterwards.
+
+mod common;
+
+use common::LogDatabase;
+use expect_test::expect;
+use salsa::{DatabaseExt, Setter};
+
+#[salsa::input(debug)]
+struct File {
+    text: String,
+}
+
+struct NotClone;
+
+#[salsa::input]
+struct Opaque {
+    #[returns(ref)]
+    value: NotClone,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn LogDatabase, file: File) -> usize {
+    db.push_log(format!("length({:?})", file.text(db)));
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn is_long(db: &dyn LogDatabase, file: File) -> bool {
+    db.push_log("is_long".to_string());
+    length(db, file) > 2
+}
+
+#[test]
+fn undo_without_reads_keeps_memos() {
+    let mut db = common::LoggerDatabase::default();
+    let file = File::new(&db, "a".to_string());
+
+    assert!(!is_long(&db, file));
+    db.clear_logs();
+
+    let mut checkpoint = db.checkpoint();
+    file.set_text(&mut checkpoint).to("abc".to_string());
+    file.set_text(&mut checkpoint).to("abcd".to_string());
+    checkpoint.undo();
+
+    assert_eq!(file.text(&db), "a");
+    assert!(!is_long(&db, file));
+    db.assert_logs(expect!["[]"]);
+}
+
+#[test]
+fn undo_after_reads_backdates() {
+    let mut db = common::LoggerDatabase::default();
+    let file = File::new(&db, "a".to_string());
+    let other = File::new(&db, "b".to_string());
+
+    assert!(!is_long(&db, file));
+    db.clear_logs();
+
+    let mut checkpoint = db.checkpoint();
+    file.set_text(&mut checkpoint).to("c".to_string());
+    assert_eq!(length(checkpoint.db(), other), 1);
+    checkpoint.undo();
+    db.clear_logs();
+
+    // The restored value is a new change, but `length` is backdated
+    // so that `is_long` doesn't have to re-execute.
+    assert!(!is_long(&db, file));
+    db.assert_logs(expect![[r#"
+        [
+            "length(\"a\")",
+        ]"#]]);
+}
+
+#[test]
+fn undo_after_reads_of_written_value() {
+    let mut db = common::LoggerDatabase::default();
+    let file = File::new(&db, "a".to_string());
+
+    let mut checkpoint = db.checkpoint();
+    file.set_text(&mut checkpoint).to("abc".to_string());
+    assert!(is_long(checkpoint.db(), file));
+    checkpoint.undo();
+    db.clear_logs();
+
+    assert!(!is_long(&db, file));
+    db.assert_logs(expect![[r#"
+        [
+            "length(\"a\")",
+            "is_long",
+        ]"#]]);
+}
+
+#[test]
+fn undo_to_nested_checkpoints() {
+    let mut db = salsa::DatabaseImpl::new();
+    let file = File::new(&db, "a".to_string());
+
+    let mut first = db.checkpoint();
+    file.set_text(&mut first).to("b".to_string());
+    let mut second = first.checkpoint();
+    file.set_text(&mut second).to("c".to_string());
+
+    second.undo();
+    assert_eq!(file.text(first.db()), "b");
+
+    first.undo();
+    assert_eq!(file.text(&db), "a");
+}
+
+#[test]
+fn release_keeps_writes() {
+    let mut db = salsa::DatabaseImpl::new();
+    let file = File::new(&db, "a".to_string());
+    let opaque = Opaque::new(&db, NotClone);
+
+    let mut first = db.checkpoint();
+    file.set_text(&mut first).to("b".to_string());
+    let mut second = first.checkpoint();
+    file.set_text(&mut second).to("c".to_string());
+    second.release();
+
+    // Undoing the enclosing checkpoint undoes the writes of released nested checkpoints.
+    first.undo();
+    assert_eq!(file.text(&db), "a");
+
+    let mut checkpoint = db.checkpoint();
+    file.set_text(&mut checkpoint).to("d".to_string());
+    drop(checkpoint);
+    assert_eq!(file.text(&db), "d");
+
+    // The journal is discarded with the last checkpoint, so fields that can't be
+    // restored can be set again.
+    opaque.set_value(&mut db).to(NotClone);
+}
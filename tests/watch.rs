@@ -0,0 +1,131 @@
+#![cfg(feature = "inventory")]
+
+//! Test that watch callbacks are only invoked when the
+//! result of the watched query changed.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use common::LogDatabase;
+use expect_test::expect;
+use salsa::{DatabaseExt, Setter};
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn LogDatabase, file: File) -> usize {
+    db.push_log(format!("length({:?})", file.text(db)));
+    file.text(db).len()
+}
+
+#[test]
+fn watch() {
+    let mut db = common::LoggerDatabase::default();
+    let file = File::new(&db, "a".to_string());
+    let other = File::new(&db, "b".to_string());
+
+    let lengths = Arc::new(Mutex::new(Vec::new()));
+    db.watch(|db, file| length(db, file), file, {
+        let lengths = lengths.clone();
+        move |length| lengths.lock().unwrap().push(length)
+    });
+
+    // The first poll reports the initial result.
+    db.poll_watches();
+    db.poll_watches();
+    assert_eq!(*lengths.lock().unwrap(), [1]);
+    db.assert_logs(expect![[r#"
+        [
+            "length(\"a\")",
+        ]"#]]);
+
+    // Writes revalidate the watches. Writes to other inputs don't re-execute the watched query.
+    other.set_text(&mut db).to("bb".to_string());
+    assert_eq!(*lengths.lock().unwrap(), [1]);
+    db.assert_logs(expect!["[]"]);
+
+    // The result is backdated, so the callback isn't invoked.
+    file.set_text(&mut db).to("c".to_string());
+    assert_eq!(*lengths.lock().unwrap(), [1]);
+    db.assert_logs(expect![[r#"
+        [
+            "length(\"c\")",
+        ]"#]]);
+
+    file.set_text(&mut db).to("abc".to_string());
+    assert_eq!(*lengths.lock().unwrap(), [1, 3]);
+    db.assert_logs(expect![[r#"
+        [
+            "length(\"abc\")",
+        ]"#]]);
+}
+
+#[test]
+fn unwatch() {
+    let mut db = common::LoggerDatabase::default();
+    let file = File::new(&db, "a".to_string());
+
+    let lengths = Arc::new(Mutex::new(Vec::new()));
+    let watch = db.watch(|db, file| length(db, file), file, {
+        let lengths = lengths.clone();
+        move |length| lengths.lock().unwrap().push(length)
+    });
+    db.poll_watches();
+
+    db.unwatch(watch);
+    file.set_text(&mut db).to("abc".to_string());
+    assert_eq!(*lengths.lock().unwrap(), [1]);
+}
+
+#[test]
+fn callback_removes_own_watch() {
+    let mut db = common::LoggerDatabase::default();
+    let file = File::new(&db, "a".to_string());
+
+    // The callback removes its watch through another handle, which it drops right away
+    // so that it doesn't block later writes.
+    let unwatch = Arc::new(Mutex::new(None));
+    let lengths = Arc::new(Mutex::new(Vec::new()));
+    let watch = db.watch(|db, file| length(db, file), file, {
+        let unwatch = unwatch.clone();
+        let lengths = lengths.clone();
+        move |length| {
+            lengths.lock().unwrap().push(length);
+            let handle: Option<(common::LoggerDatabase, _)> = unwatch.lock().unwrap().take();
+            if let Some((db, watch)) = handle {
+                db.unwatch(watch);
+            }
+        }
+    });
+    *unwatch.lock().unwrap() = Some((db.clone(), watch));
+    db.poll_watches();
+    assert!(unwatch.lock().unwrap().is_none());
+
+    file.set_text(&mut db).to("abc".to_string());
+    assert_eq!(*lengths.lock().unwrap(), [1]);
+}
+
+#[test]
+fn transaction_revalidates_once() {
+    let mut db = common::LoggerDatabase::default();
+    let file = File::new(&db, "a".to_string());
+
+    let lengths = Arc::new(Mutex::new(Vec::new()));
+    db.watch(|db, file| length(db, file), file, {
+        let lengths = lengths.clone();
+        move |length| lengths.lock().unwrap().push(length)
+    });
+    db.poll_watches();
+
+    db.transaction(|tx| {
+        file.set_text(tx).to("ab".to_string());
+        file.set_text(tx).to("abc".to_string());
+        Ok::<_, ()>(())
+    })
+    .unwrap();
+    assert_eq!(*lengths.lock().unwrap(), [1, 3]);
+}
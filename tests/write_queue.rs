@@ -0,0 +1,220 @@
+#![cfg(feature = "inventory")]
+
+//! Test that writes enqueued on the write queue are applied in a single revision
+//! and that their tickets resolve once the writes are visible.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use salsa::{Database, DatabaseExt, EventFilter, EventKindTag, Setter, WriteError, WriteQueue};
+
+#[salsa::db]
+#[derive(Clone, Default)]
+struct Db {
+    storage: salsa::Storage<Self>,
+}
+
+#[salsa::db]
+impl Database for Db {}
+
+impl Db {
+    fn write_queue(&self) -> WriteQueue<Db> {
+        self.storage.write_queue()
+    }
+}
+
+/// Not `Clone`, to check that applying writes doesn't require copies of the previous values.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct Text(String);
+
+#[salsa::input]
+struct File {
+    #[returns(ref)]
+    text: Text,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Database, file: File) -> usize {
+    file.text(db).0.len()
+}
+
+fn count_revisions(db: &mut Db) -> Arc<AtomicUsize> {
+    let revisions = Arc::new(AtomicUsize::new(0));
+    db.add_event_listener(EventFilter::from(EventKindTag::DidStartRevision), {
+        let revisions = revisions.clone();
+        Box::new(move |_| {
+            revisions.fetch_add(1, Ordering::SeqCst);
+        })
+    });
+    revisions
+}
+
+#[test]
+fn applies_writes_in_one_revision() {
+    let mut db = Db::default();
+    let revisions = count_revisions(&mut db);
+    let a = File::new(&db, Text("a".to_string()));
+    let b = File::new(&db, Text("b".to_string()));
+    assert_eq!(length(&db, a), 1);
+
+    let queue = db.write_queue();
+    let tickets = std::thread::spawn(move || {
+        [
+            queue.push(move |db| {
+                a.set_text(db).to(Text("aa".to_string()));
+            }),
+            queue.push(move |db| {
+                b.set_text(db).to(Text("bbb".to_string()));
+            }),
+        ]
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(db.write_queue().len(), 2);
+    assert_eq!(tickets[0].try_result(), None);
+    assert_eq!(length(&db, a), 1);
+
+    assert_eq!(db.write_queue().apply(&mut db), 2);
+    assert_eq!(revisions.load(Ordering::SeqCst), 1);
+    assert!(db.write_queue().is_empty());
+    for ticket in tickets {
+        assert_eq!(ticket.wait(), Ok(()));
+    }
+    assert_eq!(length(&db, a), 2);
+    assert_eq!(length(&db, b), 3);
+
+    // Nothing to apply.
+    assert_eq!(db.write_queue().apply(&mut db), 0);
+    assert_eq!(revisions.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn queue_is_shared_by_clones() {
+    let mut db = Db::default();
+    let file = File::new(&db, Text("a".to_string()));
+
+    let db2 = db.clone();
+    let ticket = db2.write_queue().push(move |db| {
+        file.set_text(db).to(Text("abc".to_string()));
+    });
+    drop(db2);
+
+    db.write_queue().apply(&mut db);
+    assert_eq!(ticket.wait(), Ok(()));
+    assert_eq!(length(&db, file), 3);
+}
+
+#[test]
+fn ticket_callbacks_and_futures() {
+    let mut db = Db::default();
+    let file = File::new(&db, Text("a".to_string()));
+    let queue = db.write_queue();
+
+    let results = Arc::new(Mutex::new(Vec::new()));
+    queue
+        .push(move |db| {
+            file.set_text(db).to(Text("ab".to_string()));
+        })
+        .on_complete({
+            let results = results.clone();
+            move |result| results.lock().unwrap().push(result)
+        });
+
+    let mut ticket = queue.push(move |db| {
+        file.set_text(db).to(Text("abc".to_string()));
+    });
+    let mut cx = Context::from_waker(Waker::noop());
+    assert_eq!(std::pin::Pin::new(&mut ticket).poll(&mut cx), Poll::Pending);
+
+    queue.apply(&mut db);
+    assert_eq!(*results.lock().unwrap(), [Ok(())]);
+    assert_eq!(
+        std::pin::Pin::new(&mut ticket).poll(&mut cx),
+        Poll::Ready(Ok(()))
+    );
+    assert_eq!(length(&db, file), 3);
+}
+
+#[test]
+fn panicking_write_is_reported() {
+    let mut db = Db::default();
+    let file = File::new(&db, Text("a".to_string()));
+    let queue = db.write_queue();
+
+    let panics = queue.push(|_| panic!("write failed"));
+    let applied = queue.push(move |db| {
+        file.set_text(db).to(Text("ab".to_string()));
+    });
+
+    let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| queue.apply(&mut db)))
+        .unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"write failed"));
+    assert_eq!(panics.wait(), Err(WriteError::Panicked));
+    assert_eq!(applied.wait(), Ok(()));
+    assert_eq!(length(&db, file), 2);
+}
+
+#[test]
+fn panicking_write_is_applied_partially() {
+    let mut db = Db::default();
+    let file = File::new(&db, Text("a".to_string()));
+    let queue = db.write_queue();
+
+    let ticket = queue.push(move |db| {
+        file.set_text(db).to(Text("ab".to_string()));
+        panic!("write failed");
+    });
+
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| queue.apply(&mut db))).unwrap_err();
+    assert_eq!(ticket.wait(), Err(WriteError::Panicked));
+    assert_eq!(length(&db, file), 2);
+}
+
+#[test]
+fn writes_are_applied_before_the_next_write() {
+    let mut db = Db::default();
+    let revisions = count_revisions(&mut db);
+    let a = File::new(&db, Text("a".to_string()));
+    let b = File::new(&db, Text("b".to_string()));
+
+    let ticket = db.write_queue().push(move |db| {
+        a.set_text(db).to(Text("aa".to_string()));
+    });
+    b.set_text(&mut db).to(Text("bbb".to_string()));
+
+    assert_eq!(ticket.wait(), Ok(()));
+    assert_eq!(revisions.load(Ordering::SeqCst), 2);
+    assert_eq!(length(&db, a), 2);
+    assert_eq!(length(&db, b), 3);
+}
+
+#[test]
+fn writes_are_applied_before_transactions() {
+    let mut db = Db::default();
+    let file = File::new(&db, Text("a".to_string()));
+
+    let ticket = db.write_queue().push(move |db| {
+        file.set_text(db).to(Text("ab".to_string()));
+    });
+    // Rolling back the transaction doesn't undo the queued write.
+    let result = db.transaction(|_| Err::<(), ()>(()));
+    assert_eq!(result, Err(()));
+
+    assert_eq!(ticket.wait(), Ok(()));
+    assert_eq!(length(&db, file), 2);
+}
+
+#[test]
+fn dropped_writes_are_discarded() {
+    let db = Db::default();
+    let file = File::new(&db, Text("a".to_string()));
+
+    let ticket = db.write_queue().push(move |db| {
+        file.set_text(db).to(Text("ab".to_string()));
+    });
+    drop(db);
+
+    assert_eq!(ticket.wait(), Err(WriteError::Discarded));
+}